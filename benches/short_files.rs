@@ -0,0 +1,77 @@
+//! Mide el procesamiento por lotes de muchos archivos cortos.
+//!
+//! Con archivos de un segundo, calcular los coeficientes de la ventana en cada espectrograma
+//! pesa frente a la FFT; `cached_window` los calcula una vez por tipo y tamaño. El grupo mide
+//! el lote completo con `fingerprint_file` y, por separado, el coste de obtener la ventana con
+//! y sin la caché. Los WAV se generan al arrancar en un directorio temporal.
+//!
+//! `cargo bench --bench short_files`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use sonora::{cached_window, fingerprint_file, window, FingerprintConfig};
+
+const SAMPLE_RATE: u32 = 11025;
+const FILES: usize = 100;
+
+/// Escribe un WAV mono de 16 bits de un segundo con un tono que depende de `index`.
+fn write_clip(path: &Path, index: usize) -> std::io::Result<()> {
+    let note = 220.0 * 2f32.powf((index % 24) as f32 / 12.0);
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let sample = 0.4 * (2.0 * std::f32::consts::PI * note * t).sin();
+            (sample * 20000.0) as i16
+        })
+        .collect();
+    common::write_wav(path, 1, SAMPLE_RATE, &samples)
+}
+
+fn short_files(c: &mut Criterion) {
+    let dir: PathBuf = std::env::temp_dir().join("sonora_bench_short_files");
+    std::fs::create_dir_all(&dir).expect("no se pudo crear el directorio de entrada");
+    let paths: Vec<String> = (0..FILES)
+        .map(|index| {
+            let path = dir.join(format!("{index}.wav"));
+            write_clip(&path, index).expect("no se pudo generar un WAV de entrada");
+            path.to_string_lossy().into_owned()
+        })
+        .collect();
+    let config = FingerprintConfig::default();
+
+    let mut group = c.benchmark_group("short_files");
+    group.throughput(Throughput::Elements(FILES as u64));
+    group.bench_function("fingerprint_file", |b| {
+        b.iter(|| {
+            paths
+                .iter()
+                .map(|path| fingerprint_file(path, &config).unwrap().len())
+                .sum::<usize>()
+        })
+    });
+    // Lo que cuesta la ventana de todo el lote con y sin la caché.
+    group.bench_function("window", |b| {
+        b.iter(|| {
+            (0..FILES)
+                .map(|_| window(config.window_fn, black_box(config.window_size)).len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("cached_window", |b| {
+        b.iter(|| {
+            (0..FILES)
+                .map(|_| cached_window(config.window_fn, black_box(config.window_size)).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, short_files);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+//! Compara los motores de huellas sobre un WAV generado.
+//!
+//! Cada motor por bandas ([`Engine`]) se mide de principio a fin con el mismo código de
+//! decodificación y de hash, junto al pipeline de landmarks, como funciones de un grupo de
+//! criterion; el rendimiento se informa en tramas de audio por segundo. El WAV de entrada se
+//! genera al arrancar, así que no hace falta ningún archivo de datos.
+//!
+//! `cargo bench --bench strategies` mide todos; `cargo bench --bench strategies -- simd` solo
+//! los motores cuyo nombre contiene `simd`.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use sonora::{
+    band_fingerprints, fingerprint_file, Engine, FileSource, FingerprintConfig, HashEntry,
+};
+
+const SAMPLE_RATE: u32 = 44100;
+const SECONDS: usize = 60;
+/// Muestras de criterion por función, el mínimo que admite.
+const SAMPLES: usize = 10;
+
+/// Escribe un WAV estéreo de 16 bits con una melodía de senos y algo de ruido.
+fn write_input(path: &Path) -> std::io::Result<()> {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * SECONDS)
+        .flat_map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            // Una nota nueva cada cuarto de segundo, en una escala cromática desde La 220 Hz.
+            let note = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 4) % 12) as f32 / 12.0);
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let noise = (seed >> 40) as f32 / (1u64 << 24) as f32 - 0.5;
+            let phase = 2.0 * std::f32::consts::PI * note * t;
+            let left = 0.4 * phase.sin() + 0.05 * noise;
+            let right = 0.3 * (1.5 * phase).sin() + 0.05 * noise;
+            [left, right].map(|sample| (sample * 20000.0) as i16)
+        })
+        .collect();
+    common::write_wav(path, 2, SAMPLE_RATE, &samples)
+}
+
+fn strategies(c: &mut Criterion) {
+    let path: PathBuf = std::env::temp_dir().join("sonora_bench_input.wav");
+    write_input(&path).expect("no se pudo generar el WAV de entrada");
+    let input = path.to_str().unwrap();
+    let config = FingerprintConfig::default();
+
+    let mut group = c.benchmark_group("strategies");
+    // Cada iteración procesa todo el WAV: pocas muestras bastan.
+    group
+        .sample_size(SAMPLES)
+        .throughput(Throughput::Elements(SAMPLE_RATE as u64 * SECONDS as u64));
+
+    for engine in Engine::ALL {
+        group.bench_function(engine.name(), |b| {
+            b.iter(|| {
+                let mut count = 0;
+                band_fingerprints(
+                    FileSource::open(input).unwrap(),
+                    &config,
+                    engine,
+                    &mut |entries: &[HashEntry]| {
+                        count += entries.len();
+                        Ok(())
+                    },
+                )
+                .unwrap();
+                count
+            })
+        });
+    }
+    group.bench_function("landmarks", |b| {
+        b.iter(|| fingerprint_file(input, &config).unwrap().len())
+    });
+    group.finish();
+
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, strategies);
+criterion_main!(benches);
@@ -0,0 +1,109 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::sync::mpsc::{channel, Receiver};
+use tokio::task::spawn_blocking;
+
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::source::{AudioSource, FileSource};
+use crate::stream::stream_source;
+
+/// Hashes que pueden esperar en el canal de `stream_source_async` a que los consuma la tarea
+/// asíncrona; acota la memoria cuando el cálculo va por delante.
+const HASH_QUEUE: usize = 1024;
+
+/// Genera huellas de forma perezosa a partir de cualquier [`AudioSource`] sin bloquear el
+/// runtime de tokio.
+///
+/// Es `stream_source` ejecutado en el pool de tareas bloqueantes de tokio: la lectura, la FFT y
+/// el hash no ocupan los hilos del runtime, y cada hash llega por un [`Stream`] en cuanto es
+/// definitivo. Si el flujo se descarta antes del final, el cálculo se detiene en el siguiente
+/// hash. Debe llamarse desde un runtime de tokio.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `AsyncFingerprintStream` - Flujo de hashes en orden de tiempo del ancla.
+pub async fn stream_source_async<S>(source: S, config: &FingerprintConfig) -> AsyncFingerprintStream
+where
+    S: AudioSource + Send + 'static,
+{
+    let (tx, rx) = channel(HASH_QUEUE);
+    let config = config.clone();
+
+    spawn_blocking(move || {
+        for entry in stream_source(source, &config) {
+            if tx.blocking_send(entry).is_err() {
+                return;
+            }
+        }
+    });
+
+    AsyncFingerprintStream { rx }
+}
+
+/// Como `fingerprint_file_stream`, pero sin bloquear el runtime de tokio: el archivo se abre y
+/// se decodifica en el pool de tareas bloqueantes.
+///
+/// # Argumentos
+///
+/// * `path` - Ruta al archivo de audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<AsyncFingerprintStream, SonoraError>` - Flujo de hashes en orden de tiempo del
+///   ancla, o el error si el archivo no se puede abrir o su formato no se reconoce.
+pub async fn fingerprint_file_stream_async(
+    path: &str,
+    config: &FingerprintConfig,
+) -> Result<AsyncFingerprintStream, SonoraError> {
+    let path = path.to_owned();
+    let source = run_blocking(move || FileSource::open(&path)).await?;
+    Ok(stream_source_async(source, config).await)
+}
+
+/// Ejecuta `f` en el pool de tareas bloqueantes de tokio y espera su resultado.
+///
+/// Si `f` entra en pánico, el pánico se propaga a la tarea que espera.
+pub(crate) async fn run_blocking<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    match spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+/// Flujo asíncrono de huellas de `stream_source_async` y `fingerprint_file_stream_async`.
+///
+/// Implementa [`Stream`] y produce cada [`HashEntry`] en el mismo orden que
+/// `FingerprintStream`; `next` sirve para recorrerlo sin importar extensiones de `Stream`.
+#[derive(Debug)]
+pub struct AsyncFingerprintStream {
+    rx: Receiver<HashEntry>,
+}
+
+impl AsyncFingerprintStream {
+    /// Espera el siguiente hash; `None` cuando el audio se ha terminado.
+    pub async fn next(&mut self) -> Option<HashEntry> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for AsyncFingerprintStream {
+    type Item = HashEntry;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<HashEntry>> {
+        self.rx.poll_recv(cx)
+    }
+}
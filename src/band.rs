@@ -0,0 +1,411 @@
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use num_complex::Complex;
+
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+
+/// Límites de las bandas de los motores por bandas.
+///
+/// Cada límite es el bin superior de su banda, incluido; una banda empieza justo después del
+/// límite anterior y la primera en `min_freq`. Los límites determinan tanto la tabla de
+/// `band_lookup` como los campos que empaqueta `band_hash`, uno por banda. Se lee como un
+/// slice de límites.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bands(Vec<usize>);
+
+impl Bands {
+    /// Crea las bandas a partir de sus límites superiores.
+    ///
+    /// # Argumentos
+    ///
+    /// * `edges` - Límites en bins, en orden estrictamente creciente.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Bands, SonoraError>` - Las bandas, o `InvalidConfig` si no hay límites o no
+    ///   son estrictamente crecientes.
+    pub fn new(edges: Vec<usize>) -> Result<Self, SonoraError> {
+        let bands = Self(edges);
+        bands.check()?;
+        Ok(bands)
+    }
+
+    /// Reparte `min_freq..=max_freq` en `count` bandas de anchura casi igual.
+    ///
+    /// # Argumentos
+    ///
+    /// * `min_freq` - Primer bin de la primera banda.
+    /// * `max_freq` - Último bin de la última banda.
+    /// * `count` - Número de bandas.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Bands, SonoraError>` - Las bandas, o `InvalidConfig` si no caben `count`
+    ///   bandas no vacías en el rango.
+    pub fn uniform(min_freq: usize, max_freq: usize, count: usize) -> Result<Self, SonoraError> {
+        let width = (max_freq + 1).saturating_sub(min_freq);
+        if count == 0 || count > width {
+            return Err(SonoraError::InvalidConfig(format!(
+                "no caben {count} bandas entre los bins {min_freq} y {max_freq}"
+            )));
+        }
+        Self::new(
+            (1..=count)
+                .map(|i| min_freq + width * i / count - 1)
+                .collect(),
+        )
+    }
+
+    /// Límites superiores de las bandas.
+    pub fn edges(&self) -> &[usize] {
+        &self.0
+    }
+
+    /// Comprueba que haya límites y sean estrictamente crecientes.
+    pub(crate) fn check(&self) -> Result<(), SonoraError> {
+        if self.0.is_empty() || self.0.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(SonoraError::InvalidConfig(
+                "las bandas deben ser estrictamente crecientes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Comprueba que el último límite no supere `max_freq`: los bins ganadores se guardan en
+    /// tablas de `max_freq + 1` elementos indexadas por límite.
+    pub(crate) fn check_max_freq(&self, max_freq: usize) -> Result<(), SonoraError> {
+        if self.0.last().is_some_and(|&last| last > max_freq) {
+            return Err(SonoraError::InvalidConfig(
+                "el último límite de las bandas no puede superar `max_freq`".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Banda (su límite superior) a la que pertenece un bin; la última recoge lo que la supera.
+    pub fn index(&self, bin: usize) -> Option<usize> {
+        let last = *self.0.last()?;
+        Some(
+            self.0
+                .iter()
+                .copied()
+                .find(|&band| bin <= band)
+                .unwrap_or(last),
+        )
+    }
+
+    /// Tabla de `index` para los bins `0..=max_freq`; sin bandas, cada bin va a `max_freq`.
+    pub fn lookup(&self, max_freq: usize) -> Vec<usize> {
+        (0..=max_freq)
+            .map(|bin| self.index(bin).unwrap_or(max_freq))
+            .collect()
+    }
+
+    /// Empaqueta el bin ganador de cada banda, dividido por `fuz_factor`, con
+    /// `pack_band_fields`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `points` - Bin ganador de cada banda, indexado por el límite superior de la banda.
+    /// * `fuz_factor` - Tolerancia aplicada a cada bin.
+    /// * `max_freq` - Mayor bin posible; fija el ancho de cada campo.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<usize, SonoraError>` - Bins empaquetados, o `InvalidConfig` si `fuz_factor`
+    ///   es 0, alguna banda queda fuera de `points` o los campos no caben en un `usize`.
+    pub fn pack(
+        &self,
+        points: &[usize],
+        fuz_factor: usize,
+        max_freq: usize,
+    ) -> Result<usize, SonoraError> {
+        if fuz_factor == 0 {
+            return Err(SonoraError::InvalidConfig(
+                "la tolerancia de los bins debe ser al menos 1".to_string(),
+            ));
+        }
+        let fields = self
+            .0
+            .iter()
+            .map(|&band| {
+                points
+                    .get(band)
+                    .map(|&bin| bin / fuz_factor)
+                    .ok_or_else(|| {
+                        SonoraError::InvalidConfig(format!("la banda {band} no tiene bin ganador"))
+                    })
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        pack_band_fields(&fields, max_freq / fuz_factor)
+    }
+
+    /// Bits que ocupa el hash de `pack` con estos parámetros.
+    pub fn hash_bits(&self, fuz_factor: usize, max_freq: usize) -> u32 {
+        (usize::BITS - (max_freq / fuz_factor).leading_zeros()) * self.0.len() as u32
+    }
+}
+
+impl Default for Bands {
+    /// Las cinco bandas clásicas: 40, 80, 120, 180 y 300.
+    fn default() -> Self {
+        Self(vec![40, 80, 120, 180, 300])
+    }
+}
+
+impl Deref for Bands {
+    type Target = [usize];
+
+    fn deref(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+impl From<Vec<usize>> for Bands {
+    /// Envuelve los límites sin comprobarlos; `Fingerprinter::builder` los valida al construir.
+    fn from(edges: Vec<usize>) -> Self {
+        Self(edges)
+    }
+}
+
+/// Empaqueta los bins dominantes de todas las bandas en un único entero.
+///
+/// Cada bin se divide por `fuz_factor` y ocupa un campo de bits de ancho fijo, suficiente para
+/// `max_freq / fuz_factor`, de modo que dos bandas nunca se solapan por grande que sea
+/// `max_freq`.
+///
+/// # Argumentos
+///
+/// * `points` - Bin ganador de cada banda, indexado por el límite superior de la banda.
+/// * `config` - Configuración con las bandas y el factor de tolerancia.
+///
+/// # Retorna
+///
+/// * `Result<usize, SonoraError>` - Hash del bloque, o `InvalidConfig` si las bandas no
+///   caben en `points` o en el hash.
+#[inline]
+pub fn band_hash(points: &[usize], config: &FingerprintConfig) -> Result<usize, SonoraError> {
+    config
+        .bands
+        .pack(points, config.fuz_factor, config.max_freq)
+}
+
+/// Empaqueta valores en campos de bits consecutivos, el primero en los bits más bajos.
+///
+/// El ancho de cada campo es el número de bits de `max_value`; los valores mayores se recortan
+/// a `max_value` para que no invadan el campo vecino.
+///
+/// # Argumentos
+///
+/// * `fields` - Valores a empaquetar.
+/// * `max_value` - Mayor valor representable en un campo.
+///
+/// # Retorna
+///
+/// * `Result<usize, SonoraError>` - Valores empaquetados, o `InvalidConfig` si los campos no
+///   caben en un `usize`.
+#[inline]
+pub fn pack_band_fields(fields: &[usize], max_value: usize) -> Result<usize, SonoraError> {
+    let width = usize::BITS - max_value.leading_zeros();
+    if width as usize * fields.len() > usize::BITS as usize {
+        return Err(SonoraError::InvalidConfig(format!(
+            "{} campos de {} bits no caben en un usize",
+            fields.len(),
+            width
+        )));
+    }
+
+    Ok(fields.iter().enumerate().fold(0, |hash, (i, &value)| {
+        hash | (value.min(max_value) << (i as u32 * width))
+    }))
+}
+
+/// Registra un bin como máximo de su banda si supera al actual.
+///
+/// Regla de desempate: a igual magnitud gana el bin de menor frecuencia, sea cual sea el orden
+/// en que se recorran los bins. Una banda sin ningún bin con energía conserva el bin 0.
+///
+/// # Argumentos
+///
+/// * `points` - Bin ganador de cada banda.
+/// * `scores` - Magnitud del bin ganador de cada banda.
+/// * `band` - Banda del bin, tal como la devuelve `band_index`.
+/// * `freq` - Bin candidato.
+/// * `mag` - Magnitud del bin candidato.
+#[inline]
+pub fn update_band_max(
+    points: &mut [usize],
+    scores: &mut [f32],
+    band: usize,
+    freq: usize,
+    mag: f32,
+) {
+    if mag > scores[band] || (mag == scores[band] && freq < points[band]) {
+        points[band] = freq;
+        scores[band] = mag;
+    }
+}
+
+/// Calcula la magnitud al cuadrado de cada bin de la FFT.
+///
+/// En x86_64 procesa cuatro bins por iteración con SSE, que forma parte de la base de la
+/// arquitectura y no necesita detección en tiempo de ejecución; en otras arquitecturas usa
+/// `norm_sqr` bin a bin.
+///
+/// # Argumentos
+///
+/// * `bins` - Bins complejos de la FFT.
+/// * `out` - Destino, de la misma longitud que `bins`.
+#[cfg(target_arch = "x86_64")]
+pub fn bin_magnitudes(bins: &[Complex<f32>], out: &mut [f32]) {
+    use core::arch::x86_64::*;
+
+    let mut bin_groups = bins.chunks_exact(4);
+    let mut out_groups = out.chunks_exact_mut(4);
+    for (group, dst) in (&mut bin_groups).zip(&mut out_groups) {
+        // `Complex<f32>` es repr(C): cuatro bins son ocho f32 contiguos, re0 im0 re1 im1 ...
+        unsafe {
+            let ptr = group.as_ptr() as *const f32;
+            let lo = _mm_loadu_ps(ptr);
+            let hi = _mm_loadu_ps(ptr.add(4));
+            let re = _mm_shuffle_ps::<0b10_00_10_00>(lo, hi);
+            let im = _mm_shuffle_ps::<0b11_01_11_01>(lo, hi);
+            let mag = _mm_add_ps(_mm_mul_ps(re, re), _mm_mul_ps(im, im));
+            _mm_storeu_ps(dst.as_mut_ptr(), mag);
+        }
+    }
+    for (bin, dst) in bin_groups
+        .remainder()
+        .iter()
+        .zip(out_groups.into_remainder())
+    {
+        *dst = bin.norm_sqr();
+    }
+}
+
+/// Calcula la magnitud al cuadrado de cada bin de la FFT.
+///
+/// # Argumentos
+///
+/// * `bins` - Bins complejos de la FFT.
+/// * `out` - Destino, de la misma longitud que `bins`.
+#[cfg(not(target_arch = "x86_64"))]
+pub fn bin_magnitudes(bins: &[Complex<f32>], out: &mut [f32]) {
+    for (bin, dst) in bins.iter().zip(out) {
+        *dst = bin.norm_sqr();
+    }
+}
+
+/// Devuelve la banda (su límite superior) a la que pertenece un bin de frecuencia.
+///
+/// Los límites son inclusivos: con las bandas por defecto el bin 40 pertenece a la banda 40,
+/// el 41 a la 80 y el 300 a la 300, así que al recorrer `min_freq..=max_freq` las cinco bandas
+/// reciben candidatos.
+///
+/// # Argumentos
+///
+/// * `x` - Bin de frecuencia.
+/// * `config` - Configuración con los límites de las bandas.
+///
+/// # Retorna
+///
+/// * `usize` - Límite superior de la banda; la última banda recoge todo lo que la supera.
+#[inline]
+pub fn band_index(x: usize, config: &FingerprintConfig) -> usize {
+    config.bands.index(x).unwrap_or(config.max_freq)
+}
+
+/// Tabla precalculada de `band_index` para los bins `0..=max_freq`.
+pub fn band_lookup(config: &FingerprintConfig) -> Vec<usize> {
+    config.bands.lookup(config.max_freq)
+}
+
+/// Calcula el hash de un bloque ya transformado, común a todos los motores por bandas.
+///
+/// Toma la magnitud al cuadrado de los bins `min_freq..=max_freq` con `bin_magnitudes`, elige
+/// el bin dominante de cada banda con `update_band_max` y los empaqueta con `band_hash`. Que
+/// los motores solo se diferencien en cómo reparten el trabajo entre hilos garantiza que
+/// generen exactamente los mismos hashes.
+///
+/// # Argumentos
+///
+/// * `bins` - Bins complejos de la FFT del bloque, de `chunk_size` elementos.
+/// * `lookup` - Tabla de `band_lookup` para la misma configuración.
+/// * `config` - Configuración con las bandas y el factor de tolerancia.
+///
+/// # Retorna
+///
+/// * `Result<u64, SonoraError>` - Hash del bloque, o `InvalidConfig` si las bandas no caben
+///   en `max_freq` o en el hash.
+pub fn chunk_band_hash(
+    bins: &[Complex<f32>],
+    lookup: &[usize],
+    config: &FingerprintConfig,
+) -> Result<u64, SonoraError> {
+    chunk_band_hash_with(bins, lookup, config, bin_magnitudes)
+}
+
+/// Como `chunk_band_hash`, con otra forma de calcular las magnitudes al cuadrado.
+pub(crate) fn chunk_band_hash_with(
+    bins: &[Complex<f32>],
+    lookup: &[usize],
+    config: &FingerprintConfig,
+    magnitudes: fn(&[Complex<f32>], &mut [f32]),
+) -> Result<u64, SonoraError> {
+    config.bands.check_max_freq(config.max_freq)?;
+    let mut points = vec![0; config.max_freq + 1];
+    let mut scores = vec![0.0; config.max_freq + 1];
+    let max_freq = config.max_freq.min(bins.len().saturating_sub(1));
+    if config.min_freq <= max_freq {
+        let mut mags = vec![0.0; max_freq + 1 - config.min_freq];
+        magnitudes(&bins[config.min_freq..=max_freq], &mut mags);
+        for (freq, &mag) in (config.min_freq..=max_freq).zip(&mags) {
+            update_band_max(&mut points, &mut scores, lookup[freq], freq, mag);
+        }
+    }
+
+    Ok(band_hash(&points, config)? as u64)
+}
+
+/// Número de bloques de `chunk_size` muestras, separados `chunk_hop`, que cubren una señal.
+///
+/// El último bloque puede sobresalir del final de la señal; quien lo procese debe rellenarlo
+/// con ceros hasta `(bloques - 1) * chunk_hop + chunk_size` muestras.
+///
+/// # Argumentos
+///
+/// * `len` - Número de muestras de la señal.
+/// * `config` - Configuración con el tamaño y el salto de los bloques.
+///
+/// # Retorna
+///
+/// * `usize` - Número de bloques; 0 si la señal está vacía.
+///
+/// # Panics
+///
+/// Si `chunk_hop` es 0 o mayor que `chunk_size`.
+pub fn chunk_count(len: usize, config: &FingerprintConfig) -> usize {
+    assert!(
+        config.chunk_hop > 0 && config.chunk_hop <= config.chunk_size,
+        "El salto entre bloques debe estar entre 1 y el tamaño del bloque."
+    );
+    if len == 0 {
+        return 0;
+    }
+
+    1 + len
+        .saturating_sub(config.chunk_size)
+        .div_ceil(config.chunk_hop)
+}
+
+/// Resta la media de la ventana para eliminar el offset de DC antes de la FFT.
+pub fn remove_dc(chunk: &mut [Complex<f32>]) {
+    let mean = chunk.iter().map(|c| c.re).sum::<f32>() / chunk.len() as f32;
+    chunk.iter_mut().for_each(|c| c.re -= mean);
+}
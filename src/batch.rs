@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::config::FingerprintConfig;
+use crate::db::{write_atomically, FingerprintDb};
+use crate::error::SonoraError;
+use crate::fingerprint_file;
+
+/// Extensiones que se intentan decodificar al recorrer un directorio.
+const AUDIO_EXTENSIONS: [&str; 10] = [
+    "wav", "flac", "mp3", "ogg", "oga", "m4a", "mp4", "aac", "mka", "mkv",
+];
+
+/// Archivos que `index_dir` procesa entre dos puntos de control.
+const CHECKPOINT_FILES: usize = 32;
+
+/// Primera línea de los archivos de punto de control.
+const CHECKPOINT_HEADER: &str = "SNCP 1";
+
+/// Resultado de procesar un directorio con `fingerprint_dir` o `index_dir`.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    /// Archivos insertados con el `song_id` que se les asignó, en orden de ruta.
+    pub succeeded: Vec<(u32, PathBuf)>,
+    /// Archivos omitidos con el motivo del fallo.
+    pub failed: Vec<(PathBuf, SonoraError)>,
+    /// Archivos que ya estaban en la base de datos según el punto de control, con su
+    /// `song_id`; solo los rellena `index_dir` al retomar un lote.
+    pub resumed: Vec<(u32, PathBuf)>,
+}
+
+/// Genera las huellas de todos los archivos de audio de un directorio y las inserta en la base
+/// de datos.
+///
+/// Recorre el directorio de forma recursiva y procesa los archivos en paralelo con rayon. Los
+/// `song_id` se asignan en orden de ruta a partir de [`FingerprintDb::next_song_id`], de modo
+/// que repetir el lote sobre el mismo directorio da los mismos identificadores. Un archivo que
+/// no se puede decodificar se avisa con un evento `warn` de `tracing` y se omite sin abortar el
+/// lote.
+///
+/// # Argumentos
+///
+/// * `dir` - Directorio a recorrer.
+/// * `db` - Base de datos donde insertar las huellas.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<BatchSummary, SonoraError>` - Resumen de archivos procesados y fallidos, o el
+///   error de E/S si no se pudo leer el directorio.
+pub fn fingerprint_dir(
+    dir: &Path,
+    db: &mut FingerprintDb,
+    config: &FingerprintConfig,
+) -> Result<BatchSummary, SonoraError> {
+    let mut paths = Vec::new();
+    collect_audio_files(dir, &mut paths)?;
+    paths.sort();
+
+    let mut summary = BatchSummary::default();
+    let mut song_id = db.next_song_id();
+    fingerprint_batch(paths, db, config, &mut song_id, &mut summary);
+
+    Ok(summary)
+}
+
+/// Como `fingerprint_dir`, pero guarda la base de datos en `db_path` a medida que avanza y
+/// deja un punto de control para retomar el lote si se interrumpe.
+///
+/// Los archivos se procesan en grupos; tras cada grupo se guardan la base de datos y el
+/// [`Checkpoint`] junto a ella (en [`Checkpoint::path_for`]), y al terminar el punto de control
+/// se borra. Con `resume`, los archivos que el punto de control da por indexados se omiten y
+/// el lote sigue por el primero pendiente. Un archivo interrumpido a medias se vuelve a
+/// procesar entero: sus landmarks emparejan picos de todo el archivo, así que sus hashes no se
+/// pueden guardar por partes.
+///
+/// # Argumentos
+///
+/// * `dir` - Directorio a recorrer.
+/// * `db_path` - Base de datos donde insertar las huellas; se crea si no existe.
+/// * `config` - Parámetros del pipeline.
+/// * `resume` - Retoma el lote desde el punto de control, si lo hay.
+///
+/// # Retorna
+///
+/// * `Result<BatchSummary, SonoraError>` - Resumen de archivos procesados, fallidos y
+///   retomados, el error de E/S, `InvalidCheckpoint` si el punto de control está dañado o la
+///   base de datos cambió después de escribirlo, o `InvalidConfig` si la base de datos usa otro
+///   `hash_scheme`.
+pub fn index_dir(
+    dir: &Path,
+    db_path: &Path,
+    config: &FingerprintConfig,
+    resume: bool,
+) -> Result<BatchSummary, SonoraError> {
+    let mut db = if db_path.exists() {
+        let db = FingerprintDb::load(db_path)?;
+        db.check_hash_scheme(config)?;
+        db
+    } else {
+        FingerprintDb::with_hash_scheme(config.hash_scheme)
+    };
+    let checkpoint_path = Checkpoint::path_for(db_path);
+    let mut checkpoint = match Checkpoint::load(&checkpoint_path) {
+        Ok(checkpoint) if resume => {
+            if checkpoint.next_song_id != db.next_song_id() {
+                return Err(SonoraError::InvalidCheckpoint);
+            }
+            checkpoint
+        }
+        Err(SonoraError::Io(e)) if e.kind() == ErrorKind::NotFound => Checkpoint::default(),
+        Err(e) if resume => return Err(e),
+        _ => Checkpoint::default(),
+    };
+
+    let mut paths = Vec::new();
+    collect_audio_files(dir, &mut paths)?;
+    paths.sort();
+
+    let mut summary = BatchSummary::default();
+    let (done, pending): (Vec<_>, Vec<_>) = paths
+        .into_iter()
+        .partition(|path| checkpoint.song_id(path).is_some());
+    summary.resumed = done
+        .into_iter()
+        .filter_map(|path| Some((checkpoint.song_id(&path)?, path)))
+        .collect();
+
+    let mut song_id = db.next_song_id();
+    for group in pending.chunks(CHECKPOINT_FILES) {
+        let from = summary.succeeded.len();
+        fingerprint_batch(group.to_vec(), &mut db, config, &mut song_id, &mut summary);
+        for (id, path) in &summary.succeeded[from..] {
+            checkpoint.done.insert(path.clone(), *id);
+        }
+        // Un archivo sin hashes no avanza el `next_song_id` de la base.
+        checkpoint.next_song_id = db.next_song_id();
+        // Primero la base de datos: un punto de control por delante de ella perdería archivos.
+        db.save(db_path)?;
+        checkpoint.save(&checkpoint_path)?;
+    }
+    if pending.is_empty() {
+        db.save(db_path)?;
+    }
+
+    match fs::remove_file(&checkpoint_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(summary),
+    }
+}
+
+/// Calcula en paralelo las huellas de `paths` y las inserta en orden con identificadores
+/// consecutivos a partir de `song_id`.
+fn fingerprint_batch(
+    paths: Vec<PathBuf>,
+    db: &mut FingerprintDb,
+    config: &FingerprintConfig,
+    song_id: &mut u32,
+    summary: &mut BatchSummary,
+) {
+    let results: Vec<_> = paths
+        .into_par_iter()
+        .map(|path| {
+            let hashes = path
+                .to_str()
+                .ok_or_else(|| SonoraError::InvalidPath(path.clone()))
+                .and_then(|p| fingerprint_file(p, config));
+            (path, hashes)
+        })
+        .collect();
+
+    for (path, hashes) in results {
+        match hashes {
+            Ok(hashes) => {
+                db.insert(*song_id, &hashes);
+                summary.succeeded.push((*song_id, path));
+                *song_id += 1;
+            }
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "archivo omitido");
+                summary.failed.push((path, e));
+            }
+        }
+    }
+}
+
+/// Punto de control de `index_dir`: los archivos ya insertados en la base de datos, con su
+/// `song_id`, y el `next_song_id` que tenía la base de datos al escribirlo.
+///
+/// Se guarda como texto, una línea por archivo, y se escribe en un archivo temporal que
+/// después sustituye al anterior.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checkpoint {
+    done: BTreeMap<PathBuf, u32>,
+    next_song_id: u32,
+}
+
+impl Checkpoint {
+    /// Ruta del punto de control de una base de datos: la misma con `.checkpoint` añadido.
+    pub fn path_for(db_path: &Path) -> PathBuf {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(".checkpoint");
+        PathBuf::from(path)
+    }
+
+    /// `song_id` con que se insertó un archivo, si el punto de control lo da por indexado.
+    pub fn song_id(&self, path: &Path) -> Option<u32> {
+        self.done.get(path).copied()
+    }
+
+    /// Número de archivos indexados.
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    /// `true` si no hay ningún archivo indexado.
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    /// Carga un punto de control guardado con `save`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta del archivo.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Checkpoint, SonoraError>` - El punto de control, el error de E/S o
+    ///   `InvalidCheckpoint` si el contenido no tiene el formato esperado.
+    pub fn load(path: &Path) -> Result<Self, SonoraError> {
+        let mut lines = BufReader::new(fs::File::open(path)?).lines();
+        let mut next_line =
+            || -> Result<Option<String>, SonoraError> { Ok(lines.next().transpose()?) };
+
+        if next_line()?.as_deref() != Some(CHECKPOINT_HEADER) {
+            return Err(SonoraError::InvalidCheckpoint);
+        }
+        let next_song_id = next_line()?
+            .as_deref()
+            .and_then(|line| line.strip_prefix("next "))
+            .and_then(|id| id.parse().ok())
+            .ok_or(SonoraError::InvalidCheckpoint)?;
+
+        let mut done = BTreeMap::new();
+        while let Some(line) = next_line()? {
+            let (id, path) = line
+                .split_once('\t')
+                .ok_or(SonoraError::InvalidCheckpoint)?;
+            let id = id.parse().map_err(|_| SonoraError::InvalidCheckpoint)?;
+            done.insert(PathBuf::from(path), id);
+        }
+
+        Ok(Self { done, next_song_id })
+    }
+
+    /// Guarda el punto de control.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta del archivo a crear o sobrescribir.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<(), SonoraError>` - Vacío, el error de E/S, o `InvalidPath` si alguna ruta no
+    ///   es UTF-8 o tiene saltos de línea.
+    pub fn save(&self, path: &Path) -> Result<(), SonoraError> {
+        let mut lines = Vec::with_capacity(self.done.len());
+        for (file, id) in &self.done {
+            match file.to_str() {
+                Some(text) if !text.contains('\n') => lines.push((id, text)),
+                _ => return Err(SonoraError::InvalidPath(file.clone())),
+            }
+        }
+
+        write_atomically(path, |w| {
+            writeln!(w, "{}", CHECKPOINT_HEADER)?;
+            writeln!(w, "next {}", self.next_song_id)?;
+            for (id, text) in lines {
+                writeln!(w, "{}\t{}", id, text)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Añade a `paths` los archivos con extensión de audio bajo `dir`, recursivamente.
+fn collect_audio_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), SonoraError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_audio_files(&path, paths)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
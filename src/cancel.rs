@@ -0,0 +1,42 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::SonoraError;
+
+/// Señal compartida para abortar un cálculo de huellas en curso.
+///
+/// Los clones comparten el mismo estado: quien embebe la biblioteca guarda uno, se lo pasa al
+/// [`Fingerprinter`](crate::Fingerprinter) con `cancel_token` y llama a `cancel` desde otro
+/// hilo. El pipeline lo comprueba entre bloques de lectura y entre ventanas de la FFT y
+/// termina con `SonoraError::Cancelled`. Para quedarse con los resultados parciales basta con
+/// usar `stream_source` y dejar de consumir el iterador.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Crea un token sin cancelar.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pide que los cálculos que usan este token se detengan.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Indica si ya se pidió la cancelación.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Devuelve `Cancelled` si ya se pidió la cancelación.
+    pub(crate) fn check(&self) -> Result<(), SonoraError> {
+        if self.is_cancelled() {
+            Err(SonoraError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use alloc::format;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::band::Bands;
+use crate::error::SonoraError;
+use crate::hash::{HashScheme, TargetZone};
+use crate::resample::CANONICAL_SAMPLE_RATE;
+use crate::spectrogram::WindowFn;
+
+/// Orden en que se normaliza la señal respecto a la mezcla a mono.
+///
+/// Con `AfterDownmix` los canales se promedian y la mezcla se normaliza por su máximo, por lo
+/// que un canal mucho más fuerte domina la señal resultante. Con `BeforeDownmix` cada canal se
+/// normaliza primero por su propio máximo, de modo que en material paneado ambos canales pesan
+/// lo mismo en la mezcla; después la mezcla se vuelve a normalizar para quedar en `[-1.0, 1.0]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NormalizeOrder {
+    BeforeDownmix,
+    #[default]
+    AfterDownmix,
+}
+
+/// Cómo se reduce el audio multicanal a la señal de la que salen las huellas.
+///
+/// `Left` y `Right` toman el primer y el segundo canal, y `Mid` y `Side` la semisuma y la
+/// semidiferencia de ambos; en 5.1 y 7.1 son los frontales. Con audio mono el canal que falta
+/// es el mismo canal, así que todas dan la señal original salvo `Side`, que da silencio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ChannelPolicy {
+    /// Media de todos los canales, con `normalize_order` decidiendo cuándo se normaliza.
+    #[default]
+    Mix,
+    /// Solo el canal izquierdo.
+    Left,
+    /// Solo el canal derecho.
+    Right,
+    /// `(L + R) / 2`: lo común a ambos canales, como la voz centrada.
+    Mid,
+    /// `(L - R) / 2`: lo que difiere entre canales, como la reverberación o los instrumentos
+    /// paneados.
+    Side,
+    /// Una huella independiente por canal, con `fingerprint_channels`; los pipelines de una
+    /// sola huella la rechazan con `InvalidConfig`.
+    PerChannel,
+}
+
+impl ChannelPolicy {
+    /// Todas las políticas, en el orden en que se documentan.
+    pub const ALL: [ChannelPolicy; 6] = [
+        ChannelPolicy::Mix,
+        ChannelPolicy::Left,
+        ChannelPolicy::Right,
+        ChannelPolicy::Mid,
+        ChannelPolicy::Side,
+        ChannelPolicy::PerChannel,
+    ];
+
+    /// Nombre de la política en la línea de comandos.
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelPolicy::Mix => "mix",
+            ChannelPolicy::Left => "left",
+            ChannelPolicy::Right => "right",
+            ChannelPolicy::Mid => "mid",
+            ChannelPolicy::Side => "side",
+            ChannelPolicy::PerChannel => "per-channel",
+        }
+    }
+}
+
+impl fmt::Display for ChannelPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ChannelPolicy {
+    type Err = SonoraError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChannelPolicy::ALL
+            .into_iter()
+            .find(|policy| policy.name() == s)
+            .ok_or_else(|| {
+                SonoraError::InvalidConfig(format!("política de canales desconocida: {s}"))
+            })
+    }
+}
+
+/// Parámetros del pipeline de huellas.
+///
+/// Agrupa lo que antes eran constantes repartidas por los binarios para poder ajustar el
+/// detector (voz frente a música, por ejemplo) sin recompilar. Los valores por defecto dan la
+/// misma resolución que usaba `fingerprint_v2` a 44.1 kHz (ventanas de ~93 ms y bins de
+/// ~10.8 Hz), ahora a la tasa canónica de 11025 Hz, y las mismas bandas que los binarios de
+/// hash por bandas. La ventana está en muestras, así que al cambiar `canonical_rate` conviene
+/// escalarla con ella para conservar la resolución.
+#[derive(Debug, Clone)]
+pub struct FingerprintConfig {
+    /// Tasa en Hz a la que se remuestrea todo el audio antes del espectrograma de los
+    /// landmarks; las huellas solo son comparables entre configuraciones con la misma tasa.
+    pub canonical_rate: u32,
+    /// Tamaño de la ventana para FFT.
+    pub window_size: usize,
+    /// Solapamiento entre ventanas, en muestras.
+    pub overlap: usize,
+    /// Función de ventana aplicada antes de la FFT.
+    pub window_fn: WindowFn,
+    /// Umbral mínimo de amplitud para detectar picos.
+    pub amp_min: f32,
+    /// Tamaño del vecindario para la detección de máximos locales.
+    pub neighborhood_size: usize,
+    /// Número de picos a emparejar con cada pico ancla.
+    pub fan_value: usize,
+    /// Máximo intervalo de tiempo en segundos para emparejar picos.
+    pub max_delta_t: f32,
+    /// Zona objetivo en la que se buscan los destinos de cada ancla; con `None` se emparejan los
+    /// `fan_value` picos siguientes dentro de `max_delta_t`.
+    pub target_zone: Option<TargetZone>,
+    /// Ancho en Hz de los intervalos a los que se redondean las frecuencias de un landmark
+    /// antes del hash; un pico desplazado un bin suele caer en el mismo intervalo. 0 solo
+    /// redondea al Hz.
+    pub freq_bucket_hz: f32,
+    /// Ancho en segundos de los intervalos a los que se redondea el `delta_t` de un landmark
+    /// antes del hash. 0 solo redondea al segundo.
+    pub delta_t_bucket: f32,
+    /// Cómo se convierten los índices cuantizados de un landmark en su hash.
+    pub hash_scheme: HashScheme,
+    /// Uno de cada cuántos picos (por intensidad) actúa como ancla; reduce el tamaño de la
+    /// base de datos a costa de exhaustividad.
+    pub anchor_stride: usize,
+    /// Colapsa los hashes repetidos de una misma pista, como los que produce una nota sostenida
+    /// o un bucle: nunca se emite dos veces el mismo hash en el mismo tiempo y cada hash
+    /// aparece como mucho `max_hash_repeats` veces, las primeras.
+    pub dedup: bool,
+    /// Apariciones máximas de un mismo hash por pista cuando `dedup` está activo; 0 no limita.
+    pub max_hash_repeats: usize,
+    /// Flujo espectral mínimo (ver `spectral_flux`) para que los picos de una ventana entren en
+    /// el hash; las ventanas casi iguales a la anterior, como una nota sostenida, se omiten.
+    /// 0 desactiva la puerta.
+    pub novelty_threshold: f32,
+    /// Armónicos del énfasis HPS; 0 o 1 lo desactiva.
+    pub hps_harmonics: usize,
+    /// Resta la media de cada ventana antes de la FFT del espectrograma; los motores por bandas
+    /// usan `chunk_remove_dc`.
+    pub remove_dc: bool,
+    /// Orden de la normalización respecto a la mezcla a mono.
+    pub normalize_order: NormalizeOrder,
+    /// Cómo se reduce el audio multicanal antes de generar las huellas.
+    pub channel_policy: ChannelPolicy,
+    /// Muestras por bloque en los motores de hash por bandas.
+    pub chunk_size: usize,
+    /// Salto entre bloques consecutivos en los motores de hash por bandas; con un salto menor
+    /// que `chunk_size` los bloques se solapan y un landmark en la frontera no se parte.
+    pub chunk_hop: usize,
+    /// Resta la media de cada bloque antes de la FFT en los motores por bandas, como hacían sus
+    /// binarios; a diferencia de `remove_dc`, está activo por defecto.
+    pub chunk_remove_dc: bool,
+    /// Tolerancia aplicada a cada bin ganador antes de empaquetarlo en el hash por bandas.
+    pub fuz_factor: usize,
+    /// Primer bin de frecuencia considerado al buscar máximos por banda, incluido; no debe
+    /// superar el primer límite de `bands` para que la primera banda no quede vacía.
+    pub min_freq: usize,
+    /// Último bin de frecuencia considerado al buscar máximos por banda, incluido; normalmente
+    /// el último límite de `bands`.
+    pub max_freq: usize,
+    /// Límites superiores de las bandas, en bins, incluidos y en orden creciente. Cada banda
+    /// empieza justo después del límite anterior y la primera en `min_freq`.
+    pub bands: Bands,
+    /// Presupuesto aproximado en bytes para el audio en vuelo. Con `Some`, `fingerprint_file` y
+    /// `fingerprint_source` procesan el audio en streaming en lugar de cargar la pista entera
+    /// (sin normalizar por el máximo, como `stream_source`), y los lotes y las colas entre
+    /// hilos de los motores por bandas y de `stream_source` se acortan hasta caber en él. Con
+    /// `None` no hay límite.
+    pub max_memory: Option<usize>,
+}
+
+impl FingerprintConfig {
+    /// Comprueba que la ventana admite el solapamiento configurado, para que las entradas de
+    /// la biblioteca devuelvan `InvalidConfig` en lugar de fallar dentro del espectrograma.
+    pub(crate) fn check_window(&self) -> Result<(), SonoraError> {
+        if self.window_size == 0 || self.overlap >= self.window_size {
+            return Err(SonoraError::InvalidConfig(
+                "el solapamiento debe ser menor que la ventana".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Comprueba que los bloques y las bandas de los motores por bandas sean coherentes: que
+    /// los límites de las bandas queden entre `min_freq` y `max_freq`, que estos quepan en el
+    /// bloque y que los campos del hash quepan en un `usize`.
+    #[cfg(feature = "std")]
+    pub(crate) fn check_bands(&self) -> Result<(), SonoraError> {
+        let invalid = |reason: &str| Err(SonoraError::InvalidConfig(reason.into()));
+
+        if self.chunk_hop == 0 || self.chunk_hop > self.chunk_size {
+            return invalid("el salto entre bloques debe estar entre 1 y el tamaño del bloque");
+        }
+        if self.fuz_factor == 0 {
+            return invalid("la tolerancia de los bins debe ser al menos 1");
+        }
+        self.bands.check()?;
+        if self.min_freq > self.bands[0] || self.max_freq >= self.chunk_size {
+            return invalid("las bandas deben caber entre `min_freq` y el tamaño del bloque");
+        }
+        self.bands.check_max_freq(self.max_freq)?;
+        if self.bands.hash_bits(self.fuz_factor, self.max_freq) > usize::BITS {
+            return invalid("los campos de las bandas no caben en el hash");
+        }
+        Ok(())
+    }
+
+    /// Elementos de `item_bytes` bytes que caben en `max_memory`, entre 1 y `default`; sin
+    /// presupuesto, `default`.
+    #[cfg(feature = "std")]
+    pub(crate) fn budget_items(&self, item_bytes: usize, default: usize) -> usize {
+        self.max_memory.map_or(default, |budget| {
+            (budget / item_bytes.max(1)).clamp(1, default)
+        })
+    }
+}
+
+impl Default for FingerprintConfig {
+    fn default() -> Self {
+        Self {
+            canonical_rate: CANONICAL_SAMPLE_RATE,
+            window_size: 1024,
+            overlap: 512,
+            window_fn: WindowFn::Hann,
+            // Una ventana 4 veces más corta da magnitudes 4 veces menores.
+            amp_min: 2.5,
+            neighborhood_size: 20,
+            fan_value: 15,
+            max_delta_t: 5.0,
+            target_zone: None,
+            // Unos dos bins de ~10.8 Hz.
+            freq_bucket_hz: 20.0,
+            delta_t_bucket: 0.05,
+            hash_scheme: HashScheme::Packed,
+            anchor_stride: 1,
+            dedup: false,
+            max_hash_repeats: 1,
+            novelty_threshold: 0.0,
+            hps_harmonics: 0,
+            remove_dc: false,
+            normalize_order: NormalizeOrder::AfterDownmix,
+            channel_policy: ChannelPolicy::Mix,
+            chunk_size: 1024 * 4,
+            chunk_hop: 1024 * 4,
+            chunk_remove_dc: true,
+            fuz_factor: 2,
+            min_freq: 40,
+            max_freq: 300,
+            bands: Bands::default(),
+            max_memory: None,
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+use crate::features::SpectralSummary;
+use crate::hash::{HashEntry, HashScheme};
+use crate::sink::HashSink;
+use crate::FINGERPRINT_VERSION;
+
+/// Metadatos de una canción de referencia guardados junto a sus huellas.
+#[derive(Debug, Clone, Default)]
+pub struct SongMeta {
+    /// Resumen tímbrico, para descartar candidatos antes de comparar huellas.
+    pub spectral: SpectralSummary,
+}
+
+/// Firma al inicio de los archivos de base de datos.
+const DB_MAGIC: &[u8; 4] = b"SNDB";
+
+/// Base de datos de huellas de referencia.
+///
+/// Indexa cada hash con las canciones y los tiempos de ancla en que aparece, que es lo que
+/// necesita `match_query` para votar desplazamientos. Vive en memoria como una tabla hash, de
+/// modo que `lookup` es de coste constante aunque haya millones de entradas, y se persiste con
+/// `save` y `load` para reutilizarla entre ejecuciones. Recuerda el [`HashScheme`] de sus
+/// huellas, ya que solo las consultas generadas con el mismo dan coincidencias.
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintDb {
+    index: HashMap<u64, Vec<(u32, f32)>>,
+    meta: HashMap<u32, SongMeta>,
+    hash_scheme: HashScheme,
+}
+
+impl FingerprintDb {
+    /// Crea una base de datos vacía para huellas de `HashScheme::Packed`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Crea una base de datos vacía para huellas del esquema indicado.
+    ///
+    /// # Argumentos
+    ///
+    /// * `hash_scheme` - Esquema con que se generan las huellas que se insertarán.
+    pub fn with_hash_scheme(hash_scheme: HashScheme) -> Self {
+        Self {
+            hash_scheme,
+            ..Self::default()
+        }
+    }
+
+    /// Esquema de hash de las huellas de la base de datos.
+    pub fn hash_scheme(&self) -> HashScheme {
+        self.hash_scheme
+    }
+
+    /// Comprueba que una configuración genera huellas comparables con las de la base de datos.
+    ///
+    /// `match_query` solo ve hashes, así que con otro esquema no daría error sino ninguna
+    /// coincidencia; conviene llamarlo antes de consultar o de insertar con `config`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `config` - Parámetros con que se generan las consultas o las nuevas huellas.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<(), SonoraError>` - Vacío, o `InvalidConfig` si `config.hash_scheme` no es el
+    ///   de la base de datos.
+    pub fn check_hash_scheme(&self, config: &FingerprintConfig) -> Result<(), SonoraError> {
+        if config.hash_scheme != self.hash_scheme {
+            return Err(SonoraError::InvalidConfig(format!(
+                "la base de datos usa el esquema de hash {:?} y la configuración {:?}",
+                self.hash_scheme, config.hash_scheme
+            )));
+        }
+        Ok(())
+    }
+
+    /// Añade las huellas de una canción.
+    ///
+    /// # Argumentos
+    ///
+    /// * `song_id` - Identificador de la canción.
+    /// * `hashes` - Hashes generados para la canción.
+    pub fn insert(&mut self, song_id: u32, hashes: &[HashEntry]) {
+        for entry in hashes {
+            self.index
+                .entry(entry.hash)
+                .or_default()
+                .push((song_id, entry.time));
+        }
+    }
+
+    /// Primer identificador libre: uno más que el mayor `song_id` presente.
+    ///
+    /// Recorre todo el índice, así que conviene llamarlo una vez por lote y no por canción.
+    ///
+    /// # Retorna
+    ///
+    /// * `u32` - Identificador sin usar; 0 si la base de datos está vacía.
+    pub fn next_song_id(&self) -> u32 {
+        self.index
+            .values()
+            .flatten()
+            .map(|&(song_id, _)| song_id)
+            .chain(self.meta.keys().copied())
+            .max()
+            .map_or(0, |max| max + 1)
+    }
+
+    /// Devuelve las apariciones `(song_id, anchor_time)` de un hash.
+    ///
+    /// # Argumentos
+    ///
+    /// * `hash` - Hash a buscar.
+    ///
+    /// # Retorna
+    ///
+    /// * `&[(u32, f32)]` - Apariciones del hash; vacío si no está en la base de datos.
+    pub fn lookup(&self, hash: u64) -> &[(u32, f32)] {
+        self.index.get(&hash).map_or(&[], Vec::as_slice)
+    }
+
+    /// Número de hashes distintos del índice.
+    pub fn num_hashes(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Número de apariciones indexadas de cada canción.
+    ///
+    /// Recorre todo el índice, como `next_song_id`.
+    ///
+    /// # Retorna
+    ///
+    /// * `Vec<(u32, usize)>` - Pares `(song_id, apariciones)` ordenados por `song_id`.
+    pub fn song_hash_counts(&self) -> Vec<(u32, usize)> {
+        let mut counts: HashMap<u32, usize> = HashMap::new();
+        for &(song_id, _) in self.index.values().flatten() {
+            *counts.entry(song_id).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_unstable();
+        counts
+    }
+
+    /// Guarda los metadatos de una canción, sustituyendo los anteriores.
+    ///
+    /// # Argumentos
+    ///
+    /// * `song_id` - Identificador de la canción.
+    /// * `meta` - Metadatos de la canción.
+    pub fn set_meta(&mut self, song_id: u32, meta: SongMeta) {
+        self.meta.insert(song_id, meta);
+    }
+
+    /// Devuelve los metadatos de una canción, si se guardaron.
+    ///
+    /// # Argumentos
+    ///
+    /// * `song_id` - Identificador de la canción.
+    ///
+    /// # Retorna
+    ///
+    /// * `Option<&SongMeta>` - Metadatos de la canción.
+    pub fn meta(&self, song_id: u32) -> Option<&SongMeta> {
+        self.meta.get(&song_id)
+    }
+
+    /// Canciones cuyo timbre se aleja más de `max_distance` del de la consulta.
+    ///
+    /// Sirve para descartar candidatos antes de comparar huellas. Las canciones sin metadatos
+    /// nunca se descartan, ya que no hay con qué compararlas.
+    ///
+    /// # Argumentos
+    ///
+    /// * `query` - Resumen tímbrico de la consulta.
+    /// * `max_distance` - Distancia máxima según [`SpectralSummary::distance`].
+    ///
+    /// # Retorna
+    ///
+    /// * `Vec<u32>` - Identificadores de las canciones descartadas, en orden creciente.
+    pub fn timbre_mismatches(&self, query: &SpectralSummary, max_distance: f32) -> Vec<u32> {
+        let mut rejected: Vec<u32> = self
+            .meta
+            .iter()
+            .filter(|(_, meta)| meta.spectral.distance(query) > max_distance)
+            .map(|(&song_id, _)| song_id)
+            .collect();
+        rejected.sort_unstable();
+        rejected
+    }
+
+    /// Guarda la base de datos en un archivo binario.
+    ///
+    /// El formato es una cabecera con la firma `SNDB`, la [`FINGERPRINT_VERSION`] con que se
+    /// generaron las huellas y un byte con el esquema de hash, seguida de las entradas del
+    /// índice (hash y sus apariciones) y de los metadatos por canción, con enteros y flotantes
+    /// en little-endian.
+    ///
+    /// Se escribe primero en un archivo temporal junto al destino, que después lo sustituye, así
+    /// que una interrupción nunca deja una base de datos a medias.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta del archivo a crear o sobrescribir.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<(), SonoraError>` - Vacío o el error de E/S.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SonoraError> {
+        write_atomically(path.as_ref(), |w| {
+            w.write_all(DB_MAGIC)?;
+            w.write_all(&FINGERPRINT_VERSION.to_le_bytes())?;
+            w.write_all(&[self.hash_scheme.id()])?;
+            self.write_to(w)
+        })
+    }
+
+    /// Carga una base de datos guardada con `save`.
+    ///
+    /// Falla si el archivo se escribió con otra [`FINGERPRINT_VERSION`]: sus hashes no son
+    /// comparables con los que genera esta versión y `match_query` devolvería coincidencias sin
+    /// sentido en lugar de un error. La única excepción es la versión anterior, cuyos hashes
+    /// son los mismos y que guardaba el esquema de hash como un byte opcional al final del
+    /// archivo: si falta, se lee como `HashScheme::Packed`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta del archivo.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<FingerprintDb, SonoraError>` - Base de datos cargada o el error que lo impidió.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SonoraError> {
+        let mut r = BufReader::new(File::open(path)?);
+
+        let mut header = [0u8; 8];
+        r.read_exact(&mut header)
+            .map_err(|_| SonoraError::InvalidDatabase)?;
+        if &header[..4] != DB_MAGIC {
+            return Err(SonoraError::InvalidDatabase);
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version == FINGERPRINT_VERSION {
+            let mut scheme = [0u8; 1];
+            r.read_exact(&mut scheme)
+                .map_err(|_| SonoraError::InvalidDatabase)?;
+            let hash_scheme = HashScheme::from_id(scheme[0]).ok_or(SonoraError::InvalidDatabase)?;
+            return Ok(Self {
+                hash_scheme,
+                ..Self::read_from(&mut r)?
+            });
+        }
+        if version != FINGERPRINT_VERSION - 1 {
+            return Err(SonoraError::VersionMismatch {
+                found: version,
+                expected: FINGERPRINT_VERSION,
+            });
+        }
+
+        let mut db = Self::read_from(&mut r)?;
+        let mut scheme = [0u8; 1];
+        if r.read(&mut scheme)? == 1 {
+            db.hash_scheme = HashScheme::from_id(scheme[0]).ok_or(SonoraError::InvalidDatabase)?;
+        }
+        Ok(db)
+    }
+
+    fn write_to(&self, w: &mut impl Write) -> std::io::Result<()> {
+        w.write_all(&(self.index.len() as u64).to_le_bytes())?;
+        for (hash, postings) in &self.index {
+            w.write_all(&hash.to_le_bytes())?;
+            w.write_all(&(postings.len() as u64).to_le_bytes())?;
+            for &(song_id, time) in postings {
+                w.write_all(&song_id.to_le_bytes())?;
+                w.write_all(&time.to_le_bytes())?;
+            }
+        }
+
+        w.write_all(&(self.meta.len() as u64).to_le_bytes())?;
+        for (&song_id, meta) in &self.meta {
+            let spectral = &meta.spectral;
+            w.write_all(&song_id.to_le_bytes())?;
+            for value in [spectral.centroid, spectral.rolloff, spectral.flatness] {
+                w.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_from(r: &mut impl Read) -> Result<Self, SonoraError> {
+        fn read_u32(r: &mut impl Read) -> std::io::Result<u32> {
+            let mut buf = [0u8; 4];
+            r.read_exact(&mut buf)?;
+            Ok(u32::from_le_bytes(buf))
+        }
+        fn read_u64(r: &mut impl Read) -> std::io::Result<u64> {
+            let mut buf = [0u8; 8];
+            r.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        }
+        fn read_f32(r: &mut impl Read) -> std::io::Result<f32> {
+            read_u32(r).map(f32::from_bits)
+        }
+
+        let mut db = Self::new();
+
+        let num_hashes = read_u64(r)?;
+        for _ in 0..num_hashes {
+            let hash = read_u64(r)?;
+            let num_postings = read_u64(r)?;
+            let mut postings = Vec::new();
+            for _ in 0..num_postings {
+                postings.push((read_u32(r)?, read_f32(r)?));
+            }
+            db.index.insert(hash, postings);
+        }
+
+        let num_meta = read_u64(r)?;
+        for _ in 0..num_meta {
+            let song_id = read_u32(r)?;
+            let spectral = SpectralSummary {
+                centroid: read_f32(r)?,
+                rolloff: read_f32(r)?,
+                flatness: read_f32(r)?,
+            };
+            db.meta.insert(song_id, SongMeta { spectral });
+        }
+
+        Ok(db)
+    }
+}
+
+/// Escribe un archivo con `write` en `<path>.tmp`, lo lleva a disco y lo renombra a `path`.
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) -> Result<(), SonoraError> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut w = BufWriter::new(File::create(&tmp)?);
+    write(&mut w)?;
+    w.flush()?;
+    w.get_ref().sync_all()?;
+    drop(w);
+    fs::rename(&tmp, path)?;
+
+    Ok(())
+}
+
+/// [`HashSink`] que añade las huellas de una canción a una [`FingerprintDb`] según llegan.
+#[derive(Debug)]
+pub struct DbInserter<'a> {
+    db: &'a mut FingerprintDb,
+    song_id: u32,
+}
+
+impl<'a> DbInserter<'a> {
+    /// Crea el destino.
+    ///
+    /// # Argumentos
+    ///
+    /// * `db` - Base de datos en la que se insertan las huellas.
+    /// * `song_id` - Identificador de la canción a la que pertenecen.
+    pub fn new(db: &'a mut FingerprintDb, song_id: u32) -> Self {
+        Self { db, song_id }
+    }
+}
+
+impl HashSink for DbInserter<'_> {
+    fn accept(&mut self, hashes: &[HashEntry]) -> Result<(), SonoraError> {
+        self.db.insert(self.song_id, hashes);
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_AAC, CODEC_TYPE_NULL},
+    conv::FromSample,
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::{MediaSource, MediaSourceStream, ReadOnlySource},
+    meta::MetadataOptions,
+    probe::Hint,
+    sample::Sample,
+    units::{Time, TimeBase},
+};
+
+use crate::config::NormalizeOrder;
+use crate::error::SonoraError;
+
+/// Tramas de cebado de un flujo AAC que no declara las suyas: el retardo que añaden la mayoría
+/// de los codificadores. Los de Apple lo declaran en la etiqueta `iTunSMPB`.
+const AAC_PRIMING_FRAMES: u64 = 1024;
+
+/// Paquetes corruptos seguidos a partir de los cuales el flujo se da por perdido en lugar de
+/// seguir sustituyéndolos por silencio.
+const MAX_CONSECUTIVE_CORRUPT: u32 = 64;
+
+/// Carga un archivo de audio y devuelve una señal mono y normalizada.
+///
+/// El contenedor se detecta automáticamente (WAV, FLAC, MP3, Ogg Vorbis, AAC/M4A, MKV). Acepta
+/// muestras enteras de 8, 16, 24 y 32 bits y flotantes; cada formato se escala por su valor
+/// de fondo de escala antes de mezclar y normalizar.
+///
+/// # Argumentos
+///
+/// * `file_path` - Ruta al archivo de audio.
+///
+/// # Retorna
+///
+/// * `Result<(Vec<f32>, u32), SonoraError>` - Muestras normalizadas y tasa de muestreo del
+///   archivo, o el error que lo impidió.
+pub fn load_audio(file_path: &str) -> Result<(Vec<f32>, u32), SonoraError> {
+    load_audio_with_order(file_path, NormalizeOrder::default())
+}
+
+/// Carga un archivo de audio eligiendo el orden de normalización respecto a la mezcla.
+///
+/// # Argumentos
+///
+/// * `file_path` - Ruta al archivo de audio.
+/// * `order` - Orden de la normalización respecto a la mezcla a mono.
+///
+/// # Retorna
+///
+/// * `Result<(Vec<f32>, u32), SonoraError>` - Muestras normalizadas y tasa de muestreo del
+///   archivo, o el error que lo impidió.
+pub fn load_audio_with_order(
+    file_path: &str,
+    order: NormalizeOrder,
+) -> Result<(Vec<f32>, u32), SonoraError> {
+    // Decodifica a f32 sea cual sea el formato de muestra (U8, S16, S24, S32, F32, ...).
+    let decoded = decode_to_samples(file_path)?;
+    let samples = interleaved_to_mono(&decoded.samples, decoded.channels, order)?;
+
+    Ok((samples, decoded.sample_rate))
+}
+
+/// Convierte muestras PCM de 16 bits intercaladas en una señal mono y normalizada.
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras intercaladas por canal, tal como las entrega el decodificador.
+/// * `channels` - Número de canales de la señal.
+/// * `order` - Orden de la normalización respecto a la mezcla a mono.
+///
+/// # Retorna
+///
+/// * `Result<Vec<f32>, SonoraError>` - Vector de muestras mono normalizadas o el error.
+pub fn pcm_i16_to_mono(
+    samples: &[i16],
+    channels: u16,
+    order: NormalizeOrder,
+) -> Result<Vec<f32>, SonoraError> {
+    let samples: Vec<f32> = samples.iter().map(|&s| s as f32 / 32768.0).collect();
+
+    interleaved_to_mono(&samples, channels as usize, order)
+}
+
+/// Convierte muestras `f32` intercaladas en una señal mono y normalizada.
+///
+/// Solo se mezclan tramas completas: si la señal termina con una trama incompleta (algún canal
+/// con una muestra menos, como en WAV malformados) esa trama se descarta en lugar de
+/// promediarse con canales que faltan.
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras intercaladas por canal.
+/// * `num_channels` - Número de canales de la señal.
+/// * `order` - Orden de la normalización respecto a la mezcla a mono.
+///
+/// # Retorna
+///
+/// * `Result<Vec<f32>, SonoraError>` - Vector de muestras mono normalizadas o el error.
+pub fn interleaved_to_mono(
+    samples: &[f32],
+    num_channels: usize,
+    order: NormalizeOrder,
+) -> Result<Vec<f32>, SonoraError> {
+    if num_channels == 0 {
+        return Err(SonoraError::NoChannels);
+    }
+
+    // Factor de escala por canal: 1.0 salvo que se normalice antes de la mezcla.
+    let mut channel_scale = vec![1.0f32; num_channels];
+    if order == NormalizeOrder::BeforeDownmix {
+        let mut channel_max = vec![0.0f32; num_channels];
+        for frame in samples.chunks_exact(num_channels) {
+            for (max, &s) in channel_max.iter_mut().zip(frame) {
+                *max = max.max(s.abs());
+            }
+        }
+        for (scale, max) in channel_scale.iter_mut().zip(channel_max) {
+            *scale = if max > 0.0 { 1.0 / max } else { 0.0 };
+        }
+    }
+
+    let scaled: Vec<f32>;
+    let samples = if order == NormalizeOrder::BeforeDownmix {
+        scaled = samples
+            .iter()
+            .zip(channel_scale.iter().cycle())
+            .map(|(&s, scale)| s * scale)
+            .collect();
+        &scaled
+    } else {
+        samples
+    };
+
+    let mut mono_samples = Vec::with_capacity(samples.len() / num_channels);
+    downmix_to_mono(samples, num_channels, &mut mono_samples);
+
+    // Encuentra el máximo absoluto para normalización.
+    let max_amplitude = mono_samples.iter().map(|&s| s.abs()).fold(0.0f32, f32::max);
+
+    if max_amplitude == 0.0 {
+        return Err(SonoraError::SilentAudio);
+    }
+
+    // Normaliza las muestras.
+    let normalized_samples: Vec<f32> = mono_samples.iter().map(|&s| s / max_amplitude).collect();
+
+    Ok(normalized_samples)
+}
+
+/// Mezcla a mono muestras intercaladas promediando todos los canales de cada trama.
+///
+/// Sirve para cualquier número de canales (mono, estéreo, 5.1, 7.1, ...) y cualquier formato
+/// de muestra de symphonia: la media se calcula en `f32` y se convierte de vuelta al formato de
+/// entrada. Las tramas incompletas del final se descartan. Es la mezcla que usan todos los
+/// caminos de decodificación, tanto `interleaved_to_mono` como los binarios por bandas.
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras intercaladas por canal.
+/// * `num_channels` - Número de canales de la señal; con 0 no se añade nada.
+/// * `out` - Destino al que se añaden las muestras mono.
+pub fn downmix_to_mono<S>(samples: &[S], num_channels: usize, out: &mut Vec<S>)
+where
+    S: Sample + FromSample<f32>,
+    f32: FromSample<S>,
+{
+    if num_channels == 0 {
+        return;
+    }
+    if num_channels == 1 {
+        out.extend_from_slice(samples);
+        return;
+    }
+    out.extend(samples.chunks_exact(num_channels).map(|frame| {
+        let sum: f32 = frame.iter().map(|&s| f32::from_sample(s)).sum();
+        S::from_sample(sum / num_channels as f32)
+    }));
+}
+
+/// Audio decodificado: muestras intercaladas por canal, normalizadas a `[-1.0, 1.0]`.
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+    /// Paquetes corruptos que se omitieron y se sustituyeron por silencio.
+    pub skipped_packets: u64,
+}
+
+/// Decodifica un archivo de audio con symphonia y convierte cualquier formato de muestra a `f32`.
+///
+/// El contenedor se detecta con el probe de symphonia a partir del contenido, usando la
+/// extensión del archivo como pista, y se decodifica la pista por defecto. El decodificador
+/// elige el formato de salida según el códec (`S16`, `S24`, `S32`, `F32`, `U8`, ...); en lugar
+/// de quedarse solo con `S16` y descartar el resto, cada paquete se convierte a `f32` con la
+/// escala completa de su formato.
+///
+/// # Argumentos
+///
+/// * `file_path` - Ruta al archivo de audio.
+///
+/// # Retorna
+///
+/// * `Result<DecodedAudio, SonoraError>` - Audio decodificado o el error que lo impidió.
+pub fn decode_to_samples(file_path: &str) -> Result<DecodedAudio, SonoraError> {
+    decode_track(TrackDecoder::open_file(file_path, None)?)
+}
+
+/// Decodifica una pista de audio concreta de un contenedor con varias, como `decode_to_samples`.
+///
+/// # Argumentos
+///
+/// * `file_path` - Ruta al archivo de audio.
+/// * `track` - Índice de la pista entre las pistas de audio del contenedor, empezando en 0.
+///
+/// # Retorna
+///
+/// * `Result<DecodedAudio, SonoraError>` - Audio decodificado, `TrackNotFound` si el
+///   contenedor no tiene esa pista, o el error que lo impidió.
+pub fn decode_track_to_samples(file_path: &str, track: usize) -> Result<DecodedAudio, SonoraError> {
+    decode_track(TrackDecoder::open_file(file_path, Some(track))?)
+}
+
+/// Decodifica audio leído de cualquier origen, como `decode_to_samples`.
+///
+/// Sin extensión que sirva de pista, el contenedor se detecta solo por el contenido. El origen
+/// se lee de principio a fin sin retroceder, así que vale un socket o una tubería.
+///
+/// # Argumentos
+///
+/// * `reader` - Origen de los bytes del archivo de audio.
+///
+/// # Retorna
+///
+/// * `Result<DecodedAudio, SonoraError>` - Audio decodificado o el error que lo impidió.
+pub fn decode_reader<R>(reader: R) -> Result<DecodedAudio, SonoraError>
+where
+    R: Read + Send + Sync + 'static,
+{
+    let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
+    decode_track(TrackDecoder::open(mss, &Hint::new(), None)?)
+}
+
+/// Decodifica hasta el final una pista ya abierta.
+fn decode_track(mut track: TrackDecoder) -> Result<DecodedAudio, SonoraError> {
+    let mut samples = Vec::new();
+    while let Some(result) = track.read_packet(&mut samples) {
+        result?;
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        channels: track.channels,
+        sample_rate: track.sample_rate,
+        skipped_packets: track.skipped_packets,
+    })
+}
+
+/// Pista de audio de un contenedor, decodificada paquete a paquete a `f32`.
+///
+/// Sirve tanto para decodificar un archivo entero como para procesarlo sin tenerlo completo en
+/// memoria. `channels` y `sample_rate` empiezan con lo que declara la pista y se actualizan con
+/// cada paquete decodificado.
+pub(crate) struct TrackDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_buf: Option<SampleBuffer<f32>>,
+    pub(crate) channels: usize,
+    pub(crate) sample_rate: u32,
+    /// Bytes de los paquetes de la pista leídos hasta ahora.
+    pub(crate) bytes_read: u64,
+    /// Paquetes corruptos que se han omitido.
+    pub(crate) skipped_packets: u64,
+    /// Paquetes corruptos seguidos desde el último que se decodificó.
+    consecutive_corrupt: u32,
+    /// Tramas del principio que aún hay que descartar: el cebado del codificador.
+    skip_frames: u64,
+    /// Tramas que quedan por entregar antes del relleno final, si el archivo lo declara.
+    remaining_frames: Option<u64>,
+    /// Si se puede saltar: el origen admite retroceder y el cebado no se recorta a mano.
+    seekable: bool,
+    time_base: Option<TimeBase>,
+}
+
+impl TrackDecoder {
+    /// Abre un archivo de audio, usando su extensión como pista para detectar el contenedor.
+    pub(crate) fn open_file(file_path: &str, track: Option<usize>) -> Result<Self, SonoraError> {
+        let src = File::open(file_path)?;
+        let mss = MediaSourceStream::new(Box::new(src), Default::default());
+
+        let mut hint = Hint::new();
+        if let Some(extension) = Path::new(file_path).extension().and_then(|e| e.to_str()) {
+            hint.with_extension(extension);
+        }
+        Self::open(mss, &hint, track)
+    }
+
+    /// Detecta el contenedor de `mss` y prepara el decodificador de una de sus pistas: la de
+    /// índice `track` entre las pistas de audio, o la pista por defecto si es `None`.
+    ///
+    /// Con la reproducción sin huecos se recortan el retardo del codificador y el relleno
+    /// final que añaden formatos como MP3, de modo que un MP3 empieza en la misma muestra que
+    /// el audio del que salió y sus tiempos coinciden con los de un WAV del mismo contenido.
+    pub(crate) fn open(
+        mss: MediaSourceStream,
+        hint: &Hint,
+        track: Option<usize>,
+    ) -> Result<Self, SonoraError> {
+        let seekable = mss.is_seekable();
+        let options = FormatOptions {
+            enable_gapless: true,
+            ..Default::default()
+        };
+        let probed = symphonia::default::get_probe()
+            .format(hint, mss, &options, &MetadataOptions::default())
+            .map_err(SonoraError::UnsupportedFormat)?;
+        let mut format = probed.format;
+
+        let track = match track {
+            None => format.default_track().ok_or(SonoraError::NoDefaultTrack)?,
+            Some(index) => {
+                // Las pistas sin códec conocido (subtítulos, vídeo, ...) no cuentan.
+                let audio: Vec<_> = format
+                    .tracks()
+                    .iter()
+                    .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+                    .collect();
+                let available = audio.len();
+                *audio
+                    .get(index)
+                    .ok_or(SonoraError::TrackNotFound { index, available })?
+            }
+        };
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(SonoraError::UnsupportedFormat)?;
+        let (track_id, channels, sample_rate, time_base) = (
+            track.id,
+            track.codec_params.channels.map_or(0, |c| c.count()),
+            track.codec_params.sample_rate.unwrap_or(0),
+            track.codec_params.time_base,
+        );
+        // symphonia no recorta el cebado de AAC, ni siquiera con la reproducción sin huecos.
+        let aac = track.codec_params.codec == CODEC_TYPE_AAC;
+        let (skip_frames, remaining_frames) = if aac {
+            itunes_gapless(format.as_mut()).unwrap_or((AAC_PRIMING_FRAMES, None))
+        } else {
+            (0, None)
+        };
+
+        Ok(Self {
+            track_id,
+            channels,
+            sample_rate,
+            format,
+            decoder,
+            sample_buf: None,
+            bytes_read: 0,
+            skipped_packets: 0,
+            consecutive_corrupt: 0,
+            skip_frames,
+            remaining_frames,
+            seekable: seekable && !aac,
+            time_base,
+        })
+    }
+
+    /// Salta a la trama `frame` con el seek del contenedor, descartando lo que sobre del
+    /// paquete en el que cae.
+    ///
+    /// # Retorna
+    ///
+    /// * `bool` - `false`, y la pista sigue donde estaba, si el origen no admite retroceder
+    ///   (como la entrada estándar), si el cebado de la pista se recorta a mano (AAC) o si el
+    ///   contenedor no sabe saltar a ese punto.
+    pub(crate) fn seek(&mut self, frame: u64) -> bool {
+        if !self.seekable || self.sample_rate == 0 {
+            return false;
+        }
+        let time = Time::from(frame as f64 / self.sample_rate as f64);
+        let to = SeekTo::Time {
+            time,
+            track_id: Some(self.track_id),
+        };
+        let Ok(seeked) = self.format.seek(SeekMode::Accurate, to) else {
+            return false;
+        };
+        self.decoder.reset();
+
+        // El salto cae al principio de un paquete; se descarta hasta la trama pedida.
+        let behind = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        self.skip_frames = self.ts_to_frames(behind);
+        true
+    }
+
+    /// Convierte una duración en unidades de la base de tiempo de la pista a tramas.
+    fn ts_to_frames(&self, ts: u64) -> u64 {
+        match self.time_base {
+            Some(time_base) => {
+                let time = time_base.calc_time(ts);
+                ((time.seconds as f64 + time.frac) * self.sample_rate as f64).round() as u64
+            }
+            None => ts,
+        }
+    }
+
+    /// Decodifica el siguiente paquete de la pista y añade sus muestras intercaladas a `out`.
+    ///
+    /// Un paquete que el decodificador no puede leer (datos corruptos o truncados) no corta la
+    /// pista: se cuenta, se avisa con su posición y se sustituye por silencio de su misma
+    /// duración, para que los tiempos del resto del audio no se desplacen. Solo se aborta con
+    /// errores del contenedor o del origen, o tras `MAX_CONSECUTIVE_CORRUPT` paquetes corruptos
+    /// seguidos.
+    ///
+    /// # Retorna
+    ///
+    /// * `Option<Result<(), SonoraError>>` - `None` al final limpio del flujo, o el resultado
+    ///   de decodificar el paquete; un error indica que la pista no se puede seguir leyendo.
+    pub(crate) fn read_packet(&mut self, out: &mut Vec<f32>) -> Option<Result<(), SonoraError>> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                // El contenedor se quedó sin datos: es el final del flujo.
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return None
+                }
+                Err(e) => return Some(Err(SonoraError::Decode(e))),
+            };
+            // Los contenedores con varias pistas intercalan paquetes de otras pistas.
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            self.bytes_read += packet.buf().len() as u64;
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                Err(e @ (SymphoniaError::DecodeError(_) | SymphoniaError::IoError(_))) => {
+                    self.skipped_packets += 1;
+                    self.consecutive_corrupt += 1;
+                    let seconds =
+                        self.ts_to_frames(packet.ts()) as f64 / self.sample_rate.max(1) as f64;
+                    tracing::warn!(
+                        seconds,
+                        byte = self.bytes_read - packet.buf().len() as u64,
+                        error = %e,
+                        "paquete corrupto omitido"
+                    );
+                    if self.consecutive_corrupt >= MAX_CONSECUTIVE_CORRUPT {
+                        return Some(Err(SonoraError::Decode(e)));
+                    }
+                    let frames = self.ts_to_frames(packet.dur()) as usize;
+                    if frames == 0 || self.channels == 0 {
+                        continue;
+                    }
+                    let silence = vec![0.0; frames * self.channels];
+                    self.push_trimmed(&silence, self.channels, out);
+                    return Some(Ok(()));
+                }
+                Err(e) => return Some(Err(SonoraError::Decode(e))),
+            };
+            self.consecutive_corrupt = 0;
+            let spec = *decoded.spec();
+            self.channels = spec.channels.count();
+            self.sample_rate = spec.rate;
+
+            // Reutiliza el buffer mientras quepa el paquete.
+            let channels = self.channels;
+            let mut buf = match self.sample_buf.take() {
+                Some(buf) if buf.capacity() >= decoded.capacity() * channels => buf,
+                _ => SampleBuffer::new(decoded.capacity() as u64, spec),
+            };
+            // Vorbis y FLAC entregan planos por canal; se intercalan aquí para que la mezcla a
+            // mono y el resto del pipeline vean siempre tramas intercaladas en `f32`.
+            buf.copy_interleaved_ref(decoded);
+            self.push_trimmed(buf.samples(), channels, out);
+            self.sample_buf = Some(buf);
+            return Some(Ok(()));
+        }
+    }
+
+    /// Añade a `out` las tramas de un paquete, sin el cebado que quede por descartar ni el
+    /// relleno final.
+    fn push_trimmed(&mut self, samples: &[f32], channels: usize, out: &mut Vec<f32>) {
+        let frames = (samples.len() / channels.max(1)) as u64;
+        let skip = self.skip_frames.min(frames);
+        self.skip_frames -= skip;
+        let mut end = frames;
+        if let Some(remaining) = &mut self.remaining_frames {
+            end = skip + (frames - skip).min(*remaining);
+            *remaining -= end - skip;
+        }
+        out.extend_from_slice(&samples[skip as usize * channels..end as usize * channels]);
+    }
+}
+
+/// Cebado y duración real de una pista según la etiqueta `iTunSMPB` de iTunes, si la tiene.
+///
+/// La etiqueta son campos hexadecimales separados por espacios: el segundo es el cebado, el
+/// tercero el relleno final y el cuarto el número de tramas del audio original.
+fn itunes_gapless(format: &mut dyn FormatReader) -> Option<(u64, Option<u64>)> {
+    let metadata = format.metadata();
+    let tag = metadata
+        .current()?
+        .tags()
+        .iter()
+        .find(|tag| tag.key.ends_with("iTunSMPB"))?;
+    let value = tag.value.to_string();
+    let mut fields = value
+        .split_whitespace()
+        .map(|field| u64::from_str_radix(field, 16).ok());
+    let delay = fields.nth(1)??;
+    let frames = fields.nth(1)?.filter(|&frames| frames > 0);
+    Some((delay, frames))
+}
+
+/// Comprueba si decodificar un archivo dos veces produce exactamente las mismas muestras.
+///
+/// Algunos decodificadores con pérdida no son bit a bit reproducibles entre ejecuciones o
+/// plataformas; si este chequeo falla, una diferencia de huellas puede venir del decodificador
+/// y no del pipeline.
+///
+/// # Argumentos
+///
+/// * `file_path` - Ruta al archivo de audio.
+///
+/// # Retorna
+///
+/// * `bool` - `true` si ambas decodificaciones son idénticas; `false` si difieren o si el
+///   archivo no se puede decodificar.
+pub fn decode_is_deterministic(file_path: &str) -> bool {
+    match (decode_to_samples(file_path), decode_to_samples(file_path)) {
+        (Ok(first), Ok(second)) => {
+            first.channels == second.channels
+                && first.sample_rate == second.sample_rate
+                && first.samples.len() == second.samples.len()
+                && first
+                    .samples
+                    .iter()
+                    .zip(&second.samples)
+                    .all(|(a, b)| a.to_bits() == b.to_bits())
+        }
+        _ => false,
+    }
+}
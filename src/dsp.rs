@@ -0,0 +1,178 @@
+//! Bloques de procesado de señal que no forman parte del pipeline de huellas, como el diezmado
+//! con filtro antialiasing.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::error::SonoraError;
+use crate::resample::sinc;
+
+/// Diezmador polifásico: filtro FIR paso bajo y una de cada `factor` muestras.
+///
+/// Quedarse con una de cada `factor` muestras sin filtrar pliega todo lo que hay por encima de
+/// la nueva frecuencia de Nyquist sobre las bandas útiles. El filtro es un sinc enventanado con
+/// Hann, como el de `resample`, con ganancia 1 en continua y centrado en cada muestra que se
+/// conserva, así que no retrasa la señal; solo se evalúa en esas muestras, de modo que cuesta
+/// `taps / factor` productos por muestra de entrada. Guarda entre llamadas la entrada que aún
+/// necesita, así que una señal troceada da el mismo resultado que entera.
+///
+/// `resample` también filtra al bajar la tasa y admite cualquier relación; el diezmador es la
+/// alternativa más barata cuando el factor es entero, con el corte y el número de coeficientes
+/// a elección.
+#[derive(Debug, Clone)]
+pub struct Decimator {
+    factor: usize,
+    /// Coeficientes del filtro, simétricos y en número impar.
+    taps: Vec<f32>,
+    /// Muestras de entrada pendientes, a partir del índice absoluto `pending_start`.
+    pending: Vec<f32>,
+    pending_start: usize,
+    /// Índice absoluto de la siguiente muestra de salida.
+    next_out: usize,
+}
+
+impl Decimator {
+    /// Diseña el filtro.
+    ///
+    /// # Argumentos
+    ///
+    /// * `factor` - Una muestra de salida por cada `factor` de entrada; 1 solo filtra.
+    /// * `cutoff` - Frecuencia de corte relativa a la frecuencia de Nyquist de la salida, en
+    ///   `(0.0, 1.0]`; algo por debajo de 1, como 0.9, deja margen a la banda de transición.
+    /// * `taps` - Coeficientes del filtro; se redondea a impar. Más coeficientes dan una
+    ///   transición más estrecha y más atenuación a cambio de más cálculo.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Decimator, SonoraError>` - El diezmador, o `InvalidConfig` si algún parámetro
+    ///   está fuera de rango.
+    pub fn new(factor: usize, cutoff: f32, taps: usize) -> Result<Self, SonoraError> {
+        if factor == 0 {
+            return Err(SonoraError::InvalidConfig(
+                "el factor de diezmado debe ser al menos 1".to_string(),
+            ));
+        }
+        if !(cutoff > 0.0 && cutoff <= 1.0) {
+            return Err(SonoraError::InvalidConfig(
+                "el corte del diezmado debe estar en (0, 1]".to_string(),
+            ));
+        }
+        if taps == 0 {
+            return Err(SonoraError::InvalidConfig(
+                "el filtro de diezmado necesita al menos un coeficiente".to_string(),
+            ));
+        }
+
+        // Corte relativo al Nyquist de la entrada.
+        let fc = cutoff as f64 / factor as f64;
+        let half = (taps / 2) as isize;
+        let raw: Vec<f64> = (-half..=half)
+            .map(|t| {
+                let t = t as f64;
+                let window = 0.5 * (1.0 + (PI * t / (half + 1) as f64).cos());
+                fc * sinc(fc * t) * window
+            })
+            .collect();
+        let gain: f64 = raw.iter().sum();
+
+        Ok(Self {
+            factor,
+            taps: raw.iter().map(|&h| (h / gain) as f32).collect(),
+            pending: Vec::new(),
+            pending_start: 0,
+            next_out: 0,
+        })
+    }
+
+    /// Muestras de entrada por cada muestra de salida.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Añade un bloque de entrada y añade a `out` las muestras de salida que ya se pueden
+    /// calcular.
+    ///
+    /// # Argumentos
+    ///
+    /// * `input` - Siguiente bloque de la señal.
+    /// * `out` - Destino al que se añaden las muestras diezmadas.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        self.pending.extend_from_slice(input);
+        let half = self.taps.len() / 2;
+        let available = self.pending_start + self.pending.len();
+        while self.next_out * self.factor + half < available {
+            out.push(self.output());
+            self.next_out += 1;
+        }
+
+        // Descarta la entrada que ya no entra en ningún filtro futuro.
+        let keep_from = (self.next_out * self.factor).saturating_sub(half);
+        let drop = keep_from
+            .saturating_sub(self.pending_start)
+            .min(self.pending.len());
+        self.pending.drain(..drop);
+        self.pending_start += drop;
+    }
+
+    /// Añade a `out` las muestras que faltan, con la señal rellena de ceros tras el final, y
+    /// deja el diezmador listo para otra señal.
+    ///
+    /// # Argumentos
+    ///
+    /// * `out` - Destino al que se añaden las muestras diezmadas.
+    pub fn finish(&mut self, out: &mut Vec<f32>) {
+        let end = self.pending_start + self.pending.len();
+        while self.next_out * self.factor < end {
+            out.push(self.output());
+            self.next_out += 1;
+        }
+        self.pending.clear();
+        self.pending_start = 0;
+        self.next_out = 0;
+    }
+
+    /// Filtra la entrada alrededor de la siguiente muestra de salida; fuera de la señal cuenta
+    /// como cero.
+    fn output(&self) -> f32 {
+        let center = (self.next_out * self.factor) as isize;
+        let first = center - (self.taps.len() / 2) as isize - self.pending_start as isize;
+        self.taps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &h)| {
+                let index = usize::try_from(first + i as isize).ok()?;
+                Some(h * self.pending.get(index)?)
+            })
+            .sum()
+    }
+}
+
+/// Diezma una señal mono completa con un [`Decimator`].
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras de la señal.
+/// * `factor` - Una muestra de salida por cada `factor` de entrada.
+/// * `cutoff` - Frecuencia de corte relativa a la frecuencia de Nyquist de la salida.
+/// * `taps` - Coeficientes del filtro.
+///
+/// # Retorna
+///
+/// * `Result<Vec<f32>, SonoraError>` - Una muestra por cada `factor` de entrada (redondeando
+///   hacia arriba), o `InvalidConfig` como en `Decimator::new`.
+pub fn decimate(
+    samples: &[f32],
+    factor: usize,
+    cutoff: f32,
+    taps: usize,
+) -> Result<Vec<f32>, SonoraError> {
+    let mut decimator = Decimator::new(factor, cutoff, taps)?;
+    let mut out = Vec::with_capacity(samples.len().div_ceil(factor));
+    decimator.process(samples, &mut out);
+    decimator.finish(&mut out);
+    Ok(out)
+}
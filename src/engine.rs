@@ -0,0 +1,421 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam::channel::bounded;
+use rayon::prelude::*;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use symphonia::core::conv::FromSample;
+
+use crate::band::{band_lookup, chunk_band_hash, chunk_band_hash_with, chunk_count, remove_dc};
+use crate::config::FingerprintConfig;
+use crate::decode::downmix_to_mono;
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::sink::HashSink;
+use crate::source::{AudioSource, ChannelSource, BLOCK_FRAMES};
+
+/// Bloques que el motor `Rayon` procesa en paralelo de una vez. Solo se guardan en memoria las
+/// muestras de un lote y sus FFT, así que el consumo no depende de la duración del archivo;
+/// `max_memory` puede reducirlo.
+const BATCH_CHUNKS: usize = 1024;
+
+/// Bloques de muestras que pueden esperar en el canal del motor `Pipelined`; `max_memory`
+/// puede reducirlo.
+const PIPELINE_QUEUE: usize = 20;
+
+/// Estrategia de ejecución del hash por bandas.
+///
+/// Todos los motores leen el audio del mismo modo, lo cortan en los mismos bloques y calculan
+/// cada hash con la misma función, así que generan exactamente los mismos hashes; solo cambia
+/// cómo se reparte el trabajo, y las comparaciones de rendimiento entre ellos son justas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Engine {
+    /// Un solo hilo, bloque a bloque, con magnitudes escalares.
+    Sequential,
+    /// Lotes de bloques en paralelo con rayon (el antiguo `fingerprint_v1`).
+    #[default]
+    Rayon,
+    /// Un solo hilo con las magnitudes calculadas con SIMD (el antiguo `fingerprint_v3`).
+    Simd,
+    /// Un hilo decodifica y otro calcula los hashes, unidos por un canal acotado (el antiguo
+    /// `fingerprint_v4`).
+    Pipelined,
+}
+
+impl Engine {
+    /// Todos los motores, en el orden en que se documentan.
+    pub const ALL: [Engine; 4] = [
+        Engine::Sequential,
+        Engine::Rayon,
+        Engine::Simd,
+        Engine::Pipelined,
+    ];
+
+    /// Nombre del motor en la línea de comandos.
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Sequential => "sequential",
+            Engine::Rayon => "rayon",
+            Engine::Simd => "simd",
+            Engine::Pipelined => "pipelined",
+        }
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = SonoraError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Engine::ALL
+            .into_iter()
+            .find(|engine| engine.name() == s)
+            .ok_or_else(|| SonoraError::InvalidConfig(format!("motor desconocido: {s}")))
+    }
+}
+
+/// Genera los hashes por bandas de un origen de audio con el motor indicado.
+///
+/// La señal se reduce a mono según `channel_policy`, se cuantiza a 16 bits como hacían los
+/// binarios por bandas y se corta en bloques de `chunk_size` muestras separados `chunk_hop`; el
+/// último se rellena con ceros. Con `chunk_remove_dc` (por defecto) a cada bloque se le resta
+/// la DC antes de la FFT, y su hash se calcula con `chunk_band_hash`. Los hashes se entregan a
+/// `sink` por lotes y en orden de tiempo, así que la memoria no crece con la duración del
+/// audio; al terminar sin errores se llama a su `finish`. Con `max_memory`, el lote del motor
+/// `Rayon` y la cola del motor `Pipelined` se acortan para caber en el presupuesto, sin cambiar
+/// los hashes.
+///
+/// Los tiempos salen de la tasa real del origen, pero los bloques y las bandas se cuentan en
+/// muestras y bins a esa tasa, sin remuestrear: a diferencia de los landmarks, las huellas por
+/// bandas solo son comparables entre archivos con la misma tasa de muestreo.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Configuración con los bloques y las bandas.
+/// * `engine` - Estrategia de ejecución.
+/// * `sink` - Destino de los hashes, por ejemplo un `Vec<HashEntry>`, un `FingerprintWriter`
+///   o una clausura; si devuelve un error, el cálculo se detiene con él.
+///
+/// # Retorna
+///
+/// * `Result<(), SonoraError>` - El primer error de lectura o de `sink`, o `InvalidConfig` si
+///   los bloques o las bandas son incoherentes o la política de canales es `PerChannel`.
+pub fn band_fingerprints<S, K>(
+    source: S,
+    config: &FingerprintConfig,
+    engine: Engine,
+    sink: &mut K,
+) -> Result<(), SonoraError>
+where
+    S: AudioSource + Send + 'static,
+    K: HashSink + ?Sized,
+{
+    config.check_bands()?;
+    let mut source = ChannelSource::new(source, config.channel_policy)?;
+
+    let _span = tracing::debug_span!(
+        "band_fingerprints",
+        %engine,
+        chunk_size = config.chunk_size,
+        chunk_hop = config.chunk_hop
+    )
+    .entered();
+    let mut hasher = ChunkHasher::new(config, source.sample_rate(), engine);
+    let mut chunker = Chunker::new(config);
+
+    if engine == Engine::Pipelined {
+        let block_bytes = BLOCK_FRAMES * std::mem::size_of::<f32>();
+        let (tx, rx) = bounded(config.budget_items(block_bytes, PIPELINE_QUEUE));
+        let producer = thread::spawn(move || -> Result<(), SonoraError> {
+            let mut reader = MonoReader::new(&source);
+            while let Some(block) = reader.next(&mut source)? {
+                // Si el consumidor terminó, ya tiene su error y no hay a quién enviar.
+                if tx.send(block).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+        for block in rx.iter() {
+            tracing::trace!(
+                queue_depth = rx.len(),
+                frames = block.len(),
+                "bloque recibido"
+            );
+            chunker.push(&block);
+            hasher.hash_ready(&mut chunker, 1, sink)?;
+        }
+        producer
+            .join()
+            .expect("el hilo de decodificación entró en pánico")?;
+    } else {
+        let batch = if engine == Engine::Rayon {
+            // Cada bloque del lote retiene sus muestras pendientes y su hash.
+            let chunk_bytes =
+                config.chunk_hop * std::mem::size_of::<f32>() + std::mem::size_of::<HashEntry>();
+            config.budget_items(chunk_bytes, BATCH_CHUNKS)
+        } else {
+            1
+        };
+        let mut reader = MonoReader::new(&source);
+        while let Some(block) = reader.next(&mut source)? {
+            chunker.push(&block);
+            hasher.hash_ready(&mut chunker, batch, sink)?;
+        }
+    }
+
+    let remaining = chunker.finish();
+    hasher.hash_chunks(&chunker, remaining, sink)?;
+    sink.finish()
+}
+
+/// Lee bloques de un origen y los mezcla a mono con la escala de 16 bits de los motores.
+struct MonoReader {
+    frames: Vec<f32>,
+    mono: Vec<f32>,
+    channels: usize,
+}
+
+impl MonoReader {
+    fn new(source: &impl AudioSource) -> Self {
+        let channels = source.channels().max(1);
+        Self {
+            frames: vec![0.0; BLOCK_FRAMES * channels],
+            mono: Vec::new(),
+            channels,
+        }
+    }
+
+    /// Siguiente bloque mono; `None` al final del origen.
+    fn next(&mut self, source: &mut impl AudioSource) -> Result<Option<Vec<f32>>, SonoraError> {
+        let read = source.read_frames(&mut self.frames)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.mono.clear();
+        downmix_to_mono(
+            &self.frames[..read * self.channels],
+            self.channels,
+            &mut self.mono,
+        );
+        Ok(Some(
+            self.mono
+                .iter()
+                .map(|&sample| i16::from_sample(sample) as f32)
+                .collect(),
+        ))
+    }
+}
+
+/// Muestras pendientes a partir del bloque `next_chunk`.
+struct Chunker {
+    pending: Vec<f32>,
+    next_chunk: usize,
+    chunk_size: usize,
+    hop: usize,
+    config: FingerprintConfig,
+}
+
+impl Chunker {
+    fn new(config: &FingerprintConfig) -> Self {
+        Self {
+            pending: Vec::new(),
+            next_chunk: 0,
+            chunk_size: config.chunk_size,
+            hop: config.chunk_hop,
+            config: config.clone(),
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+    }
+
+    /// Bloques completos que ya se pueden calcular.
+    fn ready(&self) -> usize {
+        if self.pending.len() < self.chunk_size {
+            0
+        } else {
+            (self.pending.len() - self.chunk_size) / self.hop + 1
+        }
+    }
+
+    /// Descarta los primeros `chunks` bloques ya calculados.
+    fn consume(&mut self, chunks: usize) {
+        let drained = (chunks * self.hop).min(self.pending.len());
+        self.pending.drain(..drained);
+        self.next_chunk += chunks;
+    }
+
+    /// Bloques finales: los que empiezan en las muestras pendientes y no quedan cubiertos por
+    /// el bloque anterior, rellenos con ceros hasta completar el último.
+    fn finish(&mut self) -> usize {
+        let remaining = if self.next_chunk == 0 || self.pending.len() + self.hop > self.chunk_size {
+            chunk_count(self.pending.len(), &self.config)
+        } else {
+            0
+        };
+        if remaining > 0 {
+            let len = (remaining - 1) * self.hop + self.chunk_size;
+            self.pending.resize(self.pending.len().max(len), 0.0);
+        }
+        remaining
+    }
+}
+
+/// FFT y hash de los bloques, repartidos según el motor.
+struct ChunkHasher<'a> {
+    fft: Arc<dyn Fft<f32>>,
+    lookup: Vec<usize>,
+    config: &'a FingerprintConfig,
+    sample_rate: u32,
+    engine: Engine,
+    scratch: Vec<Complex<f32>>,
+    entries: Vec<HashEntry>,
+}
+
+impl<'a> ChunkHasher<'a> {
+    fn new(config: &'a FingerprintConfig, sample_rate: u32, engine: Engine) -> Self {
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(config.chunk_size),
+            lookup: band_lookup(config),
+            config,
+            sample_rate,
+            engine,
+            scratch: vec![Complex::default(); config.chunk_size],
+            entries: Vec::new(),
+        }
+    }
+
+    /// Calcula los bloques completos en grupos de `batch`, dejando pendientes los que no lo
+    /// llenan.
+    fn hash_ready<K>(
+        &mut self,
+        chunker: &mut Chunker,
+        batch: usize,
+        sink: &mut K,
+    ) -> Result<(), SonoraError>
+    where
+        K: HashSink + ?Sized,
+    {
+        let ready = chunker.ready() / batch * batch;
+        if ready == 0 {
+            return Ok(());
+        }
+        self.hash_chunks(chunker, ready, sink)?;
+        chunker.consume(ready);
+        Ok(())
+    }
+
+    /// Calcula los primeros `chunks` bloques pendientes y los entrega a `sink`.
+    fn hash_chunks<K>(
+        &mut self,
+        chunker: &Chunker,
+        chunks: usize,
+        sink: &mut K,
+    ) -> Result<(), SonoraError>
+    where
+        K: HashSink + ?Sized,
+    {
+        if chunks == 0 {
+            return Ok(());
+        }
+        let _span = tracing::trace_span!("hash", chunks, first = chunker.next_chunk).entered();
+        let chunk_size = self.config.chunk_size;
+        let hop = self.config.chunk_hop;
+        let time = |i: usize| ((chunker.next_chunk + i) * hop) as f32 / self.sample_rate as f32;
+        let samples = |i: usize| &chunker.pending[i * hop..i * hop + chunk_size];
+        let subtract_dc = self.config.chunk_remove_dc;
+
+        self.entries.clear();
+        match self.engine {
+            Engine::Rayon => {
+                let (fft, lookup, config) = (self.fft.as_ref(), &self.lookup, self.config);
+                let hashes: Result<Vec<HashEntry>, SonoraError> = (0..chunks)
+                    .into_par_iter()
+                    .map_init(
+                        || vec![Complex::default(); chunk_size],
+                        |scratch, i| {
+                            Ok(HashEntry {
+                                hash: hash_chunk(samples(i), scratch, fft, subtract_dc, |bins| {
+                                    chunk_band_hash(bins, lookup, config)
+                                })?,
+                                time: time(i),
+                            })
+                        },
+                    )
+                    .collect();
+                self.entries = hashes?;
+            }
+            Engine::Sequential => {
+                for i in 0..chunks {
+                    let hash = hash_chunk(
+                        samples(i),
+                        &mut self.scratch,
+                        self.fft.as_ref(),
+                        subtract_dc,
+                        |bins| {
+                            chunk_band_hash_with(bins, &self.lookup, self.config, scalar_magnitudes)
+                        },
+                    )?;
+                    self.entries.push(HashEntry {
+                        hash,
+                        time: time(i),
+                    });
+                }
+            }
+            Engine::Simd | Engine::Pipelined => {
+                for i in 0..chunks {
+                    let hash = hash_chunk(
+                        samples(i),
+                        &mut self.scratch,
+                        self.fft.as_ref(),
+                        subtract_dc,
+                        |bins| chunk_band_hash(bins, &self.lookup, self.config),
+                    )?;
+                    self.entries.push(HashEntry {
+                        hash,
+                        time: time(i),
+                    });
+                }
+            }
+        }
+
+        sink.accept(&self.entries)
+    }
+}
+
+/// Resta la DC de un bloque si `subtract_dc` lo indica, calcula su FFT en `scratch` y aplica
+/// `hash` a los bins.
+fn hash_chunk(
+    samples: &[f32],
+    scratch: &mut [Complex<f32>],
+    fft: &dyn Fft<f32>,
+    subtract_dc: bool,
+    hash: impl Fn(&[Complex<f32>]) -> Result<u64, SonoraError>,
+) -> Result<u64, SonoraError> {
+    for (bin, &sample) in scratch.iter_mut().zip(samples) {
+        *bin = Complex::new(sample, 0.0);
+    }
+    if subtract_dc {
+        remove_dc(scratch);
+    }
+    fft.process(scratch);
+    hash(scratch)
+}
+
+/// Magnitud al cuadrado bin a bin, sin SIMD.
+fn scalar_magnitudes(bins: &[Complex<f32>], out: &mut [f32]) {
+    for (bin, dst) in bins.iter().zip(out) {
+        *dst = bin.norm_sqr();
+    }
+}
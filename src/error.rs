@@ -0,0 +1,84 @@
+use alloc::string::String;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+#[cfg(feature = "std")]
+use symphonia::core::errors::Error as SymphoniaError;
+use thiserror::Error;
+
+/// Nombre corto de [`SonoraError`], accesible como `sonora::Error`.
+pub type Error = SonoraError;
+
+/// Errores de la biblioteca.
+///
+/// Cada variante corresponde a una causa distinta, para que quien use la biblioteca pueda
+/// reintentar, omitir el archivo o avisar al usuario según el caso.
+#[derive(Debug, Error)]
+pub enum SonoraError {
+    /// Fallo de lectura o escritura en disco.
+    #[cfg(feature = "std")]
+    #[error("Error de entrada/salida: {0}")]
+    Io(#[from] std::io::Error),
+    /// El contenedor o el códec no se reconocen.
+    #[cfg(feature = "std")]
+    #[error("Formato de audio no soportado: {0}")]
+    UnsupportedFormat(#[source] SymphoniaError),
+    /// El contenedor no tiene una pista de audio por defecto.
+    #[error("No se encontró el track de audio")]
+    NoDefaultTrack,
+    /// Se pidió una pista de audio que el contenedor no tiene.
+    #[error("El contenedor no tiene la pista de audio {index}: tiene {available}")]
+    TrackNotFound { index: usize, available: usize },
+    /// La pista no indica su tasa de muestreo y no se puede deducir de los paquetes.
+    #[error("El track no indica su tasa de muestreo")]
+    UnknownSampleRate,
+    /// La señal no tiene ningún canal.
+    #[error("El audio no tiene canales.")]
+    NoChannels,
+    /// Todas las muestras son cero; no hay nada que normalizar ni que identificar.
+    #[error("El archivo de audio está completamente silencioso.")]
+    SilentAudio,
+    /// Un paquete del flujo de audio no se pudo decodificar.
+    #[cfg(feature = "std")]
+    #[error("Error decodificando el audio: {0}")]
+    Decode(#[source] SymphoniaError),
+    /// La ruta no se puede representar como UTF-8.
+    #[cfg(feature = "std")]
+    #[error("La ruta no es UTF-8 válido: {}", .0.display())]
+    InvalidPath(PathBuf),
+    /// El archivo no es una base de datos de huellas o está dañado.
+    #[error("El archivo no es una base de datos de huellas válida.")]
+    InvalidDatabase,
+    /// Un archivo de huellas no tiene el formato indicado o está dañado, o unas huellas no se
+    /// pueden escribir en él (un tiempo no finito en NDJSON).
+    #[error("El archivo de huellas no tiene un formato válido.")]
+    InvalidFingerprints,
+    /// El punto de control de un lote está dañado o no corresponde a la base de datos.
+    #[error("El punto de control no es válido o no corresponde a la base de datos.")]
+    InvalidCheckpoint,
+    /// Los parámetros del pipeline son incoherentes (por ejemplo, un solapamiento mayor que la
+    /// ventana).
+    #[error("Configuración no válida: {0}")]
+    InvalidConfig(String),
+    /// Un pico tiene un tiempo o una frecuencia NaN, así que no se puede ordenar.
+    #[error("Un pico tiene un tiempo o una frecuencia que no es un número.")]
+    InvalidPeak,
+    /// El cálculo se abortó con un `CancelToken`.
+    #[error("El cálculo de huellas se canceló.")]
+    Cancelled,
+    /// La base de datos o el archivo de huellas se generó con otra versión del formato de
+    /// huellas.
+    #[error(
+        "Las huellas son de la versión {found} y esta es la versión {expected}; hay que \
+         regenerarlas."
+    )]
+    VersionMismatch { found: u32, expected: u32 },
+    /// Un flujo HTTP no se pudo pedir o el servidor lo rechazó.
+    #[cfg(feature = "std")]
+    #[error("Error HTTP: {0}")]
+    Http(String),
+    /// No se pudo abrir o arrancar la captura de audio del dispositivo de entrada.
+    #[cfg(feature = "mic")]
+    #[error("Error en la captura de audio: {0}")]
+    Capture(String),
+}
@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use crate::config::FingerprintConfig;
+use crate::db::FingerprintDb;
+use crate::error::SonoraError;
+use crate::fingerprint_file;
+use crate::matching::{match_query_with, MatchConfig};
+
+/// Resultado de una consulta del conjunto etiquetado.
+#[derive(Debug, Clone)]
+pub struct QueryOutcome {
+    pub path: PathBuf,
+    /// Canción esperada, o `None` si la consulta no debería coincidir con nada.
+    pub expected: Option<u32>,
+    /// Mejor candidato devuelto, con su puntuación.
+    pub top_match: Option<(u32, usize)>,
+}
+
+/// Precisión y exhaustividad al aceptar solo coincidencias con `score >= threshold`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThresholdPoint {
+    pub threshold: usize,
+    pub precision: f32,
+    pub recall: f32,
+}
+
+/// Informe de evaluación sobre un conjunto de consultas etiquetadas.
+#[derive(Debug, Clone)]
+pub struct EvalReport {
+    pub outcomes: Vec<QueryOutcome>,
+    /// Un punto por cada puntuación observada, ordenados por umbral creciente.
+    pub points: Vec<ThresholdPoint>,
+}
+
+/// Evalúa el emparejamiento sobre consultas etiquetadas para elegir un umbral de puntuación.
+///
+/// Cada consulta se identifica sin umbral (basta un voto) y se guarda su mejor candidato; a
+/// partir de ahí se calculan precisión y exhaustividad para cada puntuación observada. Un
+/// acierto es un mejor candidato aceptado que coincide con la canción esperada; un candidato
+/// aceptado distinto, o cualquiera para una consulta sin canción esperada, es un falso
+/// positivo.
+///
+/// # Argumentos
+///
+/// * `queries` - Rutas de las consultas con la canción que deberían identificar.
+/// * `db` - Base de datos de huellas de referencia.
+/// * `config` - Parámetros del pipeline usados para generar las huellas de las consultas.
+///
+/// # Retorna
+///
+/// * `Result<EvalReport, SonoraError>` - Informe de evaluación, el error de la primera consulta
+///   que no se pudo procesar, o `InvalidConfig` si `config` usa otro `hash_scheme` que `db`.
+pub fn evaluate(
+    queries: &[(PathBuf, Option<u32>)],
+    db: &FingerprintDb,
+    config: &FingerprintConfig,
+) -> Result<EvalReport, SonoraError> {
+    db.check_hash_scheme(config)?;
+    let match_config = MatchConfig {
+        min_score: 1,
+        hash_scheme: config.hash_scheme,
+        ..MatchConfig::default()
+    };
+
+    let mut outcomes = Vec::with_capacity(queries.len());
+    for (path, expected) in queries {
+        let hashes = fingerprint_file(&path.to_string_lossy(), config)?;
+        let top_match = match_query_with(&hashes, db, &match_config)
+            .first()
+            .map(|result| (result.song_id, result.score));
+        outcomes.push(QueryOutcome {
+            path: path.clone(),
+            expected: *expected,
+            top_match,
+        });
+    }
+
+    let mut thresholds: Vec<usize> = outcomes
+        .iter()
+        .filter_map(|outcome| outcome.top_match.map(|(_, score)| score))
+        .collect();
+    thresholds.sort_unstable();
+    thresholds.dedup();
+
+    let relevant = outcomes.iter().filter(|o| o.expected.is_some()).count();
+    let points = thresholds
+        .into_iter()
+        .map(|threshold| {
+            let mut true_positives = 0;
+            let mut accepted = 0;
+            for outcome in &outcomes {
+                if let Some((song_id, score)) = outcome.top_match {
+                    if score >= threshold {
+                        accepted += 1;
+                        if outcome.expected == Some(song_id) {
+                            true_positives += 1;
+                        }
+                    }
+                }
+            }
+            ThresholdPoint {
+                threshold,
+                precision: ratio(true_positives, accepted),
+                recall: ratio(true_positives, relevant),
+            }
+        })
+        .collect();
+
+    Ok(EvalReport { outcomes, points })
+}
+
+/// Cociente que vale 1.0 cuando el denominador es cero (nada que fallar).
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        1.0
+    } else {
+        numerator as f32 / denominator as f32
+    }
+}
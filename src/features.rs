@@ -0,0 +1,155 @@
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::spectrogram::Spectrogram;
+
+/// Fracción de la energía que queda por debajo de la frecuencia de rolloff.
+const ROLLOFF_FRACTION: f32 = 0.85;
+
+/// Resumen tímbrico de un archivo: descriptores espectrales promediados sobre sus ventanas.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpectralSummary {
+    /// Centroide espectral medio en Hz; más alto cuanto más brillante es el sonido.
+    pub centroid: f32,
+    /// Frecuencia media en Hz por debajo de la cual queda el 85 % de la energía.
+    pub rolloff: f32,
+    /// Planitud espectral media en `[0.0, 1.0]`: cercana a 0 para tonos, a 1 para ruido.
+    pub flatness: f32,
+}
+
+impl SpectralSummary {
+    /// Diferencia tímbrica entre dos resúmenes.
+    ///
+    /// Es la mayor de las diferencias relativas de centroide y rolloff y de la diferencia
+    /// absoluta de planitud, así que un umbral como `0.5` descarta candidatos con el doble de
+    /// brillo sin depender de la escala en Hz.
+    ///
+    /// # Argumentos
+    ///
+    /// * `other` - Resumen con el que comparar.
+    ///
+    /// # Retorna
+    ///
+    /// * `f32` - Distancia no negativa; 0 para resúmenes idénticos.
+    pub fn distance(&self, other: &SpectralSummary) -> f32 {
+        let relative = |a: f32, b: f32| {
+            let scale = a.abs().max(b.abs());
+            if scale > 0.0 {
+                (a - b).abs() / scale
+            } else {
+                0.0
+            }
+        };
+
+        relative(self.centroid, other.centroid)
+            .max(relative(self.rolloff, other.rolloff))
+            .max((self.flatness - other.flatness).abs())
+    }
+}
+
+/// Calcula el centroide, el rolloff y la planitud espectral medios de un espectrograma.
+///
+/// Las ventanas sin energía (silencio) no entran en la media, para que los silencios al
+/// principio o al final de un archivo no arrastren los descriptores hacia 0.
+///
+/// # Argumentos
+///
+/// * `spec` - Espectrograma de entrada.
+///
+/// # Retorna
+///
+/// * `SpectralSummary` - Descriptores promediados; todo a 0 si no hay ventanas con energía.
+pub fn spectral_features(spec: &Spectrogram) -> SpectralSummary {
+    let mut sum = SpectralSummary::default();
+    let mut frames = 0usize;
+
+    for frame in &spec.magnitudes {
+        let energy: f32 = frame.iter().map(|m| m * m).sum();
+        if energy <= 0.0 {
+            continue;
+        }
+        frames += 1;
+
+        // Centroide ponderado por magnitud.
+        let total: f32 = frame.iter().sum();
+        let weighted: f32 = frame
+            .iter()
+            .zip(&spec.frequencies)
+            .map(|(m, f)| m * f)
+            .sum();
+        sum.centroid += weighted / total;
+
+        // Primer bin en el que la energía acumulada alcanza la fracción de rolloff.
+        let target = energy * ROLLOFF_FRACTION;
+        let mut accumulated = 0.0;
+        let rolloff_bin = frame
+            .iter()
+            .position(|m| {
+                accumulated += m * m;
+                accumulated >= target
+            })
+            .unwrap_or(frame.len() - 1);
+        sum.rolloff += spec.frequencies[rolloff_bin];
+
+        // Media geométrica sobre media aritmética de la potencia.
+        let n = frame.len() as f32;
+        let log_mean = frame
+            .iter()
+            .map(|m| (m * m).max(f32::MIN_POSITIVE).ln())
+            .sum::<f32>()
+            / n;
+        sum.flatness += log_mean.exp() / (energy / n);
+    }
+
+    if frames == 0 {
+        return sum;
+    }
+    let frames = frames as f32;
+    SpectralSummary {
+        centroid: sum.centroid / frames,
+        rolloff: sum.rolloff / frames,
+        flatness: sum.flatness / frames,
+    }
+}
+
+/// Flujo espectral normalizado de cada ventana: cuánto aumenta la magnitud respecto a la
+/// ventana anterior.
+///
+/// Para cada ventana suma los incrementos positivos de magnitud bin a bin y los divide por la
+/// magnitud total de la ventana, de modo que el valor queda en `[0.0, 1.0]` y no depende del
+/// volumen: una nota sostenida da valores cercanos a 0 y un ataque o un cambio de nota, valores
+/// altos. La primera ventana no tiene con qué compararse y vale 1.
+///
+/// # Argumentos
+///
+/// * `spec` - Espectrograma de entrada.
+///
+/// # Retorna
+///
+/// * `Vec<f32>` - Flujo de cada ventana; 0 para las ventanas en silencio.
+pub fn spectral_flux(spec: &Spectrogram) -> Vec<f32> {
+    let mut flux = Vec::with_capacity(spec.magnitudes.len());
+    let mut previous: Option<&Vec<f32>> = None;
+
+    for frame in &spec.magnitudes {
+        let total: f32 = frame.iter().sum();
+        let value = match previous {
+            _ if total <= 0.0 => 0.0,
+            None => 1.0,
+            Some(previous) => {
+                let increase: f32 = frame
+                    .iter()
+                    .zip(previous)
+                    .map(|(m, p)| (m - p).max(0.0))
+                    .sum();
+                increase / total
+            }
+        };
+        flux.push(value);
+        previous = Some(frame);
+    }
+
+    flux
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(feature = "tokio")]
+use crate::async_stream::run_blocking;
+use crate::band::Bands;
+use crate::cancel::CancelToken;
+use crate::config::{ChannelPolicy, FingerprintConfig};
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::hasher::{Hasher, LandmarkHasher};
+use crate::peaks::{NeighborhoodDetector, PeakDetector};
+use crate::progress::{Hooks, ProgressSink};
+use crate::run_pipeline;
+use crate::sink::HashSink;
+use crate::source::{AudioSource, FileSource};
+
+/// Generador de huellas con una configuración validada.
+///
+/// Se construye con [`Fingerprinter::builder`], que permite ajustar los parámetros más
+/// habituales sin tocar el resto de [`FingerprintConfig`], y genera las huellas de archivos o
+/// de cualquier origen de bytes con el mismo pipeline que `fingerprint_file` y el esquema de
+/// hash elegido (por defecto [`LandmarkHasher`]).
+#[derive(Clone)]
+pub struct Fingerprinter {
+    config: FingerprintConfig,
+    hasher: Arc<dyn Hasher>,
+    cancel: Option<CancelToken>,
+    progress: Option<Arc<dyn ProgressSink>>,
+}
+
+impl fmt::Debug for Fingerprinter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fingerprinter")
+            .field("config", &self.config)
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Fingerprinter {
+    /// Crea un constructor con la configuración por defecto.
+    pub fn builder() -> FingerprinterBuilder {
+        FingerprinterBuilder::default()
+    }
+
+    /// Configuración con la que se generan las huellas; sirve también para las primitivas de
+    /// los motores por bandas, que leen `chunk_size`, `fuz_factor` y `bands`.
+    pub fn config(&self) -> &FingerprintConfig {
+        &self.config
+    }
+
+    /// Genera las huellas de un archivo de audio.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta al archivo de audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    pub fn fingerprint(&self, path: impl AsRef<Path>) -> Result<Vec<HashEntry>, SonoraError> {
+        let path = path.as_ref();
+        let path = path
+            .to_str()
+            .ok_or_else(|| SonoraError::InvalidPath(path.to_path_buf()))?;
+        self.fingerprint_source(FileSource::open(path)?)
+    }
+
+    /// Genera las huellas de un archivo de audio y las entrega a un [`HashSink`], en un solo
+    /// lote seguido de `finish`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta al archivo de audio.
+    /// * `sink` - Destino de los hashes.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<(), SonoraError>` - El error de la generación o del destino.
+    pub fn fingerprint_into<K: HashSink + ?Sized>(
+        &self,
+        path: impl AsRef<Path>,
+        sink: &mut K,
+    ) -> Result<(), SonoraError> {
+        let hashes = self.fingerprint(path)?;
+        sink.accept(&hashes)?;
+        sink.finish()
+    }
+
+    /// Genera las huellas de audio leído de cualquier origen, por ejemplo un archivo en memoria
+    /// o una respuesta HTTP.
+    ///
+    /// El contenedor se detecta por el contenido, como en [`FileSource::from_reader`].
+    ///
+    /// # Argumentos
+    ///
+    /// * `reader` - Origen de los bytes del archivo de audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    pub fn fingerprint_reader<R>(&self, reader: R) -> Result<Vec<HashEntry>, SonoraError>
+    where
+        R: Read + Send + Sync + 'static,
+    {
+        self.fingerprint_source(FileSource::from_reader(reader)?)
+    }
+
+    /// Genera las huellas de todo el audio de un [`AudioSource`].
+    ///
+    /// # Argumentos
+    ///
+    /// * `source` - Origen del audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    pub fn fingerprint_source<S: AudioSource>(
+        &self,
+        source: S,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        run_pipeline(
+            source,
+            &self.config,
+            self.hasher.as_ref(),
+            Hooks {
+                cancel: self.cancel.as_ref(),
+                progress: self.progress.as_deref(),
+            },
+        )
+    }
+
+    /// Como `fingerprint`, pero sin bloquear el runtime de tokio: la decodificación y el cálculo
+    /// se hacen en su pool de tareas bloqueantes.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta al archivo de audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    #[cfg(feature = "tokio")]
+    pub async fn fingerprint_async(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        let fingerprinter = self.clone();
+        let path = path.as_ref().to_path_buf();
+        run_blocking(move || fingerprinter.fingerprint(path)).await
+    }
+
+    /// Como `fingerprint_source`, pero sin bloquear el runtime de tokio.
+    ///
+    /// # Argumentos
+    ///
+    /// * `source` - Origen del audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    #[cfg(feature = "tokio")]
+    pub async fn fingerprint_source_async<S>(
+        &self,
+        source: S,
+    ) -> Result<Vec<HashEntry>, SonoraError>
+    where
+        S: AudioSource + Send + 'static,
+    {
+        let fingerprinter = self.clone();
+        run_blocking(move || fingerprinter.fingerprint_source(source)).await
+    }
+}
+
+/// Constructor de [`Fingerprinter`].
+///
+/// Parte de [`FingerprintConfig::default`] o de una configuración completa con `config`, y
+/// cada método sustituye un parámetro. `build` comprueba que el conjunto sea coherente.
+#[derive(Clone)]
+pub struct FingerprinterBuilder {
+    config: FingerprintConfig,
+    hasher: Arc<dyn Hasher>,
+    cancel: Option<CancelToken>,
+    progress: Option<Arc<dyn ProgressSink>>,
+}
+
+impl Default for FingerprinterBuilder {
+    fn default() -> Self {
+        Self {
+            config: FingerprintConfig::default(),
+            hasher: Arc::new(LandmarkHasher::new(NeighborhoodDetector)),
+            cancel: None,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for FingerprinterBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FingerprinterBuilder")
+            .field("config", &self.config)
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FingerprinterBuilder {
+    /// Sustituye la configuración completa; los métodos que se llamen después la modifican.
+    pub fn config(mut self, config: FingerprintConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Muestras por bloque en los motores por bandas. El salto entre bloques pasa a ser el
+    /// bloque entero, sin solapamiento, como en la configuración por defecto.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.config.chunk_size = chunk_size;
+        self.config.chunk_hop = chunk_size;
+        self
+    }
+
+    /// Tamaño de la ventana de la FFT del espectrograma.
+    pub fn window_size(mut self, window_size: usize) -> Self {
+        self.config.window_size = window_size;
+        self
+    }
+
+    /// Solapamiento entre ventanas del espectrograma, en muestras.
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.config.overlap = overlap;
+        self
+    }
+
+    /// Tolerancia aplicada a cada bin ganador en los motores por bandas.
+    pub fn fuz_factor(mut self, fuz_factor: usize) -> Self {
+        self.config.fuz_factor = fuz_factor;
+        self
+    }
+
+    /// Límites superiores de las bandas, en bins e incluidos, como un [`Bands`] o un
+    /// `Vec<usize>`. El último pasa a ser `max_freq`.
+    pub fn bands(mut self, bands: impl Into<Bands>) -> Self {
+        let bands = bands.into();
+        if let Some(&last) = bands.last() {
+            self.config.max_freq = last;
+        }
+        self.config.bands = bands;
+        self
+    }
+
+    /// Número de picos a emparejar con cada pico ancla.
+    pub fn fan_value(mut self, fan_value: usize) -> Self {
+        self.config.fan_value = fan_value;
+        self
+    }
+
+    /// Máximo intervalo de tiempo en segundos para emparejar picos.
+    pub fn max_delta_t(mut self, max_delta_t: f32) -> Self {
+        self.config.max_delta_t = max_delta_t;
+        self
+    }
+
+    /// Tasa en Hz a la que se remuestrea el audio antes del espectrograma (ver
+    /// `FingerprintConfig::canonical_rate`).
+    pub fn canonical_rate(mut self, rate: u32) -> Self {
+        self.config.canonical_rate = rate;
+        self
+    }
+
+    /// Presupuesto aproximado en bytes para el audio en vuelo; activa el pipeline en streaming
+    /// (ver `FingerprintConfig::max_memory`).
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.config.max_memory = Some(bytes);
+        self
+    }
+
+    /// Cómo se reduce el audio multicanal; `PerChannel` da varias huellas por archivo y no se
+    /// admite aquí (ver `fingerprint_channels`).
+    pub fn channel_policy(mut self, policy: ChannelPolicy) -> Self {
+        self.config.channel_policy = policy;
+        self
+    }
+
+    /// Esquema de hash con el que se generan las huellas.
+    pub fn hasher(mut self, hasher: impl Hasher + 'static) -> Self {
+        self.hasher = Arc::new(hasher);
+        self
+    }
+
+    /// Detector de picos de los landmarks; sustituye el esquema de hash por un
+    /// [`LandmarkHasher`] con ese detector.
+    pub fn peak_detector(self, detector: impl PeakDetector + 'static) -> Self {
+        self.hasher(LandmarkHasher::new(detector))
+    }
+
+    /// Token con el que se puede abortar cada cálculo desde otro hilo; un cálculo cancelado
+    /// termina con `SonoraError::Cancelled`.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Destino de los avisos de avance de cada cálculo, por ejemplo una clausura que mueve una
+    /// barra de progreso.
+    pub fn progress(mut self, sink: impl ProgressSink + 'static) -> Self {
+        self.progress = Some(Arc::new(sink));
+        self
+    }
+
+    /// Valida la configuración y crea el generador.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Fingerprinter, SonoraError>` - El generador, o `InvalidConfig` con el motivo
+    ///   si los parámetros son incoherentes.
+    pub fn build(self) -> Result<Fingerprinter, SonoraError> {
+        let Self {
+            config,
+            hasher,
+            cancel,
+            progress,
+        } = self;
+        let invalid = |reason: &str| Err(SonoraError::InvalidConfig(reason.to_string()));
+
+        config.check_window()?;
+        if config.canonical_rate == 0 {
+            return invalid("la tasa canónica debe ser mayor que 0");
+        }
+        config.check_bands()?;
+        if config.fan_value == 0 || config.max_delta_t.is_nan() || config.max_delta_t <= 0.0 {
+            return invalid("cada ancla debe poder emparejarse con algún pico");
+        }
+        if config.channel_policy == ChannelPolicy::PerChannel {
+            return invalid("la política per-channel da una huella por canal");
+        }
+
+        Ok(Fingerprinter {
+            config,
+            hasher,
+            cancel,
+            progress,
+        })
+    }
+}
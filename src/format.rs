@@ -0,0 +1,310 @@
+use std::io::{BufRead, Write};
+
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::sink::HashSink;
+use crate::FINGERPRINT_VERSION;
+
+/// Firma al inicio de los archivos binarios de huellas.
+const FINGERPRINTS_MAGIC: &[u8; 4] = b"SNFP";
+
+/// Formato de serialización de una lista de huellas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FingerprintFormat {
+    /// Un objeto JSON por línea, `{"hash":"...","time":...}` con el hash en 16 dígitos
+    /// hexadecimales, tras una primera línea `{"version":N}` con la versión de las huellas;
+    /// fácil de inspeccionar y de procesar con otras herramientas.
+    #[default]
+    NdJson,
+    /// Registros binarios en little-endian tras una cabecera con firma y versión: el hash como
+    /// `u64` y el tiempo como `f32`. Más compacto y rápido de leer que el JSON.
+    Binary,
+}
+
+/// Escribe huellas en el formato indicado.
+///
+/// Los tiempos se escriben sin pérdida en ambos formatos, así que `read_fingerprints`
+/// devuelve exactamente las mismas entradas. Los dos formatos empiezan por una cabecera con
+/// [`FINGERPRINT_VERSION`], así que cada llamada escribe un archivo completo; para escribir
+/// por partes está [`FingerprintWriter`]. El destino no se vacía, eso queda a cargo de quien
+/// llama.
+///
+/// El JSON se escribe a mano y no con serde para que el formato no dependa de la feature
+/// `serde` ni de `serde_json`. Como JSON no admite NaN ni infinitos, un tiempo no finito se
+/// rechaza antes de escribir nada del lote; en binario se guarda tal cual.
+///
+/// # Argumentos
+///
+/// * `w` - Destino de la escritura.
+/// * `entries` - Huellas a escribir.
+/// * `format` - Formato de salida.
+///
+/// # Retorna
+///
+/// * `Result<(), SonoraError>` - Error de escritura, o `InvalidFingerprints` si en JSON algún
+///   tiempo no es finito.
+pub fn write_fingerprints<W: Write>(
+    mut w: W,
+    entries: &[HashEntry],
+    format: FingerprintFormat,
+) -> Result<(), SonoraError> {
+    let _span = tracing::trace_span!("write", entries = entries.len(), ?format).entered();
+    match format {
+        FingerprintFormat::NdJson => {
+            check_json_times(entries)?;
+            write_json_header(&mut w)?;
+            write_json_entries(&mut w, entries, None)?;
+        }
+        FingerprintFormat::Binary => {
+            w.write_all(FINGERPRINTS_MAGIC)?;
+            w.write_all(&FINGERPRINT_VERSION.to_le_bytes())?;
+            w.write_all(&(entries.len() as u64).to_le_bytes())?;
+            for entry in entries {
+                w.write_all(&entry.hash.to_le_bytes())?;
+                w.write_all(&entry.time.to_le_bytes())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Escribe la línea `{"version":N}` con la que empieza un archivo NDJSON.
+fn write_json_header<W: Write>(mut w: W) -> Result<(), SonoraError> {
+    writeln!(w, "{{\"version\":{}}}", FINGERPRINT_VERSION)?;
+    Ok(())
+}
+
+/// Rechaza los tiempos que JSON no puede representar.
+fn check_json_times(entries: &[HashEntry]) -> Result<(), SonoraError> {
+    if entries.iter().any(|entry| !entry.time.is_finite()) {
+        return Err(SonoraError::InvalidFingerprints);
+    }
+    Ok(())
+}
+
+/// Escribe una línea JSON por entrada; con `first_chunk`, numera las entradas a partir de él
+/// en un campo `chunk`.
+fn write_json_entries<W: Write>(
+    mut w: W,
+    entries: &[HashEntry],
+    first_chunk: Option<usize>,
+) -> Result<(), SonoraError> {
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(first) = first_chunk {
+            write!(w, "{{\"chunk\":{},", first + i)?;
+        } else {
+            write!(w, "{{")?;
+        }
+        // Como cadena: un entero JSON de 64 bits pierde precisión en muchos lectores.
+        writeln!(
+            w,
+            "\"hash\":\"{:016x}\",\"time\":{}}}",
+            entry.hash, entry.time
+        )?;
+    }
+    Ok(())
+}
+
+/// [`HashSink`] que escribe las huellas en un archivo o cualquier otro destino con
+/// `write_fingerprints`.
+///
+/// En JSON la cabecera se escribe con el primer lote y cada lote en cuanto llega. El formato
+/// binario lleva el número de entradas en la cabecera, así que sus lotes se acumulan y se
+/// escriben juntos en `finish`. `finish` vacía además el destino.
+///
+/// Los motores por bandas entregan un hash por bloque y en orden, así que con
+/// `with_chunk_index` cada línea JSON lleva además el índice de su bloque,
+/// `{"chunk":N,"hash":"...","time":...}`. En binario el índice es la posición del registro y
+/// no se escribe; `read_fingerprints` ignora el campo.
+#[derive(Debug)]
+pub struct FingerprintWriter<W: Write> {
+    writer: W,
+    format: FingerprintFormat,
+    pending: Vec<HashEntry>,
+    written: usize,
+    header_written: bool,
+    chunk_index: bool,
+}
+
+impl<W: Write> FingerprintWriter<W> {
+    /// Crea el destino.
+    ///
+    /// # Argumentos
+    ///
+    /// * `writer` - Destino de la escritura; conviene que tenga buffer, como un `BufWriter`.
+    /// * `format` - Formato de salida.
+    pub fn new(writer: W, format: FingerprintFormat) -> Self {
+        Self {
+            writer,
+            format,
+            pending: Vec::new(),
+            written: 0,
+            header_written: false,
+            chunk_index: false,
+        }
+    }
+
+    /// Numera las entradas JSON con el índice de su bloque, contando desde 0 la primera
+    /// recibida.
+    pub fn with_chunk_index(mut self) -> Self {
+        self.chunk_index = true;
+        self
+    }
+
+    /// Huellas recibidas hasta ahora, escritas o pendientes de `finish`.
+    pub fn count(&self) -> usize {
+        self.written + self.pending.len()
+    }
+
+    /// Destino de la escritura, por ejemplo para vaciarlo entre lotes de un flujo en directo.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Devuelve el destino; las huellas binarias pendientes se pierden si no se llamó a
+    /// `finish`.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Escribe la cabecera JSON si aún no se escribió.
+    fn json_header(&mut self) -> Result<(), SonoraError> {
+        if !self.header_written {
+            write_json_header(&mut self.writer)?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> HashSink for FingerprintWriter<W> {
+    fn accept(&mut self, hashes: &[HashEntry]) -> Result<(), SonoraError> {
+        match self.format {
+            FingerprintFormat::NdJson => {
+                check_json_times(hashes)?;
+                self.json_header()?;
+                let first_chunk = self.chunk_index.then_some(self.written);
+                write_json_entries(&mut self.writer, hashes, first_chunk)?;
+                self.written += hashes.len();
+            }
+            FingerprintFormat::Binary => self.pending.extend_from_slice(hashes),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), SonoraError> {
+        match self.format {
+            // Sin huellas el archivo tiene al menos la cabecera.
+            FingerprintFormat::NdJson => self.json_header()?,
+            FingerprintFormat::Binary => {
+                write_fingerprints(&mut self.writer, &self.pending, self.format)?;
+                self.written += self.pending.len();
+                self.pending.clear();
+            }
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Lee huellas escritas con `write_fingerprints`.
+///
+/// # Argumentos
+///
+/// * `r` - Origen de la lectura.
+/// * `format` - Formato con el que se escribieron.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Huellas leídas, o el error si el contenido no
+///   tiene el formato esperado (`InvalidFingerprints`, también si falta la cabecera) o es de
+///   otra [`FINGERPRINT_VERSION`] (`VersionMismatch`).
+pub fn read_fingerprints<R: BufRead>(
+    mut r: R,
+    format: FingerprintFormat,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    match format {
+        FingerprintFormat::NdJson => {
+            let mut lines = r
+                .lines()
+                .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()));
+            let header = lines.next().ok_or(SonoraError::InvalidFingerprints)??;
+            let version = parse_json_header(&header).ok_or(SonoraError::InvalidFingerprints)?;
+            check_version(version)?;
+
+            let mut entries = Vec::new();
+            for line in lines {
+                entries.push(parse_json_line(&line?).ok_or(SonoraError::InvalidFingerprints)?);
+            }
+            Ok(entries)
+        }
+        FingerprintFormat::Binary => {
+            let mut header = [0u8; 16];
+            r.read_exact(&mut header)
+                .map_err(|_| SonoraError::InvalidFingerprints)?;
+            if &header[..4] != FINGERPRINTS_MAGIC {
+                return Err(SonoraError::InvalidFingerprints);
+            }
+            check_version(u32::from_le_bytes([
+                header[4], header[5], header[6], header[7],
+            ]))?;
+            let count = u64::from_le_bytes(header[8..].try_into().unwrap());
+
+            let mut entries = Vec::new();
+            for _ in 0..count {
+                let mut record = [0u8; 12];
+                r.read_exact(&mut record)?;
+                entries.push(HashEntry {
+                    hash: u64::from_le_bytes(record[..8].try_into().unwrap()),
+                    time: f32::from_le_bytes(record[8..].try_into().unwrap()),
+                });
+            }
+            Ok(entries)
+        }
+    }
+}
+
+/// Rechaza las huellas de otra versión del formato.
+fn check_version(version: u32) -> Result<(), SonoraError> {
+    if version != FINGERPRINT_VERSION {
+        return Err(SonoraError::VersionMismatch {
+            found: version,
+            expected: FINGERPRINT_VERSION,
+        });
+    }
+    Ok(())
+}
+
+/// Interpreta la cabecera `{"version":N}`.
+fn parse_json_header(line: &str) -> Option<u32> {
+    let body = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (key, value) = body.split_once(':')?;
+    (key.trim() == "\"version\"").then_some(())?;
+    value.trim().parse().ok()
+}
+
+/// Interpreta una línea `{"hash":"...","time":...}`, con los campos en cualquier orden; los
+/// demás campos, como `chunk`, se ignoran.
+fn parse_json_line(line: &str) -> Option<HashEntry> {
+    let body = line.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (mut hash, mut time) = (None, None);
+    for field in body.split(',') {
+        let (key, value) = field.split_once(':')?;
+        let value = value.trim();
+        match key.trim() {
+            "\"hash\"" => {
+                let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+                hash = Some(u64::from_str_radix(inner, 16).ok()?);
+            }
+            // `f32::from_str` acepta `NaN` e `inf`, que no son JSON.
+            "\"time\"" => time = Some(value.parse().ok().filter(|t: &f32| t.is_finite())?),
+            _ => {}
+        }
+    }
+
+    Some(HashEntry {
+        hash: hash?,
+        time: time?,
+    })
+}
@@ -0,0 +1,520 @@
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+use crate::peaks::Peak;
+
+/// Bits de cada frecuencia en un hash de landmark.
+const FREQ_BITS: u32 = 12;
+/// Bits de `delta_t` en un hash de landmark.
+const DELTA_T_BITS: u32 = 14;
+
+/// Zona objetivo de un pico ancla: la región por delante de él, acotada en tiempo y en
+/// frecuencia, en la que se buscan sus picos destino.
+///
+/// Emparejar solo con picos cercanos en frecuencia da landmarks más distintivos que emparejar
+/// con los siguientes en el tiempo, que a menudo están en otra zona del espectro.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetZone {
+    /// Separación mínima en segundos entre el ancla y el destino, incluida.
+    pub min_dt: f32,
+    /// Separación máxima en segundos entre el ancla y el destino, incluida.
+    pub max_dt: f32,
+    /// Diferencia máxima de frecuencia en Hz, hacia arriba o hacia abajo, entre el ancla y el
+    /// destino.
+    pub freq_band: f32,
+}
+
+impl Default for TargetZone {
+    fn default() -> Self {
+        Self {
+            // Unas dos ventanas por delante del ancla.
+            min_dt: 0.1,
+            max_dt: 2.0,
+            freq_band: 500.0,
+        }
+    }
+}
+
+/// Forma en que `landmark_hash` convierte un landmark cuantizado en un hash.
+///
+/// Cada esquema es una [`HashFunction`] de este módulo; los hashes de esquemas distintos no
+/// son comparables, así que una [`FingerprintDb`](crate::FingerprintDb) guarda con cuál se
+/// construyó.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashScheme {
+    /// Índices de los intervalos empaquetados en campos de bits, con [`PackedHash`]; es
+    /// reversible y no necesita ninguna función criptográfica.
+    #[default]
+    Packed,
+    /// SHA-1 de la cadena `"f1|f2|dt"` con los mismos índices, con [`Sha1Hash`], como las
+    /// huellas anteriores a `Packed`. El hash son los 8 primeros bytes del resumen en
+    /// big-endian, es decir, los 16 primeros caracteres del hash hexadecimal de entonces.
+    #[cfg(feature = "sha1")]
+    Sha1,
+    /// FNV-1a de los tres índices, con [`TupleHash`]; a diferencia de `Packed` no satura los
+    /// índices grandes.
+    Fnv1a,
+}
+
+impl HashScheme {
+    /// Función de hash del esquema con los intervalos de `config`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `config` - Parámetros del pipeline (`freq_bucket_hz`, `delta_t_bucket`).
+    ///
+    /// # Retorna
+    ///
+    /// * `Box<dyn HashFunction>` - Función que usa `generate_hashes` con este esquema.
+    pub fn hash_function(self, config: &FingerprintConfig) -> Box<dyn HashFunction> {
+        match self {
+            HashScheme::Packed => Box::new(PackedHash::new(config)),
+            #[cfg(feature = "sha1")]
+            HashScheme::Sha1 => Box::new(Sha1Hash::new(config)),
+            HashScheme::Fnv1a => Box::new(TupleHash::<Fnv1a>::new(config)),
+        }
+    }
+
+    /// Identificador del esquema en los archivos de base de datos.
+    #[cfg(feature = "std")]
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            HashScheme::Packed => 0,
+            #[cfg(feature = "sha1")]
+            HashScheme::Sha1 => 1,
+            HashScheme::Fnv1a => 2,
+        }
+    }
+
+    /// Esquema con el identificador `id`, si existe y su feature está activa.
+    #[cfg(feature = "std")]
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(HashScheme::Packed),
+            #[cfg(feature = "sha1")]
+            1 => Some(HashScheme::Sha1),
+            2 => Some(HashScheme::Fnv1a),
+            _ => None,
+        }
+    }
+}
+
+/// Función que convierte un landmark, el par formado por un pico ancla y un pico destino, en
+/// su hash.
+///
+/// `generate_hashes` usa la que elige `hash_scheme`, y `generate_hashes_with` acepta cualquier
+/// otra para experimentar sin tocar el resto del pipeline. Una base de datos solo da
+/// coincidencias con consultas generadas con la misma función que sus huellas.
+pub trait HashFunction: Send + Sync {
+    /// Calcula el hash de un landmark.
+    ///
+    /// # Argumentos
+    ///
+    /// * `anchor` - Pico ancla.
+    /// * `target` - Pico destino, no anterior al ancla.
+    ///
+    /// # Retorna
+    ///
+    /// * `u64` - Hash del landmark; debe ser el mismo para las mismas entradas.
+    fn hash(&self, anchor: &Peak, target: &Peak) -> u64;
+}
+
+/// [`HashFunction`] de `HashScheme::Packed`: los índices de las frecuencias y de `delta_t`
+/// empaquetados en campos de bits, como se describe en `landmark_hash`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PackedHash {
+    /// Ancho en Hz de los intervalos de frecuencia.
+    pub freq_bucket_hz: f32,
+    /// Ancho en segundos de los intervalos de `delta_t`.
+    pub delta_t_bucket: f32,
+}
+
+impl PackedHash {
+    /// Crea la función con los intervalos de `config`.
+    pub fn new(config: &FingerprintConfig) -> Self {
+        Self {
+            freq_bucket_hz: config.freq_bucket_hz,
+            delta_t_bucket: config.delta_t_bucket,
+        }
+    }
+}
+
+impl HashFunction for PackedHash {
+    fn hash(&self, anchor: &Peak, target: &Peak) -> u64 {
+        let delta_t = quantize(target.time - anchor.time, self.delta_t_bucket, DELTA_T_BITS);
+        let target = quantize(target.frequency, self.freq_bucket_hz, FREQ_BITS);
+        let anchor = quantize(anchor.frequency, self.freq_bucket_hz, FREQ_BITS);
+
+        (anchor << (FREQ_BITS + DELTA_T_BITS)) | (target << DELTA_T_BITS) | delta_t
+    }
+}
+
+/// [`HashFunction`] de `HashScheme::Sha1`: el hash de las huellas antiguas, truncado a un
+/// `u64`.
+#[cfg(feature = "sha1")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sha1Hash {
+    /// Ancho en Hz de los intervalos de frecuencia.
+    pub freq_bucket_hz: f32,
+    /// Ancho en segundos de los intervalos de `delta_t`.
+    pub delta_t_bucket: f32,
+}
+
+#[cfg(feature = "sha1")]
+impl Sha1Hash {
+    /// Crea la función con los intervalos de `config`.
+    pub fn new(config: &FingerprintConfig) -> Self {
+        Self {
+            freq_bucket_hz: config.freq_bucket_hz,
+            delta_t_bucket: config.delta_t_bucket,
+        }
+    }
+}
+
+#[cfg(feature = "sha1")]
+impl HashFunction for Sha1Hash {
+    fn hash(&self, anchor: &Peak, target: &Peak) -> u64 {
+        use alloc::format;
+        use sha1::{Digest, Sha1};
+
+        let input = format!(
+            "{}|{}|{}",
+            bucket_index(anchor.frequency, self.freq_bucket_hz),
+            bucket_index(target.frequency, self.freq_bucket_hz),
+            bucket_index(target.time - anchor.time, self.delta_t_bucket)
+        );
+
+        let digest = Sha1::digest(input.as_bytes());
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(prefix)
+    }
+}
+
+/// [`HashFunction`] que pasa los índices de las dos frecuencias y de `delta_t`, sin saturar y
+/// en little-endian, por un [`core::hash::Hasher`] nuevo de tipo `H`.
+///
+/// `H` debe ser determinista: un hasher con semilla aleatoria daría hashes distintos en cada
+/// ejecución. Con el [`Fnv1a`] por defecto es la función de `HashScheme::Fnv1a`; con otro
+/// hasher (MurmurHash, xxHash) sirve para comparar esquemas.
+#[derive(Debug)]
+pub struct TupleHash<H = Fnv1a> {
+    /// Ancho en Hz de los intervalos de frecuencia.
+    pub freq_bucket_hz: f32,
+    /// Ancho en segundos de los intervalos de `delta_t`.
+    pub delta_t_bucket: f32,
+    hasher: PhantomData<fn() -> H>,
+}
+
+impl<H> TupleHash<H> {
+    /// Crea la función con los intervalos de `config`.
+    pub fn new(config: &FingerprintConfig) -> Self {
+        Self {
+            freq_bucket_hz: config.freq_bucket_hz,
+            delta_t_bucket: config.delta_t_bucket,
+            hasher: PhantomData,
+        }
+    }
+}
+
+impl<H: core::hash::Hasher + Default> HashFunction for TupleHash<H> {
+    fn hash(&self, anchor: &Peak, target: &Peak) -> u64 {
+        let mut hasher = H::default();
+        for index in [
+            bucket_index(anchor.frequency, self.freq_bucket_hz),
+            bucket_index(target.frequency, self.freq_bucket_hz),
+            bucket_index(target.time - anchor.time, self.delta_t_bucket),
+        ] {
+            // `write_u32` usaría el orden de bytes nativo y cambiaría el hash entre plataformas.
+            hasher.write(&index.to_le_bytes());
+        }
+        hasher.finish()
+    }
+}
+
+/// Hasher FNV-1a de 64 bits: sin semilla, así que da el mismo resultado en cada ejecución y
+/// en cada plataforma.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl core::hash::Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Estructura para almacenar un hash y su tiempo de ocurrencia.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashEntry {
+    pub hash: u64,
+    pub time: f32,
+}
+
+/// Hash listo para indexar en una base de datos: identifica la canción de origen y conserva el
+/// tiempo del pico ancla.
+#[derive(Debug, Clone)]
+pub struct DbHashEntry {
+    pub hash: u64,
+    pub song_id: u32,
+    pub anchor_time: f32,
+}
+
+/// Genera hashes únicos a partir de los picos detectados.
+///
+/// Cada pico ancla se empareja con los `fan_value` picos siguientes separados como mucho
+/// `max_delta_t` segundos. Con `target_zone` se empareja en cambio con los primeros
+/// `fan_value` picos que caen dentro de su [`TargetZone`]; sin ella se mantiene el
+/// emparejamiento anterior y las huellas ya generadas siguen siendo reproducibles.
+///
+/// Con `anchor_stride = N > 1` los picos se ordenan por magnitud y solo los de rango
+/// `0, N, 2N, ...` actúan como anclas, mientras que todos siguen pudiendo ser destino: con
+/// `N = 2` se generan más o menos la mitad de hashes, y la base de datos ocupa menos a cambio
+/// de perder exhaustividad, porque una consulta necesita que sus anclas coincidan con las de
+/// la referencia.
+///
+/// Con `dedup` se descartan las repeticiones de cada hash según `max_hash_repeats`, de modo
+/// que el audio repetitivo no llena la base de datos ni acapara los histogramas de
+/// `match_query`.
+///
+/// # Argumentos
+///
+/// * `peaks` - Vector de picos detectados.
+/// * `config` - Parámetros del pipeline (`fan_value`, `max_delta_t`, `target_zone`,
+///   `anchor_stride`, `dedup`, `max_hash_repeats` y los intervalos de `landmark_hash`).
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Vector de hashes generados, ordenado de forma no
+///   decreciente por el tiempo del pico ancla, o `InvalidPeak` si algún pico tiene un tiempo o
+///   una frecuencia NaN. Los picos se ordenan por `(time, frequency)` antes de emparejarlos y a
+///   igual magnitud el ranking de anclas respeta ese orden, así que el resultado es el mismo
+///   en cada ejecución y no depende del orden de los picos de entrada (por ejemplo, desde una
+///   detección de picos paralela); a igual tiempo se conserva el orden de generación.
+pub fn generate_hashes(
+    peaks: &[Peak],
+    config: &FingerprintConfig,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    generate_hashes_with(peaks, config, &*config.hash_scheme.hash_function(config))
+}
+
+/// Como `generate_hashes`, pero con una función de hash a elección en lugar de la de
+/// `hash_scheme`.
+///
+/// # Argumentos
+///
+/// * `peaks` - Vector de picos detectados.
+/// * `config` - Parámetros del pipeline.
+/// * `hash_function` - Función que calcula el hash de cada landmark.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados, o `InvalidPeak` si algún pico
+///   tiene tiempo o frecuencia NaN.
+pub fn generate_hashes_with(
+    peaks: &[Peak],
+    config: &FingerprintConfig,
+    hash_function: &dyn HashFunction,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    let fan_value = config.fan_value;
+    let max_delta_t = config.max_delta_t;
+    let mut hashes = Vec::new();
+    let mut peaks_sorted = peaks.to_vec();
+
+    // Ordenar los picos por tiempo y frecuencia; sin NaN el orden es total.
+    if peaks_sorted
+        .iter()
+        .any(|peak| peak.time.is_nan() || peak.frequency.is_nan())
+    {
+        return Err(SonoraError::InvalidPeak);
+    }
+    peaks_sorted.sort_by(|a, b| {
+        a.time
+            .total_cmp(&b.time)
+            .then(a.frequency.total_cmp(&b.frequency))
+    });
+
+    // Marcar como anclas uno de cada `anchor_stride` picos según su intensidad; la ordenación
+    // es estable, así que los empates conservan el orden anterior.
+    let stride = config.anchor_stride.max(1);
+    let mut by_strength: Vec<usize> = (0..peaks_sorted.len()).collect();
+    by_strength.sort_by(|&a, &b| {
+        peaks_sorted[b]
+            .magnitude
+            .total_cmp(&peaks_sorted[a].magnitude)
+    });
+    let mut is_anchor = vec![false; peaks_sorted.len()];
+    for (rank, &i) in by_strength.iter().enumerate() {
+        is_anchor[i] = rank % stride == 0;
+    }
+
+    for i in 0..peaks_sorted.len() {
+        if !is_anchor[i] {
+            continue;
+        }
+        let current_peak = &peaks_sorted[i];
+        let following = &peaks_sorted[i + 1..];
+        let mut push = |paired_peak: &Peak| {
+            hashes.push(HashEntry {
+                hash: hash_function.hash(current_peak, paired_peak),
+                time: current_peak.time,
+            });
+        };
+
+        match config.target_zone {
+            // Los `fan_value` picos siguientes, sin mirar su frecuencia.
+            None => following
+                .iter()
+                .take(fan_value)
+                .take_while(|peak| peak.time - current_peak.time <= max_delta_t)
+                .for_each(&mut push),
+            // Los primeros `fan_value` picos dentro de la zona por delante del ancla.
+            Some(zone) => following
+                .iter()
+                .take_while(|peak| peak.time - current_peak.time <= zone.max_dt)
+                .filter(|peak| {
+                    peak.time - current_peak.time >= zone.min_dt
+                        && (peak.frequency - current_peak.frequency).abs() <= zone.freq_band
+                })
+                .take(fan_value)
+                .for_each(&mut push),
+        }
+    }
+
+    // Garantizar el orden por tiempo del ancla; la ordenación es estable.
+    hashes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+    if config.dedup {
+        // Apariciones conservadas y último tiempo de cada hash; al recorrer en orden de tiempo
+        // se quedan las primeras.
+        let mut seen: HashMap<u64, (usize, f32)> = HashMap::new();
+        hashes.retain(|entry| {
+            let (count, last_time) = seen.entry(entry.hash).or_insert((0, f32::NEG_INFINITY));
+            let capped = config.max_hash_repeats > 0 && *count >= config.max_hash_repeats;
+            if capped || *last_time == entry.time {
+                return false;
+            }
+            *count += 1;
+            *last_time = entry.time;
+            true
+        });
+    }
+
+    Ok(hashes)
+}
+
+/// Calcula el hash de un landmark: el par formado por un pico ancla y un pico destino.
+///
+/// Antes del hash las dos frecuencias se redondean al intervalo de `freq_bucket_hz` Hz más
+/// cercano y `delta_t` al de `delta_t_bucket` segundos más cercano, y se codifica el índice del
+/// intervalo. Así un pico que se desplaza un bin entre la consulta y la referencia (algo
+/// habitual tras recodificar el audio) suele dar el mismo hash, a cambio de algo de
+/// selectividad. Un ancho de 0 deja solo el redondeo al Hz o al segundo.
+///
+/// Los tres índices se empaquetan en un `u64`, igual que `pack_band_fields` en los motores por
+/// bandas: 12 bits para la frecuencia del ancla, 12 para la del destino y 14 para `delta_t`,
+/// de más a menos significativo. Un índice que no cabe en su campo se satura al máximo. Con
+/// los valores por defecto sobra margen: 5512 Hz / 20 Hz y 5 s / 50 ms quedan muy por debajo.
+///
+/// Con `hash_scheme = HashScheme::Sha1` (feature `sha1`) los índices, sin saturar, pasan en
+/// cambio por SHA-1 como en las huellas antiguas, para poder consultar bases de datos
+/// construidas con ellas, y con `HashScheme::Fnv1a` por FNV-1a.
+///
+/// La función es pura: las mismas entradas y la misma configuración dan siempre el mismo hash.
+///
+/// # Argumentos
+///
+/// * `anchor_freq` - Frecuencia del pico ancla, en Hz.
+/// * `target_freq` - Frecuencia del pico destino, en Hz.
+/// * `delta_t` - Separación entre ambos picos, en segundos.
+/// * `config` - Parámetros del pipeline (`freq_bucket_hz`, `delta_t_bucket`, `hash_scheme`).
+///
+/// # Retorna
+///
+/// * `u64` - Los tres índices empaquetados, de los que solo se usan los 38 bits bajos, o el
+///   prefijo del SHA-1.
+pub fn landmark_hash(
+    anchor_freq: f32,
+    target_freq: f32,
+    delta_t: f32,
+    config: &FingerprintConfig,
+) -> u64 {
+    let peak = |time, frequency| Peak {
+        time,
+        frequency,
+        magnitude: 0.0,
+    };
+    config
+        .hash_scheme
+        .hash_function(config)
+        .hash(&peak(0.0, anchor_freq), &peak(delta_t, target_freq))
+}
+
+/// Índice del intervalo de ancho `bucket` más cercano a `value`, sin saturar, como en las
+/// huellas SHA-1: solo el `as u32` acota los extremos.
+fn bucket_index(value: f32, bucket: f32) -> u32 {
+    let value = if bucket > 0.0 { value / bucket } else { value };
+    value.round() as u32
+}
+
+/// Índice del intervalo de ancho `bucket` más cercano a `value`, saturado a `bits` bits; con
+/// ancho 0, `value` redondeado.
+fn quantize(value: f32, bucket: f32, bits: u32) -> u64 {
+    let index = if bucket > 0.0 {
+        (value / bucket).round()
+    } else {
+        value.round()
+    };
+    // `as` satura los negativos a 0 y los demasiado grandes a `u64::MAX`.
+    (index as u64).min((1 << bits) - 1)
+}
+
+/// Genera los hashes de una canción en forma de tripletas `(hash, song_id, anchor_time)`.
+///
+/// # Argumentos
+///
+/// * `peaks` - Vector de picos detectados.
+/// * `config` - Parámetros del pipeline.
+/// * `song_id` - Identificador de la canción a la que pertenecen los picos.
+///
+/// # Retorna
+///
+/// * `Result<Vec<DbHashEntry>, SonoraError>` - Hashes generados, en el mismo orden que
+///   `generate_hashes`, o su error.
+pub fn generate_db_hashes(
+    peaks: &[Peak],
+    config: &FingerprintConfig,
+    song_id: u32,
+) -> Result<Vec<DbHashEntry>, SonoraError> {
+    Ok(generate_hashes(peaks, config)?
+        .into_iter()
+        .map(|entry| DbHashEntry {
+            hash: entry.hash,
+            song_id,
+            anchor_time: entry.time,
+        })
+        .collect())
+}
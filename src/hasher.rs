@@ -0,0 +1,129 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::band::{band_hash, band_index, update_band_max};
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::landmark_hashes;
+use crate::peaks::{NeighborhoodDetector, PeakDetector};
+use crate::progress::ProgressSink;
+use crate::spectrogram::Spectrogram;
+
+/// Esquema de hash que convierte un espectrograma en huellas.
+///
+/// Todos los esquemas comparten la decodificación, el remuestreo y la FFT del pipeline; solo
+/// cambia cómo se resume cada ventana. Las huellas de esquemas distintos no son comparables
+/// entre sí, así que una base de datos debe construirse y consultarse con el mismo.
+pub trait Hasher: Send + Sync {
+    /// Genera las huellas de un espectrograma.
+    ///
+    /// # Argumentos
+    ///
+    /// * `spectrogram` - Espectrograma de entrada.
+    /// * `config` - Parámetros del pipeline.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error que lo impidió.
+    fn hash(
+        &self,
+        spectrogram: &Spectrogram,
+        config: &FingerprintConfig,
+    ) -> Result<Vec<HashEntry>, SonoraError>;
+
+    /// Como `hash`, pero avisa a `progress` de las etapas intermedias (por ejemplo, los picos
+    /// detectados). Por defecto llama a `hash` sin avisar de nada.
+    fn hash_with_progress(
+        &self,
+        spectrogram: &Spectrogram,
+        config: &FingerprintConfig,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        let _ = progress;
+        self.hash(spectrogram, config)
+    }
+}
+
+/// Pares de picos (landmarks), el esquema por defecto: es `fingerprint_from_spectrogram_with`
+/// con el detector de picos `D`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LandmarkHasher<D = NeighborhoodDetector> {
+    detector: D,
+}
+
+impl<D: PeakDetector> LandmarkHasher<D> {
+    /// Crea el esquema con un detector de picos a elección.
+    pub fn new(detector: D) -> Self {
+        Self { detector }
+    }
+}
+
+impl<D: PeakDetector> Hasher for LandmarkHasher<D> {
+    fn hash(
+        &self,
+        spectrogram: &Spectrogram,
+        config: &FingerprintConfig,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        landmark_hashes(spectrogram, config, &self.detector, None)
+    }
+
+    fn hash_with_progress(
+        &self,
+        spectrogram: &Spectrogram,
+        config: &FingerprintConfig,
+        progress: &dyn ProgressSink,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        landmark_hashes(spectrogram, config, &self.detector, Some(progress))
+    }
+}
+
+/// Bin dominante por banda, el esquema de `band_fingerprints`.
+///
+/// Emite un hash por ventana con `band_hash`. Los límites de `bands`, `min_freq` y `max_freq`
+/// se interpretan como bins del espectrograma (de `window_size` puntos) y no de los bloques de
+/// `chunk_size` de los motores, así que los hashes no coinciden con los de `band_fingerprints`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BandHasher;
+
+impl Hasher for BandHasher {
+    fn hash(
+        &self,
+        spectrogram: &Spectrogram,
+        config: &FingerprintConfig,
+    ) -> Result<Vec<HashEntry>, SonoraError> {
+        config.bands.check_max_freq(config.max_freq)?;
+        let mut points = vec![0usize; config.max_freq + 1];
+        let mut scores = vec![0.0f32; config.max_freq + 1];
+
+        let entries = spectrogram
+            .magnitudes
+            .iter()
+            .zip(&spectrogram.times)
+            .map(|(frame, &time)| {
+                points.fill(0);
+                scores.fill(0.0);
+                for (freq, &mag) in frame
+                    .iter()
+                    .enumerate()
+                    .take(config.max_freq + 1)
+                    .skip(config.min_freq)
+                {
+                    update_band_max(
+                        &mut points,
+                        &mut scores,
+                        band_index(freq, config),
+                        freq,
+                        mag,
+                    );
+                }
+                Ok(HashEntry {
+                    hash: band_hash(&points, config)? as u64,
+                    time,
+                })
+            })
+            .collect();
+
+        entries
+    }
+}
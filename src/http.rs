@@ -0,0 +1,415 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::SonoraError;
+use crate::source::{AudioSource, FileSource};
+
+/// Tiempo máximo para establecer la conexión con el servidor.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tiempo sin recibir datos tras el que la conexión se da por caída.
+const READ_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Redirecciones que se siguen antes de abandonar.
+const MAX_REDIRECTS: usize = 5;
+
+/// Cuándo y cuántas veces reintenta [`HttpSource`] la conexión tras un corte.
+///
+/// La espera entre intentos empieza en `initial_delay` y se duplica en cada fallo seguido
+/// hasta `max_delay`; vuelve a empezar en cuanto la conexión entrega audio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reconnect {
+    /// Intentos seguidos antes de dar el flujo por perdido; `None` reintenta siempre.
+    pub max_attempts: Option<u32>,
+    /// Espera antes del primer intento.
+    pub initial_delay: Duration,
+    /// Espera máxima entre dos intentos.
+    pub max_delay: Duration,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Origen de audio que decodifica un flujo HTTP, como una radio Icecast o SHOUTcast.
+///
+/// El contenedor se detecta con el `Content-Type` de la respuesta o, si no lo hay, con la
+/// extensión de la URL. Solo admite `http://`; las redirecciones se siguen. Un flujo en
+/// directo no termina nunca: si la conexión se corta, deja de entregar datos o el audio que
+/// llega no se puede decodificar, se vuelve a conectar según [`Reconnect`] y la lectura sigue
+/// donde estaba, así que los tiempos de las huellas cuentan el audio recibido y no el tiempo
+/// de reloj. Una respuesta con `Content-Length`, como un archivo servido por HTTP, termina al
+/// recibirla entera.
+pub struct HttpSource {
+    url: String,
+    reconnect: Reconnect,
+    inner: FileSource,
+    /// Se activa si la conexión actual se cortó antes de terminar la respuesta.
+    dropped: Arc<AtomicBool>,
+    channels: usize,
+    sample_rate: u32,
+    /// Bytes y paquetes omitidos de las conexiones anteriores.
+    bytes_before: u64,
+    skipped_before: u64,
+    reconnects: u64,
+}
+
+impl HttpSource {
+    /// Conecta con la URL y prepara el decodificador, reintentando los cortes posteriores con
+    /// la política por defecto.
+    ///
+    /// # Argumentos
+    ///
+    /// * `url` - URL `http://` del flujo.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<HttpSource, SonoraError>` - El origen, o el error de la primera conexión: esa
+    ///   no se reintenta, para que una URL equivocada falle enseguida.
+    pub fn open(url: &str) -> Result<Self, SonoraError> {
+        Self::with_reconnect(url, Reconnect::default())
+    }
+
+    /// Como `open`, con otra política de reconexión.
+    ///
+    /// # Argumentos
+    ///
+    /// * `url` - URL `http://` del flujo.
+    /// * `reconnect` - Cuándo y cuántas veces reintentar tras un corte.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<HttpSource, SonoraError>` - El origen, o el error como en `open`.
+    pub fn with_reconnect(url: &str, reconnect: Reconnect) -> Result<Self, SonoraError> {
+        let (inner, dropped) = connect(url)?;
+        Ok(Self {
+            url: url.to_string(),
+            reconnect,
+            channels: inner.channels(),
+            sample_rate: inner.sample_rate(),
+            inner,
+            dropped,
+            bytes_before: 0,
+            skipped_before: 0,
+            reconnects: 0,
+        })
+    }
+
+    /// Veces que se ha vuelto a conectar tras un corte.
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    /// Vuelve a conectar esperando entre intentos; falla si se agotan.
+    fn reopen(&mut self) -> Result<(), SonoraError> {
+        let mut delay = self.reconnect.initial_delay;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            thread::sleep(delay);
+            let error = match connect(&self.url) {
+                Ok((inner, _))
+                    if (inner.channels(), inner.sample_rate())
+                        != (self.channels, self.sample_rate) =>
+                {
+                    return Err(SonoraError::Http(format!(
+                        "el flujo cambió de formato al reconectar: {} canales a {} Hz",
+                        inner.channels(),
+                        inner.sample_rate()
+                    )));
+                }
+                Ok((inner, dropped)) => {
+                    self.bytes_before += self.inner.bytes_read().unwrap_or(0);
+                    self.skipped_before += self.inner.skipped_packets();
+                    self.inner = inner;
+                    self.dropped = dropped;
+                    self.reconnects += 1;
+                    tracing::info!(attempt, url = %self.url, "flujo reconectado");
+                    return Ok(());
+                }
+                Err(e) => e,
+            };
+            tracing::warn!(attempt, error = %error, "no se pudo reconectar");
+            if self
+                .reconnect
+                .max_attempts
+                .is_some_and(|max| attempt >= max)
+            {
+                return Err(error);
+            }
+            delay = (delay * 2).min(self.reconnect.max_delay);
+        }
+    }
+}
+
+impl AudioSource for HttpSource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        loop {
+            match self.inner.read_frames(buf) {
+                Ok(0) if !self.dropped.load(Ordering::Relaxed) => return Ok(0),
+                Ok(0) => tracing::warn!(url = %self.url, "el flujo se cortó"),
+                Ok(frames) => return Ok(frames),
+                Err(e) => tracing::warn!(url = %self.url, error = %e, "el flujo se cortó"),
+            }
+            self.reopen()?;
+        }
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.bytes_before + self.inner.bytes_read().unwrap_or(0))
+    }
+
+    fn skipped_packets(&self) -> u64 {
+        self.skipped_before + self.inner.skipped_packets()
+    }
+}
+
+/// Pide la URL, siguiendo las redirecciones, y abre el decodificador sobre el cuerpo.
+fn connect(url: &str) -> Result<(FileSource, Arc<AtomicBool>), SonoraError> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let (host, port, path) = parse_url(&url)?;
+        let response = request(&host, port, &path)?;
+        if let Some(location) = response.location {
+            url = if location.starts_with('/') {
+                format!("http://{}{}", host_port(&host, port), location)
+            } else {
+                location
+            };
+            continue;
+        }
+
+        let dropped = Arc::new(AtomicBool::new(false));
+        let body = Body {
+            reader: response.reader,
+            remaining: response.content_length,
+            chunked: response.chunked.then_some(0),
+            dropped: Arc::clone(&dropped),
+        };
+        let extension = response
+            .content_type
+            .as_deref()
+            .and_then(content_type_extension)
+            .or_else(|| Path::new(&path).extension().and_then(|e| e.to_str()));
+        let source = match extension {
+            Some(extension) => FileSource::from_reader_with_extension(body, extension)?,
+            None => FileSource::from_reader(body)?,
+        };
+        return Ok((source, dropped));
+    }
+    Err(SonoraError::Http(format!(
+        "demasiadas redirecciones desde {}",
+        url
+    )))
+}
+
+/// Separa una URL `http://` en servidor, puerto y ruta.
+fn parse_url(url: &str) -> Result<(String, u16, String), SonoraError> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| SonoraError::Http(format!("solo se admiten URL http://: {}", url)))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    // Las credenciales no se usan; los corchetes de IPv6 no forman parte del nombre.
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => {
+            let port = port
+                .parse()
+                .map_err(|_| SonoraError::Http(format!("puerto no válido en {}", url)))?;
+            (host, port)
+        }
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(SonoraError::Http(format!("falta el servidor en {}", url)));
+    }
+    Ok((host.to_string(), port, path.to_string()))
+}
+
+/// Servidor y puerto tal como van en una URL o en la cabecera `Host`.
+fn host_port(host: &str, port: u16) -> String {
+    match (host.contains(':'), port) {
+        (false, 80) => host.to_string(),
+        (false, _) => format!("{}:{}", host, port),
+        (true, _) => format!("[{}]:{}", host, port),
+    }
+}
+
+/// Cabecera de una respuesta, con el cuerpo listo para leer.
+struct Response {
+    reader: BufReader<TcpStream>,
+    location: Option<String>,
+    content_type: Option<String>,
+    content_length: Option<u64>,
+    chunked: bool,
+}
+
+/// Envía un GET y lee la cabecera de la respuesta.
+///
+/// Acepta la línea de estado `ICY 200 OK` de los servidores SHOUTcast. No pide los metadatos
+/// intercalados de Icecast (`Icy-MetaData`), así que el cuerpo es solo audio.
+fn request(host: &str, port: u16, path: &str) -> Result<Response, SonoraError> {
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| SonoraError::Http(format!("no se pudo resolver {}", host)))?;
+    let mut stream = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: sonora/{}\r\nAccept: */*\r\n\
+         Connection: close\r\n\r\n",
+        path,
+        host_port(host, port),
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let status: u16 = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| SonoraError::Http(format!("respuesta no válida: {}", line.trim())))?;
+
+    let mut response = Response {
+        reader,
+        location: None,
+        content_type: None,
+        content_length: None,
+        chunked: false,
+    };
+    loop {
+        line.clear();
+        if response.reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "location" => response.location = Some(value.to_string()),
+            "content-type" => response.content_type = Some(value.to_ascii_lowercase()),
+            "content-length" => response.content_length = value.parse().ok(),
+            "transfer-encoding" => response.chunked = value.eq_ignore_ascii_case("chunked"),
+            _ => {}
+        }
+    }
+
+    match status {
+        200..=299 => {
+            response.location = None;
+            Ok(response)
+        }
+        300..=399 if response.location.is_some() => Ok(response),
+        _ => Err(SonoraError::Http(format!(
+            "el servidor respondió {} para {}",
+            status, path
+        ))),
+    }
+}
+
+/// Extensión con la que detectar el contenedor según el `Content-Type`.
+fn content_type_extension(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim();
+    Some(match mime {
+        "audio/mpeg" | "audio/mp3" => "mp3",
+        "audio/aac" | "audio/aacp" => "aac",
+        "audio/mp4" | "audio/x-m4a" => "m4a",
+        "audio/ogg" | "application/ogg" | "audio/vorbis" => "ogg",
+        "audio/flac" | "audio/x-flac" => "flac",
+        "audio/wav" | "audio/x-wav" | "audio/wave" => "wav",
+        "audio/webm" | "audio/x-matroska" => "mkv",
+        _ => return None,
+    })
+}
+
+/// Cuerpo de una respuesta: hasta `Content-Length`, troceado o hasta que se cierre.
+struct Body {
+    reader: BufReader<TcpStream>,
+    remaining: Option<u64>,
+    /// Bytes que quedan del trozo actual si la respuesta viene troceada.
+    chunked: Option<u64>,
+    dropped: Arc<AtomicBool>,
+}
+
+impl Read for Body {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.read_body(buf);
+        // Sin longitud ni trozos, el cuerpo no tiene final: que se cierre es un corte.
+        if matches!(result, Ok(0) | Err(_)) && !buf.is_empty() && self.remaining != Some(0) {
+            self.dropped.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl Body {
+    fn read_body(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.chunked == Some(0) {
+            match self.next_chunk()? {
+                // Último trozo: no queda nada más del cuerpo.
+                0 => (self.remaining, self.chunked) = (Some(0), None),
+                size => self.chunked = Some(size),
+            }
+        }
+        let mut limit = buf.len() as u64;
+        for bound in [self.remaining, self.chunked].into_iter().flatten() {
+            limit = limit.min(bound);
+        }
+        if limit == 0 {
+            return Ok(0);
+        }
+        let read = self.reader.read(&mut buf[..limit as usize])?;
+        for bound in [&mut self.remaining, &mut self.chunked]
+            .into_iter()
+            .flatten()
+        {
+            *bound -= read as u64;
+        }
+        Ok(read)
+    }
+
+    /// Lee la cabecera del siguiente trozo, tras el fin de línea del anterior, y devuelve su
+    /// tamaño; 0 marca el último.
+    fn next_chunk(&mut self) -> io::Result<u64> {
+        let mut line = String::new();
+        while line.trim().is_empty() {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        let size = line.trim().split(';').next().unwrap_or_default();
+        u64::from_str_radix(size.trim(), 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "trozo HTTP no válido"))
+    }
+}
@@ -0,0 +1,453 @@
+//! Generación de huellas acústicas al estilo Shazam.
+//!
+//! El pipeline completo es `load_audio` → `calculate_spectrogram` → `find_peaks` →
+//! `generate_hashes`; `fingerprint_file` lo ejecuta de una vez con una [`FingerprintConfig`], y
+//! [`Fingerprinter`] hace lo mismo con una configuración validada por su constructor, también
+//! sobre cualquier lector de bytes. `fingerprint_source` acepta cualquier [`AudioSource`]
+//! (archivos, memoria, flujos HTTP o micrófono), y `fingerprint_source_with` cualquier esquema
+//! de hash ([`Hasher`]) sobre el mismo espectrograma.
+//! Las huellas de referencia se guardan en una [`FingerprintDb`] y `match_query` identifica una
+//! consulta contra ella; `spectral_features` resume el timbre para descartar candidatos. El
+//! hash por bandas se calcula con `band_fingerprints` y cualquiera de los motores de
+//! [`Engine`], y sus primitivas también se exponen aquí.
+//!
+//! Sin la feature `std` (activa por defecto) la biblioteca es `no_std` + `alloc` y solo deja el
+//! núcleo DSP, pensado para dispositivos que capturan el audio por su cuenta: las ventanas, el
+//! espectrograma sobre una [`Transform`] propia, la detección de picos, el emparejamiento y el
+//! empaquetado de los hashes (de landmarks y por bandas), el remuestreo, el diezmado del
+//! módulo [`dsp`] y la configuración. La decodificación con symphonia, los archivos, la FFT de
+//! rustfft, la base de datos y los motores paralelos necesitan `std`. Con la feature `tokio`,
+//! `stream_source_async` y los métodos `*_async` de [`Fingerprinter`] calculan en el pool de
+//! tareas bloqueantes de tokio para no bloquear el runtime.
+//!
+//! Cada etapa (`decode`, `resample`, `fft`, `peaks`, `hash`, `write`, los motores por bandas y el
+//! streaming) abre un span de `tracing` con campos como las muestras procesadas o la profundidad
+//! de las colas; cualquier suscriptor puede medir su duración. Sin suscriptor no cuestan casi
+//! nada.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+mod async_stream;
+mod band;
+#[cfg(feature = "std")]
+mod batch;
+mod cancel;
+mod config;
+#[cfg(feature = "std")]
+mod db;
+#[cfg(feature = "std")]
+mod decode;
+pub mod dsp;
+#[cfg(feature = "std")]
+mod engine;
+mod error;
+#[cfg(feature = "std")]
+mod eval;
+mod features;
+#[cfg(feature = "std")]
+mod fingerprinter;
+#[cfg(feature = "std")]
+mod format;
+mod hash;
+mod hasher;
+#[cfg(feature = "std")]
+mod http;
+#[cfg(feature = "std")]
+mod matching;
+#[cfg(feature = "mic")]
+mod mic;
+mod peaks;
+mod progress;
+mod resample;
+mod sample;
+mod sink;
+#[cfg(feature = "std")]
+mod source;
+mod spectrogram;
+#[cfg(feature = "std")]
+mod stream;
+mod transform;
+
+#[cfg(feature = "tokio")]
+pub use async_stream::{
+    fingerprint_file_stream_async, stream_source_async, AsyncFingerprintStream,
+};
+pub use band::{
+    band_hash, band_index, band_lookup, bin_magnitudes, chunk_band_hash, chunk_count,
+    pack_band_fields, remove_dc, update_band_max, Bands,
+};
+#[cfg(feature = "std")]
+pub use batch::{fingerprint_dir, index_dir, BatchSummary, Checkpoint};
+pub use cancel::CancelToken;
+pub use config::{ChannelPolicy, FingerprintConfig, NormalizeOrder};
+#[cfg(feature = "std")]
+pub use db::{DbInserter, FingerprintDb, SongMeta};
+#[cfg(feature = "std")]
+pub use decode::{
+    decode_is_deterministic, decode_reader, decode_to_samples, decode_track_to_samples,
+    downmix_to_mono, interleaved_to_mono, load_audio, load_audio_with_order, pcm_i16_to_mono,
+    DecodedAudio,
+};
+#[cfg(feature = "std")]
+pub use engine::{band_fingerprints, Engine};
+pub use error::{Error, SonoraError};
+#[cfg(feature = "std")]
+pub use eval::{evaluate, EvalReport, QueryOutcome, ThresholdPoint};
+pub use features::{spectral_features, spectral_flux, SpectralSummary};
+#[cfg(feature = "std")]
+pub use fingerprinter::{Fingerprinter, FingerprinterBuilder};
+#[cfg(feature = "std")]
+pub use format::{read_fingerprints, write_fingerprints, FingerprintFormat, FingerprintWriter};
+#[cfg(feature = "sha1")]
+pub use hash::Sha1Hash;
+pub use hash::{
+    generate_db_hashes, generate_hashes, generate_hashes_with, landmark_hash, DbHashEntry, Fnv1a,
+    HashEntry, HashFunction, HashScheme, PackedHash, TargetZone, TupleHash,
+};
+pub use hasher::{BandHasher, Hasher, LandmarkHasher};
+#[cfg(feature = "std")]
+pub use http::{HttpSource, Reconnect};
+#[cfg(feature = "std")]
+pub use matching::{match_query, match_query_with, MatchConfig, MatchResult, MatchSegment};
+#[cfg(feature = "mic")]
+pub use mic::{capture_microphone, capture_microphone_at, MicSource};
+pub use peaks::{find_peaks, NeighborhoodDetector, Peak, PeakDetector};
+pub use progress::{Progress, ProgressSink};
+pub use resample::{resample, CANONICAL_SAMPLE_RATE};
+pub use sample::{FftFloat, Sample};
+pub use sink::HashSink;
+#[cfg(feature = "std")]
+pub use source::{AudioSource, Excerpt, FileSource, MemorySource, PcmFormat, RawSource};
+#[cfg(feature = "std")]
+pub use spectrogram::{cached_window, calculate_spectrogram};
+pub use spectrogram::{
+    calculate_spectrogram_with, hann_window, harmonic_product_spectrum, window, Spectrogram,
+    WindowFn,
+};
+#[cfg(feature = "std")]
+pub use stream::{
+    fingerprint_file_stream, stream_fingerprints, stream_source, FingerprintStream,
+    IncrementalFingerprinter,
+};
+#[cfg(feature = "std")]
+pub use transform::RustFftTransform;
+pub use transform::Transform;
+
+use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+#[cfg(feature = "std")]
+use progress::Hooks;
+#[cfg(feature = "std")]
+use source::ChannelSource;
+
+/// Versión del formato de las huellas.
+///
+/// Cambia cada vez que un cambio del algoritmo hace que los hashes dejen de ser comparables
+/// con los anteriores, o cuando cambia cómo se guardan; se guarda en la cabecera de las bases
+/// de datos y `FingerprintDb::load` rechaza las de otra versión.
+pub const FINGERPRINT_VERSION: u32 = 4;
+
+/// Devuelve la versión del formato de las huellas que genera esta biblioteca.
+///
+/// # Retorna
+///
+/// * `u32` - [`FINGERPRINT_VERSION`].
+pub fn version() -> u32 {
+    FINGERPRINT_VERSION
+}
+
+/// Genera las huellas de un archivo de audio ejecutando el pipeline completo.
+///
+/// El audio se remuestrea a `canonical_rate` ([`CANONICAL_SAMPLE_RATE`] por defecto) antes del
+/// espectrograma, de modo que las huellas de un mismo contenido coinciden aunque los archivos
+/// tengan tasas distintas. Con `max_memory` el archivo se procesa en streaming y no se carga
+/// entero.
+///
+/// # Argumentos
+///
+/// * `path` - Ruta al archivo de audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error de decodificación.
+#[cfg(feature = "std")]
+pub fn fingerprint_file(
+    path: &str,
+    config: &FingerprintConfig,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    fingerprint_source(FileSource::open(path)?, config)
+}
+
+/// Genera las huellas de todo el audio de un [`AudioSource`].
+///
+/// Lee el origen hasta el final, lo reduce a mono según `channel_policy` y lo normaliza según
+/// `normalize_order`, y sigue como `fingerprint_file`. Para un origen que no termina, como una
+/// captura en vivo, hay que limitar su duración o usar `stream_source`.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error de lectura.
+#[cfg(feature = "std")]
+pub fn fingerprint_source<S: AudioSource>(
+    source: S,
+    config: &FingerprintConfig,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    fingerprint_source_with(source, config, &LandmarkHasher::new(NeighborhoodDetector))
+}
+
+/// Como `fingerprint_source`, pero con un esquema de hash a elección.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Parámetros del pipeline.
+/// * `hasher` - Esquema que convierte el espectrograma en huellas.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados o el error de lectura.
+#[cfg(feature = "std")]
+pub fn fingerprint_source_with<S: AudioSource>(
+    source: S,
+    config: &FingerprintConfig,
+    hasher: &dyn Hasher,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    run_pipeline(source, config, hasher, Hooks::default())
+}
+
+/// Genera las huellas de muestras PCM de 16 bits intercaladas ya en memoria.
+///
+/// Pensado para quien recibe el audio de un decodificador propio o de una captura en `i16`:
+/// las muestras se reducen a mono con `pcm_i16_to_mono` según `normalize_order` y siguen el
+/// pipeline de `fingerprint_source`, así que las huellas son las mismas que las de esa señal
+/// en `f32` (cada muestra entre 32768).
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras intercaladas por canal.
+/// * `channels` - Número de canales de la señal.
+/// * `sample_rate` - Tasa de muestreo de la señal, en Hz.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados, o `NoChannels` si `channels`
+///   es 0.
+#[cfg(feature = "std")]
+pub fn fingerprint_i16(
+    samples: &[i16],
+    channels: u16,
+    sample_rate: u32,
+    config: &FingerprintConfig,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    let mono = pcm_i16_to_mono(samples, channels, config.normalize_order)?;
+    fingerprint_source(MemorySource::new(mono, 1, sample_rate), config)
+}
+
+/// Genera una huella independiente por cada canal de un [`AudioSource`].
+///
+/// Pensado para comparar másteres estéreo canal a canal: cada canal se normaliza y se procesa
+/// por separado como una señal mono, con el resto de la configuración igual que en
+/// `fingerprint_source`; `channel_policy` se ignora. El origen se lee entero en memoria. Un
+/// canal en silencio hace fallar la llamada con `SilentAudio`, como en `fingerprint_source`.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<Vec<Vec<HashEntry>>, SonoraError>` - Hashes de cada canal, en el orden de los
+///   canales del origen, o el error de lectura.
+#[cfg(feature = "std")]
+pub fn fingerprint_channels<S: AudioSource>(
+    mut source: S,
+    config: &FingerprintConfig,
+) -> Result<Vec<Vec<HashEntry>>, SonoraError> {
+    let channels = source.channels();
+    if channels == 0 {
+        return Err(SonoraError::NoChannels);
+    }
+    let mut samples = Vec::new();
+    let mut block = vec![0.0; source::BLOCK_FRAMES * channels];
+    loop {
+        let frames = source.read_frames(&mut block)?;
+        if frames == 0 {
+            break;
+        }
+        samples.extend_from_slice(&block[..frames * channels]);
+    }
+
+    let config = FingerprintConfig {
+        channel_policy: ChannelPolicy::Mix,
+        ..config.clone()
+    };
+    (0..channels)
+        .map(|channel| {
+            let _span = tracing::debug_span!("channel", channel).entered();
+            let mono = samples.iter().skip(channel).step_by(channels).copied();
+            let source = MemorySource::new(mono.collect(), 1, source.sample_rate());
+            fingerprint_source(source, &config)
+        })
+        .collect()
+}
+
+/// Pipeline completo sobre un origen; comprueba la cancelación entre bloques de lectura y
+/// entre ventanas de la FFT, y avisa del avance de cada etapa. Con `max_memory` delega en el
+/// pipeline en streaming para no cargar la pista entera.
+#[cfg(feature = "std")]
+pub(crate) fn run_pipeline<S: AudioSource>(
+    source: S,
+    config: &FingerprintConfig,
+    hasher: &dyn Hasher,
+    hooks: Hooks<'_>,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    config.check_window()?;
+    let mut source = ChannelSource::new(source, config.channel_policy)?;
+    if config.max_memory.is_some() {
+        return stream::run_streaming(source, config, hasher, hooks);
+    }
+    let channels = source.channels();
+    if channels == 0 {
+        return Err(SonoraError::NoChannels);
+    }
+    let decode = tracing::debug_span!(
+        "decode",
+        channels,
+        sample_rate = source.sample_rate(),
+        frames = tracing::field::Empty,
+        skipped_packets = tracing::field::Empty
+    )
+    .entered();
+    let mut samples = Vec::new();
+    let mut block = vec![0.0; source::BLOCK_FRAMES * channels];
+    loop {
+        hooks.check()?;
+        let frames = source.read_frames(&mut block)?;
+        if frames == 0 {
+            break;
+        }
+        samples.extend_from_slice(&block[..frames * channels]);
+        hooks.report(Progress::Decoded {
+            frames: (samples.len() / channels) as u64,
+            bytes: source.bytes_read(),
+        });
+    }
+
+    decode.record("frames", samples.len() / channels);
+    decode.record("skipped_packets", source.skipped_packets());
+    let samples = interleaved_to_mono(&samples, channels, config.normalize_order)?;
+    decode.exit();
+    let samples = tracing::debug_span!("resample", from = source.sample_rate())
+        .in_scope(|| resample(&samples, source.sample_rate(), config.canonical_rate));
+    hooks.check()?;
+    let spectrogram = spectrogram::spectrogram_with_hooks(
+        &samples,
+        config.canonical_rate as usize,
+        config,
+        &RustFftTransform::new(config.window_size),
+        hooks,
+    )?;
+
+    hooks.check()?;
+    let hash = tracing::debug_span!("hash", hashes = tracing::field::Empty).entered();
+    let hashes = match hooks.progress {
+        Some(progress) => hasher.hash_with_progress(&spectrogram, config, progress)?,
+        None => hasher.hash(&spectrogram, config)?,
+    };
+    hash.record("hashes", hashes.len());
+    hash.exit();
+    hooks.report(Progress::Hashes {
+        count: hashes.len(),
+    });
+    Ok(hashes)
+}
+
+/// Genera las huellas a partir de un espectrograma ya calculado.
+///
+/// Permite omitir la decodificación y la FFT cuando las magnitudes vienen de otra fuente (por
+/// ejemplo, una FFT en GPU). Aplica el énfasis HPS configurado, la detección de picos, la
+/// puerta de novedad (`novelty_threshold`) y la generación de hashes.
+///
+/// # Argumentos
+///
+/// * `spectrogram` - Espectrograma de entrada.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados, o `InvalidPeak` si el
+///   espectrograma tiene tiempos o frecuencias NaN.
+pub fn fingerprint_from_spectrogram(
+    spectrogram: &Spectrogram,
+    config: &FingerprintConfig,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    fingerprint_from_spectrogram_with(spectrogram, config, &NeighborhoodDetector)
+}
+
+/// Como `fingerprint_from_spectrogram`, pero con un detector de picos a elección.
+///
+/// # Argumentos
+///
+/// * `spectrogram` - Espectrograma de entrada.
+/// * `config` - Parámetros del pipeline.
+/// * `detector` - Detector de picos.
+///
+/// # Retorna
+///
+/// * `Result<Vec<HashEntry>, SonoraError>` - Hashes generados, o `InvalidPeak` si algún pico
+///   tiene tiempo o frecuencia NaN.
+pub fn fingerprint_from_spectrogram_with(
+    spectrogram: &Spectrogram,
+    config: &FingerprintConfig,
+    detector: &dyn PeakDetector,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    landmark_hashes(spectrogram, config, detector, None)
+}
+
+/// Cuerpo de `fingerprint_from_spectrogram_with`; avisa a `progress` de los picos retenidos.
+pub(crate) fn landmark_hashes(
+    spectrogram: &Spectrogram,
+    config: &FingerprintConfig,
+    detector: &dyn PeakDetector,
+    progress: Option<&dyn ProgressSink>,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    let detect = tracing::trace_span!("peaks", peaks = tracing::field::Empty).entered();
+    let mut peaks = if config.hps_harmonics > 1 {
+        let mut emphasized = spectrogram.clone();
+        harmonic_product_spectrum(&mut emphasized, config.hps_harmonics);
+        detector.detect(&emphasized, config)
+    } else {
+        detector.detect(spectrogram, config)
+    };
+
+    if config.novelty_threshold > 0.0 {
+        let flux = spectral_flux(spectrogram);
+        let frame_rate = spectrogram.frame_rate();
+        peaks.retain(|peak| {
+            let frame = (peak.time * frame_rate).round() as usize;
+            flux.get(frame)
+                .is_some_and(|&value| value >= config.novelty_threshold)
+        });
+    }
+
+    detect.record("peaks", peaks.len());
+    detect.exit();
+
+    if let Some(progress) = progress {
+        progress.report(Progress::Peaks { count: peaks.len() });
+    }
+    generate_hashes(&peaks, config)
+}
@@ -1,63 +1,724 @@
-use {
-    rayon::prelude::*,
-    std::fs::File,
-    symphonia::{
-        core::{
-            audio::{AudioBufferRef, Signal},
-            codecs::DecoderOptions,
-            formats::FormatReader,
-            io::MediaSourceStream,
-        },
-        default::formats::WavReader as SymphoniaWavReader,
-    },
+use std::{
+    collections::HashSet,
+    error::Error,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
 };
 
-const INPUT_FILE: &str = "data/input.wav";
-const OUTPUT_FILE: &str = "output.txt";
-const CHUNK_SIZE: usize = 1024 * 4;
-const FUZ_FACTOR: usize = 2;
-const MIN_FREQ: usize = 40;
-const MAX_FREQ: usize = 300;
+use clap::{Parser, Subcommand};
+use mimalloc::MiMalloc;
+use sonora::{
+    band_fingerprints, decode_is_deterministic, decode_to_samples, decode_track_to_samples,
+    fingerprint_channels, fingerprint_source, index_dir, match_query_with, read_fingerprints,
+    stream_source, AudioSource, ChannelPolicy, Engine, Excerpt, FileSource, FingerprintConfig,
+    FingerprintDb, FingerprintFormat, FingerprintWriter, HashEntry, HashSink, HttpSource,
+    MatchConfig, PcmFormat, RawSource, Reconnect,
+};
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[global_allocator]
+static GLOBAL: MiMalloc = MiMalloc;
+
+/// Entrada que se lee de la entrada estándar en lugar de un archivo.
+const STDIN: &str = "-";
+
+/// Calcula, indexa e identifica huellas acústicas.
+#[derive(Parser)]
+#[command(
+    name = "sonora",
+    version,
+    after_long_help = "SONORA_LOG=debug (o trace) muestra por la salida de error cada etapa con su \
+                       duración y sus contadores; por defecto solo se muestran los avisos."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Calcula las huellas de un audio y las escribe en un archivo.
+    ///
+    /// Con --engine calcula hashes por bandas en lugar de landmarks. Todos los motores dan el
+    /// mismo resultado y solo cambia cómo se reparte el trabajo: sequential (un hilo), rayon
+    /// (lotes en paralelo), simd (un hilo con magnitudes SIMD) o pipelined (decodificación y
+    /// hash en hilos separados). En NDJSON cada línea lleva el índice del bloque, su tiempo y su
+    /// hash.
+    Fingerprint(FingerprintArgs),
+    /// Añade a una base las huellas de los archivos de un directorio.
+    ///
+    /// Guarda la base y un punto de control (<base>.checkpoint) cada pocos archivos; si se
+    /// interrumpe, --resume retoma el lote sin repetir los archivos ya indexados.
+    Index(IndexArgs),
+    /// Identifica un audio, o unas huellas ya calculadas (.ndjson o .bin), contra una base.
+    Match(MatchArgs),
+    /// Resume un audio, unas huellas ya calculadas (.ndjson o .bin) o, con --db, una base.
+    Inspect(InspectArgs),
+    /// Calcula sobre la marcha las huellas de un flujo http://.
+    ///
+    /// Escucha sin fin un flujo (una radio Icecast o SHOUTcast). Sin --db escribe las huellas en
+    /// NDJSON en <salida>, o en la salida estándar; con --db identifica cada tramo de
+    /// --query-seconds segundos contra la base. Si el flujo se corta se vuelve a conectar con
+    /// esperas crecientes, sin límite de intentos salvo que se indique --retries.
+    Monitor(MonitorArgs),
+    /// Calcula sobre la marcha las huellas del micrófono por defecto.
+    ///
+    /// Igual que `monitor`, durante --duration segundos o hasta que se interrumpa. Necesita
+    /// compilar sonora con la feature mic.
+    Listen(ListenArgs),
+    /// Comprueba que decodificar dos veces un audio da las mismas muestras.
+    // `--verify-roundtrip` se mantiene por compatibilidad
+    #[command(long_flag = "verify-roundtrip")]
+    VerifyRoundtrip(VerifyArgs),
+}
+
+impl Command {
+    /// Nombre del subcomando en la línea de comandos.
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Fingerprint(_) => "fingerprint",
+            Command::Index(_) => "index",
+            Command::Match(_) => "match",
+            Command::Inspect(_) => "inspect",
+            Command::Monitor(_) => "monitor",
+            Command::Listen(_) => "listen",
+            Command::VerifyRoundtrip(_) => "verify-roundtrip",
+        }
+    }
+}
+
+#[derive(clap::Args)]
+struct FingerprintArgs {
+    #[command(flatten)]
+    audio: AudioArgs,
+    /// Archivo de salida.
+    #[arg(short, long, value_name = "SALIDA", default_value = "output.ndjson")]
+    output: String,
+    /// Formato de la salida: ndjson o binary.
+    #[arg(long, value_name = "FORMATO", default_value = "ndjson", value_parser = parse_format)]
+    format: FingerprintFormat,
+    /// Calcula hashes por bandas con este motor: sequential, rayon, simd o pipelined.
+    #[arg(long, value_name = "MOTOR")]
+    engine: Option<Engine>,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct IndexArgs {
+    #[command(flatten)]
+    input: Input,
+    /// Base de datos; si ya existe, se añade a ella.
+    #[arg(long, value_name = "BASE")]
+    db: PathBuf,
+    /// Retoma un lote interrumpido desde su punto de control.
+    #[arg(long)]
+    resume: bool,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct MatchArgs {
+    #[command(flatten)]
+    audio: AudioArgs,
+    /// Base de datos contra la que se identifica la consulta.
+    #[arg(long, value_name = "BASE")]
+    db: String,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct InspectArgs {
+    #[command(flatten)]
+    audio: AudioArgs,
+    /// Resume esta base de datos en lugar de una entrada.
+    #[arg(long, value_name = "BASE", conflicts_with_all = ["path", "input"])]
+    db: Option<String>,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct MonitorArgs {
+    #[command(flatten)]
+    input: Input,
+    /// Intentos de reconexión antes de rendirse.
+    #[arg(long, value_name = "N")]
+    retries: Option<u32>,
+    #[command(flatten)]
+    live: LiveArgs,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct ListenArgs {
+    /// Segundos de audio que se escuchan.
+    #[arg(long, value_name = "S")]
+    duration: Option<f64>,
+    #[command(flatten)]
+    live: LiveArgs,
+    #[command(flatten)]
+    params: Params,
+}
+
+#[derive(clap::Args)]
+struct VerifyArgs {
+    #[command(flatten)]
+    input: Input,
+}
+
+/// Entrada de un subcomando, como argumento posicional o con -i/--input.
+#[derive(clap::Args)]
+struct Input {
+    /// Entrada; con `-` se lee la entrada estándar, sin poder retroceder, así que los
+    /// contenedores que guardan su índice al final (algunos MP4) no se pueden leer así.
+    #[arg(value_name = "ENTRADA")]
+    path: Option<String>,
+    /// Entrada, en lugar del argumento posicional.
+    #[arg(short, long, value_name = "ENTRADA", conflicts_with = "path")]
+    input: Option<String>,
+}
+
+impl Input {
+    fn get(&self) -> Result<&str, String> {
+        self.path
+            .as_deref()
+            .or(self.input.as_deref())
+            .ok_or_else(|| "Falta el archivo de entrada".to_string())
+    }
+}
+
+/// Entrada de audio de `fingerprint`, `match` e `inspect`: un archivo, la entrada estándar o
+/// PCM sin cabecera, opcionalmente recortado a un tramo.
+#[derive(clap::Args)]
+struct AudioArgs {
+    #[command(flatten)]
+    input: Input,
+    /// La entrada es PCM intercalado sin cabecera, como el de `ffmpeg -f s16le -`.
+    #[arg(long, requires_all = ["rate", "channels"])]
+    raw: bool,
+    /// Tasa de muestreo de la entrada --raw.
+    #[arg(long, value_name = "HZ", requires = "raw")]
+    rate: Option<u32>,
+    /// Canales intercalados de la entrada --raw.
+    #[arg(long, value_name = "N", requires = "raw")]
+    channels: Option<usize>,
+    /// Codificación de la entrada --raw: u8, s16le (por defecto), s16be, s24le, s32le, f32le o
+    /// f64le.
+    #[arg(long, value_name = "FMT", requires = "raw")]
+    sample_format: Option<PcmFormat>,
+    /// Procesa la entrada desde este segundo; los tiempos de las huellas cuentan desde aquí. Si
+    /// el contenedor lo permite se salta directamente al inicio, y si no se decodifica y se
+    /// descarta lo anterior.
+    #[arg(long, value_name = "S")]
+    start: Option<f64>,
+    /// Procesa solo estos segundos de la entrada.
+    #[arg(long, value_name = "S")]
+    duration: Option<f64>,
+    /// Pista de audio de un archivo con varias (un MKV o MP4 con varios idiomas), contando
+    /// desde 0 solo las pistas de audio; sin ella se usa la pista por defecto del contenedor.
+    #[arg(long, value_name = "N", conflicts_with = "raw")]
+    track: Option<usize>,
+}
+
+impl AudioArgs {
+    /// Origen de audio de la entrada, recortado al tramo de `--start` y `--duration` si se
+    /// indicaron.
+    fn source(&self) -> Result<Box<dyn AudioSource + Send>, Box<dyn Error>> {
+        let source = self.full_source()?;
+        if self.start.is_none() && self.duration.is_none() {
+            return Ok(source);
+        }
+        let start = self.start.unwrap_or(0.0);
+        let duration = self.duration.unwrap_or(f64::INFINITY);
+        if !(start >= 0.0 && duration > 0.0) {
+            return Err("--start no puede ser negativo y --duration debe ser positiva".into());
+        }
+        let duration = duration
+            .is_finite()
+            .then(|| Duration::from_secs_f64(duration));
+        Ok(Box::new(Excerpt::new(
+            source,
+            Duration::from_secs_f64(start),
+            duration,
+        )))
+    }
+
+    /// Origen de audio de la entrada completa, un archivo o la entrada estándar: PCM sin
+    /// cabecera con `--raw`, o un contenedor que se detecta solo.
+    fn full_source(&self) -> Result<Box<dyn AudioSource + Send>, Box<dyn Error>> {
+        let input = self.input.get()?;
+        if !self.raw {
+            return Ok(Box::new(match (input, self.track) {
+                (STDIN, None) => FileSource::from_reader(io::stdin())?,
+                (STDIN, Some(_)) => return Err("--track solo vale con archivos de audio".into()),
+                (input, None) => FileSource::open(input)?,
+                (input, Some(track)) => FileSource::open_track(input, track)?,
+            }));
+        }
+        let (format, channels, rate) = self.raw_params()?;
+        let reader: Box<dyn Read + Send> = if input == STDIN {
+            Box::new(io::stdin())
+        } else {
+            Box::new(File::open(input)?)
+        };
+        let reader = BufReader::new(reader);
+        Ok(Box::new(RawSource::new(reader, format, channels, rate)?))
+    }
+
+    /// Codificación, canales y tasa de una entrada `--raw`.
+    fn raw_params(&self) -> Result<(PcmFormat, usize, u32), String> {
+        let (channels, rate) = self
+            .channels
+            .zip(self.rate)
+            .ok_or_else(|| "--raw necesita --channels y --rate".to_string())?;
+        Ok((self.sample_format.unwrap_or_default(), channels, rate))
+    }
+}
+
+/// Salida de `monitor` y `listen`.
+#[derive(clap::Args)]
+struct LiveArgs {
+    /// Base de datos contra la que se identifica cada tramo.
+    #[arg(long, value_name = "BASE")]
+    db: Option<String>,
+    /// Segundos de cada tramo que se identifica con --db.
+    #[arg(long, value_name = "S", default_value_t = 10.0)]
+    query_seconds: f32,
+    /// Archivo NDJSON en el que se escriben las huellas sin --db; por defecto, la salida
+    /// estándar.
+    #[arg(short, long, value_name = "SALIDA")]
+    output: Option<String>,
+}
+
+/// Parámetros del algoritmo; los que no se indican conservan su valor por defecto.
+#[derive(clap::Args)]
+#[command(next_help_heading = "Parámetros del algoritmo")]
+struct Params {
+    /// Tasa a la que se remuestrea el audio antes del espectrograma (11025 por defecto); solo
+    /// se comparan huellas calculadas con la misma.
+    #[arg(long, value_name = "HZ")]
+    canonical_rate: Option<u32>,
+    /// Muestras por ventana de la FFT.
+    #[arg(long, value_name = "N")]
+    window: Option<usize>,
+    /// Solapamiento entre ventanas, en muestras.
+    #[arg(long, value_name = "N")]
+    overlap: Option<usize>,
+    /// Amplitud mínima de un pico.
+    #[arg(long, value_name = "X")]
+    amp_min: Option<f32>,
+    /// Vecindario de la detección de picos, en ventanas y bins.
+    #[arg(long, value_name = "N")]
+    neighborhood: Option<usize>,
+    /// Picos emparejados con cada ancla.
+    #[arg(long, value_name = "N")]
+    fan_value: Option<usize>,
+    /// Separación máxima entre picos emparejados, en segundos.
+    #[arg(long, value_name = "X")]
+    max_delta_t: Option<f32>,
+    /// Reducción del audio multicanal: mix (por defecto), left, right, mid, side o
+    /// per-channel; con per-channel, `fingerprint` escribe una huella por canal, en <salida>
+    /// con el número del canal antes de la extensión.
+    #[arg(long, value_name = "POLÍTICA")]
+    channel_policy: Option<ChannelPolicy>,
+    /// Procesa el audio en streaming, con colas y lotes que caben en este presupuesto en MiB,
+    /// en lugar de cargar la pista entera.
+    #[arg(long, value_name = "MIB")]
+    max_memory: Option<usize>,
+}
+
+impl Params {
+    /// Configuración por defecto con los parámetros de la línea de comandos aplicados.
+    fn config(&self) -> Result<FingerprintConfig, String> {
+        let mut config = FingerprintConfig::default();
+        config.canonical_rate = self.canonical_rate.unwrap_or(config.canonical_rate);
+        config.window_size = self.window.unwrap_or(config.window_size);
+        config.overlap = self.overlap.unwrap_or(config.overlap);
+        config.amp_min = self.amp_min.unwrap_or(config.amp_min);
+        config.neighborhood_size = self.neighborhood.unwrap_or(config.neighborhood_size);
+        config.fan_value = self.fan_value.unwrap_or(config.fan_value);
+        config.max_delta_t = self.max_delta_t.unwrap_or(config.max_delta_t);
+        config.channel_policy = self.channel_policy.unwrap_or(config.channel_policy);
+        if let Some(mib) = self.max_memory {
+            config.max_memory = Some(mib.saturating_mul(1024 * 1024));
+        }
+        if config.window_size == 0 || config.overlap >= config.window_size {
+            return Err("El solapamiento debe ser menor que la ventana".to_string());
+        }
+        if config.canonical_rate == 0 {
+            return Err("La tasa canónica debe ser mayor que 0".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// Formato de huellas de `--format`.
+fn parse_format(name: &str) -> Result<FingerprintFormat, String> {
+    match name {
+        "ndjson" => Ok(FingerprintFormat::NdJson),
+        "binary" => Ok(FingerprintFormat::Binary),
+        other => Err(format!("Formato desconocido: {}", other)),
+    }
+}
 
-const fn hash(p: &[usize; 301]) -> usize {
-    let p1 = p[40] / FUZ_FACTOR;
-    let p2 = p[80] / FUZ_FACTOR;
-    let p3 = p[120] / FUZ_FACTOR;
-    let p4 = p[180] / FUZ_FACTOR;
-    (p4 * 100_000_000) + (p3 * 100_000) + (p2 * 100) + p1
+fn fingerprint(args: &FingerprintArgs) -> Result<(), Box<dyn Error>> {
+    let config = args.params.config()?;
+    let (format, output) = (args.format, args.output.as_str());
+
+    if let Some(engine) = args.engine {
+        let mut writer = FingerprintWriter::new(BufWriter::new(File::create(output)?), format)
+            .with_chunk_index();
+        band_fingerprints(args.audio.source()?, &config, engine, &mut writer)?;
+        println!(
+            "{} hashes escritos en {} con el motor {}",
+            writer.count(),
+            output,
+            engine
+        );
+        return Ok(());
+    }
+
+    if config.channel_policy == ChannelPolicy::PerChannel {
+        let channels = fingerprint_channels(args.audio.source()?, &config)?;
+        for (channel, hashes) in channels.iter().enumerate() {
+            let path = channel_output(output, channel);
+            write_hashes(&path, hashes, format)?;
+            println!(
+                "{} hashes del canal {} escritos en {}",
+                hashes.len(),
+                channel,
+                path.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let hashes = fingerprint_source(args.audio.source()?, &config)?;
+    write_hashes(Path::new(output), &hashes, format)?;
+    println!("{} hashes escritos en {}", hashes.len(), output);
+    Ok(())
+}
+
+fn write_hashes(
+    path: &Path,
+    hashes: &[HashEntry],
+    format: FingerprintFormat,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = FingerprintWriter::new(BufWriter::new(File::create(path)?), format);
+    writer.accept(hashes)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Salida de la huella de un canal: `salida.ndjson` pasa a `salida.<canal>.ndjson`.
+fn channel_output(output: &str, channel: usize) -> PathBuf {
+    let path = Path::new(output);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => path.with_extension(format!("{}.{}", channel, extension)),
+        None => path.with_extension(channel.to_string()),
+    }
+}
+
+fn index(args: &IndexArgs) -> Result<(), Box<dyn Error>> {
+    let config = args.params.config()?;
+
+    // Se añade a la base existente, si la hay
+    let summary = index_dir(Path::new(args.input.get()?), &args.db, &config, args.resume)?;
+
+    for (song_id, path) in &summary.succeeded {
+        println!("{}\t{}", song_id, path.display());
+    }
+    println!(
+        "{} archivos indexados, {} con errores, {} ya indexados antes",
+        summary.succeeded.len(),
+        summary.failed.len(),
+        summary.resumed.len()
+    );
+    Ok(())
 }
 
-fn get_index(x: usize) -> usize {
-    match x {
-        0..=40 => 40,
-        41..=80 => 80,
-        81..=120 => 120,
-        121..=180 => 180,
-        _ => 300,
+/// Huellas de la entrada: leídas si es un archivo .ndjson o .bin, calculadas si es audio.
+fn input_hashes(audio: &AudioArgs, params: &Params) -> Result<Vec<HashEntry>, Box<dyn Error>> {
+    let input = audio.input.get()?;
+    if audio.raw {
+        return Ok(fingerprint_source(audio.source()?, &params.config()?)?);
     }
+    let hashes = match Path::new(input).extension().and_then(|e| e.to_str()) {
+        Some("ndjson") => read_fingerprints(
+            BufReader::new(File::open(input)?),
+            FingerprintFormat::NdJson,
+        )?,
+        Some("bin") => read_fingerprints(
+            BufReader::new(File::open(input)?),
+            FingerprintFormat::Binary,
+        )?,
+        _ => fingerprint_source(audio.source()?, &params.config()?)?,
+    };
+    Ok(hashes)
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Abrir archivo de entrada
-    let src = File::open(INPUT_FILE)?;
-    let mss = MediaSourceStream::new(Box::new(src), Default::default());
-    let mut wave = SymphoniaWavReader::try_new(mss, &Default::default())?;
-    let track = wave
-        .default_track()
-        .ok_or("No se encontró el track de audio")?;
-    let mut decoder =
-        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+fn match_command(args: &MatchArgs) -> Result<(), Box<dyn Error>> {
+    let db = FingerprintDb::load(&args.db)?;
+    let fingerprint_config = args.params.config()?;
+    db.check_hash_scheme(&fingerprint_config)?;
+    let hashes = input_hashes(&args.audio, &args.params)?;
+
+    let config = MatchConfig {
+        hash_scheme: fingerprint_config.hash_scheme,
+        ..MatchConfig::default()
+    };
+    let results = match_query_with(&hashes, &db, &config);
+    if results.is_empty() {
+        println!("Sin coincidencias");
+        process::exit(1);
+    }
+    for result in results {
+        println!("{}", result);
+    }
+    Ok(())
+}
 
-    // Leer y decodificar, almacenando en buffer
-    let mut raw_samples: Vec<i16> = Vec::with_capacity(1024 * 1024); // Ajustar capacidad según necesidad
+fn inspect(args: &InspectArgs) -> Result<(), Box<dyn Error>> {
+    if let Some(db_path) = &args.db {
+        let db = FingerprintDb::load(db_path)?;
+        let counts = db.song_hash_counts();
+        println!(
+            "{}: {} canciones, {} hashes distintos",
+            db_path,
+            counts.len(),
+            db.num_hashes()
+        );
+        for (song_id, count) in counts {
+            println!("canción {}\t{} hashes", song_id, count);
+        }
+        return Ok(());
+    }
 
-    let time = std::time::Instant::now();
-    while let Ok(packet) = wave.next_packet() {
-        if let AudioBufferRef::S16(buf) = decoder.decode(&packet)? {
-            raw_samples.extend(buf.chan(0).iter().chain(buf.chan(1).iter()));
+    let input = args.audio.input.get()?;
+    if input == STDIN {
+        // La entrada estándar solo se lee una vez: basta para las huellas.
+    } else if args.audio.raw {
+        let (format, channels, rate) = args.audio.raw_params()?;
+        let frames = std::fs::metadata(input)?.len() as usize
+            / (format.bytes_per_sample() * channels.max(1));
+        println!(
+            "{}: PCM {}, {} canales, {} Hz, {:.2} s",
+            input,
+            format,
+            channels,
+            rate,
+            frames as f32 / rate.max(1) as f32
+        );
+    } else if !matches!(
+        Path::new(input).extension().and_then(|e| e.to_str()),
+        Some("ndjson" | "bin")
+    ) {
+        let decoded = match args.audio.track {
+            Some(track) => decode_track_to_samples(input, track)?,
+            None => decode_to_samples(input)?,
+        };
+        let frames = decoded.samples.len() / decoded.channels.max(1);
+        println!(
+            "{}: {} canales, {} Hz, {:.2} s",
+            input,
+            decoded.channels,
+            decoded.sample_rate,
+            frames as f32 / decoded.sample_rate.max(1) as f32
+        );
+        if decoded.skipped_packets > 0 {
+            println!(
+                "{} paquetes corruptos omitidos y sustituidos por silencio",
+                decoded.skipped_packets
+            );
         }
     }
-    println!("Tiempo de lectura y decodificación: {:?}", time.elapsed());
 
+    let hashes = input_hashes(&args.audio, &args.params)?;
+    let distinct: HashSet<u64> = hashes.iter().map(|entry| entry.hash).collect();
+    let (first, last) = hashes.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(first, last), entry| (first.min(entry.time), last.max(entry.time)),
+    );
+    print!("{} hashes, {} distintos", hashes.len(), distinct.len());
+    if hashes.is_empty() {
+        println!();
+    } else {
+        println!(", anclas entre {:.2} s y {:.2} s", first, last);
+    }
     Ok(())
 }
+
+fn monitor(args: &MonitorArgs) -> Result<(), Box<dyn Error>> {
+    let url = args.input.get()?;
+    let config = args.params.config()?;
+    let reconnect = Reconnect {
+        max_attempts: args.retries,
+        ..Reconnect::default()
+    };
+    let source = HttpSource::with_reconnect(url, reconnect)?;
+    eprintln!(
+        "{}: {} canales, {} Hz",
+        url,
+        source.channels(),
+        source.sample_rate()
+    );
+    live_hashes(&args.live, &config, stream_source(source, &config))
+}
+
+/// Escucha el micrófono por defecto durante --duration segundos, o hasta que se interrumpa.
+#[cfg(feature = "mic")]
+fn listen(args: &ListenArgs) -> Result<(), Box<dyn Error>> {
+    let config = args.params.config()?;
+    let (_stream, rx) = sonora::capture_microphone_at(config.canonical_rate)?;
+    let rx = match args.duration {
+        None => rx,
+        Some(seconds) => {
+            if !(seconds > 0.0 && seconds.is_finite()) {
+                return Err("--duration debe ser positiva".into());
+            }
+            // Corta el audio al cumplirse la duración; al cerrarse el canal salen las últimas
+            // huellas.
+            let mut remaining = (seconds * config.canonical_rate as f64) as usize;
+            let (tx, limited) = crossbeam::channel::unbounded();
+            std::thread::spawn(move || {
+                while remaining > 0 {
+                    let Ok(mut block) = rx.recv() else {
+                        return;
+                    };
+                    block.truncate(remaining);
+                    remaining -= block.len();
+                    if tx.send(block).is_err() {
+                        return;
+                    }
+                }
+            });
+            limited
+        }
+    };
+    eprintln!("Escuchando el micrófono por defecto");
+    live_hashes(
+        &args.live,
+        &config,
+        sonora::stream_fingerprints(rx, &config),
+    )
+}
+
+/// Salida de `monitor` y `listen`: identifica las huellas por tramos con --db, o las escribe
+/// en NDJSON según llegan.
+fn live_hashes(
+    args: &LiveArgs,
+    config: &FingerprintConfig,
+    hashes: impl IntoIterator<Item = HashEntry>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(db_path) = &args.db {
+        let db = FingerprintDb::load(db_path)?;
+        db.check_hash_scheme(config)?;
+        let seconds = args.query_seconds;
+        if !(seconds > 0.0 && seconds.is_finite()) {
+            return Err("--query-seconds debe ser positivo".into());
+        }
+        return match_segments(hashes, &db, seconds);
+    }
+
+    let output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = FingerprintWriter::new(BufWriter::new(output), FingerprintFormat::NdJson);
+    let mut flushed = 0.0;
+    for entry in hashes {
+        let time = entry.time;
+        writer.accept(&[entry])?;
+        // Vacía el destino cada segundo de audio para que las huellas se vean llegar.
+        if time >= flushed + 1.0 {
+            writer.get_mut().flush()?;
+            flushed = time;
+        }
+    }
+    writer.finish()?;
+    eprintln!("El audio terminó tras {} hashes", writer.count());
+    Ok(())
+}
+
+/// Identifica contra `db` cada tramo de `seconds` segundos de unas huellas que llegan en orden
+/// de tiempo, e imprime la mejor coincidencia de cada uno según se completa.
+fn match_segments(
+    hashes: impl IntoIterator<Item = HashEntry>,
+    db: &FingerprintDb,
+    seconds: f32,
+) -> Result<(), Box<dyn Error>> {
+    let config = MatchConfig {
+        hash_scheme: db.hash_scheme(),
+        ..MatchConfig::default()
+    };
+    let report =
+        |start: f32, segment: &[HashEntry]| match match_query_with(segment, db, &config).first() {
+            Some(result) => println!("{:.1}-{:.1} s\t{}", start, start + seconds, result),
+            None => println!("{:.1}-{:.1} s\tsin coincidencias", start, start + seconds),
+        };
+    let mut segment = Vec::new();
+    let mut start = 0.0;
+    for entry in hashes {
+        while entry.time >= start + seconds {
+            report(start, &segment);
+            segment.clear();
+            start += seconds;
+        }
+        segment.push(entry);
+    }
+    if !segment.is_empty() {
+        report(start, &segment);
+    }
+    Ok(())
+}
+
+fn verify_roundtrip(args: &VerifyArgs) -> Result<(), Box<dyn Error>> {
+    let path = args.input.get()?;
+    if decode_is_deterministic(path) {
+        println!("{}: la decodificación es determinista", path);
+        return Ok(());
+    }
+    println!("{}: la decodificación NO es determinista", path);
+    process::exit(1);
+}
+
+/// Envía los eventos de `tracing` a la salida de error, a partir del nivel de `SONORA_LOG`
+/// (`warn` si no se indica). Desde `debug` cada etapa informa de su duración al cerrarse.
+fn init_tracing() {
+    let level = std::env::var("SONORA_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(Level::WARN);
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .init();
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    init_tracing();
+    let cli = Cli::parse();
+
+    let _span = tracing::info_span!("sonora", command = cli.command.name()).entered();
+    match &cli.command {
+        Command::Fingerprint(args) => fingerprint(args),
+        Command::Index(args) => index(args),
+        Command::Match(args) => match_command(args),
+        Command::Inspect(args) => inspect(args),
+        Command::Monitor(args) => monitor(args),
+        #[cfg(feature = "mic")]
+        Command::Listen(args) => listen(args),
+        #[cfg(not(feature = "mic"))]
+        Command::Listen(_) => Err("`listen` necesita compilar sonora con la feature mic".into()),
+        Command::VerifyRoundtrip(args) => verify_roundtrip(args),
+    }
+}
@@ -0,0 +1,367 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::db::FingerprintDb;
+use crate::hash::{HashEntry, HashScheme};
+use crate::FINGERPRINT_VERSION;
+
+/// Parámetros del emparejamiento de una consulta contra la base de datos.
+#[derive(Debug, Clone)]
+pub struct MatchConfig {
+    /// Votos mínimos en el desplazamiento dominante para aceptar una canción.
+    pub min_score: usize,
+    /// Ancho en segundos de cada bin del histograma de desplazamientos.
+    pub offset_resolution: f32,
+    /// Si se indica, activa el alineamiento local: la puntuación encadena segmentos cuyo
+    /// desplazamiento cambia como mucho este número de segundos, de modo que una consulta con
+    /// huecos (buffers perdidos en una captura) sigue sumando votos tras cada salto.
+    pub max_offset_jump: Option<f32>,
+    /// Confianza mínima (`score` entre el número de hashes de la consulta) para aceptar una
+    /// canción; descarta consultas cortas que comparten unos pocos hashes por casualidad con
+    /// una canción larga.
+    pub min_confidence: f32,
+    /// Si es `true`, cada resultado incluye el histograma completo de desplazamientos, útil
+    /// para depurar falsos positivos.
+    pub keep_histogram: bool,
+    /// Si es `true`, también se empareja la consulta con su eje de tiempo invertido (tiempos
+    /// negados y reordenados), para detectar muestras tocadas al revés en un remix. Solo tiene
+    /// sentido con hashes que no dependen del sentido del tiempo, como los de bandas.
+    pub match_reversed: bool,
+    /// Esquema con que se generaron los hashes de la consulta; si no es el de la base de
+    /// datos, sus hashes no son comparables y el emparejamiento no da ningún resultado.
+    pub hash_scheme: HashScheme,
+    /// [`FINGERPRINT_VERSION`] con que se generaron los hashes de la consulta. Una base de
+    /// datos en memoria siempre es de la versión actual, ya que `FingerprintDb::load` rechaza
+    /// las demás, así que con otra versión el emparejamiento no da ningún resultado.
+    pub version: u32,
+}
+
+impl Default for MatchConfig {
+    fn default() -> Self {
+        Self {
+            min_score: 5,
+            offset_resolution: 0.05,
+            max_offset_jump: None,
+            min_confidence: 0.0,
+            keep_histogram: false,
+            match_reversed: false,
+            hash_scheme: HashScheme::default(),
+            version: FINGERPRINT_VERSION,
+        }
+    }
+}
+
+/// Tramo de la consulta alineado con la canción en un desplazamiento constante.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchSegment {
+    /// Tiempo del primer hash alineado de la consulta, en segundos.
+    pub query_start: f32,
+    /// Tiempo del último hash alineado de la consulta, en segundos.
+    pub query_end: f32,
+    /// Desplazamiento del tramo respecto a la canción, en segundos.
+    pub offset_seconds: f32,
+    /// Hashes de la consulta que votan este desplazamiento.
+    pub votes: usize,
+}
+
+/// Canción candidata para una consulta.
+#[derive(Debug, Clone)]
+pub struct MatchResult {
+    pub song_id: u32,
+    /// Hashes de la consulta alineados en el desplazamiento dominante, o en la cadena de
+    /// segmentos si el alineamiento local está activo.
+    pub score: usize,
+    /// `score` normalizado por el número de hashes de la consulta, en `[0.0, 1.0]`.
+    pub confidence: f32,
+    /// Fracción de los votos de la canción que caen en el bin dominante, en `(0.0, 1.0]`: una
+    /// coincidencia real concentra sus hashes en un desplazamiento, mientras que una casual
+    /// los reparte aunque comparta tantos hashes con la consulta.
+    pub alignment_quality: f32,
+    /// Posición en la canción, en segundos, donde empieza la consulta; si `reversed`, donde
+    /// empieza la consulta invertida, desde la que la consulta recorre la canción hacia atrás.
+    pub offset_seconds: f32,
+    /// Segmentos alineados, ordenados por tiempo en la consulta.
+    pub segments: Vec<MatchSegment>,
+    /// Histograma de desplazamientos `(offset_seconds, votos)` ordenado por desplazamiento;
+    /// solo está presente si `MatchConfig::keep_histogram` está activo.
+    pub histogram: Option<Vec<(f32, usize)>>,
+    /// `true` si la coincidencia es con la consulta invertida en el tiempo; solo puede serlo
+    /// con `MatchConfig::match_reversed`.
+    pub reversed: bool,
+}
+
+impl MatchResult {
+    /// Indica si el candidato supera un umbral de confianza.
+    ///
+    /// # Argumentos
+    ///
+    /// * `threshold` - Confianza mínima, en `[0.0, 1.0]`.
+    ///
+    /// # Retorna
+    ///
+    /// * `bool` - `true` si `confidence` es al menos `threshold`.
+    pub fn is_match(&self, threshold: f32) -> bool {
+        self.confidence >= threshold
+    }
+}
+
+/// Ordena los candidatos por `score` y, a igual puntuación, pone delante la canción de menor
+/// id, de modo que `max` y `sort` dan el mejor candidato sin reimplementar la comparación.
+/// La igualdad sigue el mismo criterio: dos candidatos son iguales si comparten canción y
+/// puntuación, aunque difieran en el resto de campos.
+impl Ord for MatchResult {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then(other.song_id.cmp(&self.song_id))
+    }
+}
+
+impl PartialOrd for MatchResult {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MatchResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MatchResult {}
+
+impl fmt::Display for MatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "canción {}\tpuntuación {}\tconfianza {:.3}\tdesplazamiento {:.2} s",
+            self.song_id, self.score, self.confidence, self.offset_seconds
+        )?;
+        if self.reversed {
+            f.write_str("\tinvertida")?;
+        }
+        Ok(())
+    }
+}
+
+/// Identifica una consulta contra la base de datos con la configuración por defecto, que
+/// supone hashes de `HashScheme::Packed`.
+///
+/// # Argumentos
+///
+/// * `query` - Hashes de la grabación a identificar.
+/// * `db` - Base de datos de huellas de referencia.
+///
+/// # Retorna
+///
+/// * `Vec<MatchResult>` - Candidatos ordenados por puntuación descendente.
+pub fn match_query(query: &[HashEntry], db: &FingerprintDb) -> Vec<MatchResult> {
+    match_query_with(query, db, &MatchConfig::default())
+}
+
+/// Identifica una consulta contra la base de datos.
+///
+/// Para cada hash de la consulta presente en la base de datos se vota el desplazamiento
+/// `db_time - query_time` de la canción correspondiente; la puntuación de cada canción es la
+/// altura de su bin más alto, ya que una coincidencia real alinea muchos hashes en el mismo
+/// desplazamiento. La confianza es esa puntuación dividida por el número de hashes de la
+/// consulta. Se descartan las canciones por debajo de `min_score` o de `min_confidence`, y si
+/// varias canciones empatan en la mejor puntuación el resultado es ambiguo y se devuelve vacío.
+/// Con `match_reversed` cada canción se puntúa también contra la consulta invertida y se queda
+/// con el mejor de los dos sentidos; a igual puntuación gana el directo.
+///
+/// Si `config.hash_scheme` no es el esquema de la base de datos, o `config.version` no es la
+/// [`FINGERPRINT_VERSION`] actual, no se vota nada: los hashes solo coincidirían por
+/// casualidad, así que se avisa y se devuelve vacío.
+///
+/// # Argumentos
+///
+/// * `query` - Hashes de la grabación a identificar.
+/// * `db` - Base de datos de huellas de referencia.
+/// * `config` - Parámetros del emparejamiento.
+///
+/// # Retorna
+///
+/// * `Vec<MatchResult>` - Candidatos ordenados por puntuación descendente.
+pub fn match_query_with(
+    query: &[HashEntry],
+    db: &FingerprintDb,
+    config: &MatchConfig,
+) -> Vec<MatchResult> {
+    if config.hash_scheme != db.hash_scheme() {
+        tracing::warn!(
+            query = ?config.hash_scheme,
+            db = ?db.hash_scheme(),
+            "la consulta y la base de datos usan distinto esquema de hash"
+        );
+        return Vec::new();
+    }
+    if config.version != FINGERPRINT_VERSION {
+        tracing::warn!(
+            query = config.version,
+            db = FINGERPRINT_VERSION,
+            "la consulta y la base de datos son de distinta versión de huellas"
+        );
+        return Vec::new();
+    }
+
+    let mut results = candidates(query, db, config, false);
+    if config.match_reversed {
+        let mut reversed: Vec<HashEntry> = query
+            .iter()
+            .map(|entry| HashEntry {
+                hash: entry.hash,
+                time: -entry.time,
+            })
+            .collect();
+        reversed.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for candidate in candidates(&reversed, db, config, true) {
+            match results
+                .iter_mut()
+                .find(|result| result.song_id == candidate.song_id)
+            {
+                Some(result) if result.score >= candidate.score => {}
+                Some(result) => *result = candidate,
+                None => results.push(candidate),
+            }
+        }
+    }
+
+    results.sort_by(|a, b| b.cmp(a));
+
+    if results.len() > 1 && results[0].score == results[1].score {
+        return Vec::new();
+    }
+
+    results
+}
+
+/// Puntúa cada canción que comparte hashes con la consulta según su histograma de
+/// desplazamientos.
+///
+/// # Argumentos
+///
+/// * `query` - Hashes de la grabación a identificar, quizá con el tiempo invertido.
+/// * `db` - Base de datos de huellas de referencia.
+/// * `config` - Parámetros del emparejamiento.
+/// * `reversed` - Si `query` es la consulta invertida, para marcarlo en los resultados.
+///
+/// # Retorna
+///
+/// * `Vec<MatchResult>` - Candidatos que superan `min_score` y `min_confidence`, sin ordenar.
+fn candidates(
+    query: &[HashEntry],
+    db: &FingerprintDb,
+    config: &MatchConfig,
+    reversed: bool,
+) -> Vec<MatchResult> {
+    // Histograma de desplazamientos por canción, guardando los tiempos de la consulta que votan.
+    let mut histograms: HashMap<u32, HashMap<i64, Vec<f32>>> = HashMap::new();
+    for entry in query {
+        for &(song_id, db_time) in db.lookup(entry.hash) {
+            let bin = ((db_time - entry.time) / config.offset_resolution).round() as i64;
+            histograms
+                .entry(song_id)
+                .or_default()
+                .entry(bin)
+                .or_default()
+                .push(entry.time);
+        }
+    }
+
+    histograms
+        .into_iter()
+        .filter_map(|(song_id, histogram)| {
+            let votes: usize = histogram.values().map(Vec::len).sum();
+            let offsets = config.keep_histogram.then(|| {
+                let mut offsets: Vec<(f32, usize)> = histogram
+                    .iter()
+                    .map(|(&bin, times)| (bin as f32 * config.offset_resolution, times.len()))
+                    .collect();
+                offsets.sort_by(|a, b| a.0.total_cmp(&b.0));
+                offsets
+            });
+            let segments: Vec<MatchSegment> = histogram
+                .into_iter()
+                .map(|(bin, times)| MatchSegment {
+                    query_start: times.iter().copied().fold(f32::INFINITY, f32::min),
+                    query_end: times.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                    offset_seconds: bin as f32 * config.offset_resolution,
+                    votes: times.len(),
+                })
+                .collect();
+            let segments = match config.max_offset_jump {
+                Some(max_jump) => best_chain(segments, max_jump),
+                None => {
+                    // Bin más alto; a igual altura gana el desplazamiento menor.
+                    let best = segments.into_iter().max_by(|a, b| {
+                        a.votes
+                            .cmp(&b.votes)
+                            .then(b.offset_seconds.total_cmp(&a.offset_seconds))
+                    })?;
+                    vec![best]
+                }
+            };
+            let dominant = segments.iter().max_by_key(|segment| segment.votes)?;
+            let score: usize = segments.iter().map(|segment| segment.votes).sum();
+            Some(MatchResult {
+                song_id,
+                score,
+                // Un hash de la consulta puede votar varias veces si se repite en la canción.
+                confidence: (score as f32 / query.len() as f32).min(1.0),
+                alignment_quality: dominant.votes as f32 / votes as f32,
+                offset_seconds: dominant.offset_seconds,
+                segments,
+                histogram: offsets,
+                reversed,
+            })
+        })
+        .filter(|result| {
+            result.score >= config.min_score && result.confidence >= config.min_confidence
+        })
+        .collect()
+}
+
+/// Encadena los segmentos de una canción que forman el mejor alineamiento local.
+///
+/// Los segmentos se recorren por tiempo de inicio en la consulta y cada uno puede continuar a
+/// cualquier segmento anterior cuyo desplazamiento difiera como mucho `max_jump` segundos. Los
+/// bins con un único voto se ignoran por ser casi siempre coincidencias aisladas.
+///
+/// # Argumentos
+///
+/// * `segments` - Segmentos (bins del histograma) de una canción.
+/// * `max_jump` - Máximo salto de desplazamiento entre segmentos encadenados, en segundos.
+///
+/// # Retorna
+///
+/// * `Vec<MatchSegment>` - Cadena con más votos, ordenada por tiempo en la consulta.
+fn best_chain(mut segments: Vec<MatchSegment>, max_jump: f32) -> Vec<MatchSegment> {
+    segments.retain(|segment| segment.votes > 1);
+    segments.sort_by(|a, b| {
+        a.query_start
+            .total_cmp(&b.query_start)
+            .then(a.offset_seconds.total_cmp(&b.offset_seconds))
+    });
+
+    // best[i]: votos de la mejor cadena que termina en el segmento i, con su predecesor.
+    let mut best: Vec<(usize, Option<usize>)> = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        let previous = (0..i)
+            .filter(|&j| (segments[j].offset_seconds - segment.offset_seconds).abs() <= max_jump)
+            .max_by_key(|&j| best[j].0);
+        let votes = segment.votes + previous.map_or(0, |j| best[j].0);
+        best.push((votes, previous));
+    }
+
+    let mut chain = Vec::new();
+    let mut current = (0..best.len()).max_by_key(|&i| best[i].0);
+    while let Some(i) = current {
+        chain.push(segments[i].clone());
+        current = best[i].1;
+    }
+    chain.reverse();
+    chain
+}
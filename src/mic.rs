@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+use crossbeam::channel::{unbounded, Receiver, Sender};
+
+use crate::error::SonoraError;
+use crate::resample::{StreamResampler, CANONICAL_SAMPLE_RATE};
+use crate::source::{copy_frames, AudioSource};
+
+/// Abre el micrófono por defecto y envía su audio por un canal, listo para `stream_fingerprints`.
+///
+/// Cada búfer que entrega el dispositivo se mezcla a mono promediando los canales y se
+/// remuestrea a [`CANONICAL_SAMPLE_RATE`] con un remuestreador que guarda las muestras
+/// sobrantes entre búferes, así que los bloques del canal forman una señal continua.
+///
+/// # Retorna
+///
+/// * `Ok((Stream, Receiver<Vec<f32>>))` - Flujo de captura ya arrancado y canal de bloques. La
+///   captura dura mientras el `Stream` siga vivo; al soltarlo se cierra el canal.
+/// * `Err(SonoraError)` - Si no hay dispositivo de entrada o no se puede abrir.
+pub fn capture_microphone() -> Result<(Stream, Receiver<Vec<f32>>), SonoraError> {
+    capture_microphone_at(CANONICAL_SAMPLE_RATE)
+}
+
+/// Como `capture_microphone`, remuestreando a otra tasa: la `canonical_rate` de la
+/// configuración con la que se van a calcular las huellas.
+///
+/// # Argumentos
+///
+/// * `rate` - Tasa de los bloques del canal, en Hz.
+///
+/// # Retorna
+///
+/// * `Ok((Stream, Receiver<Vec<f32>>))` - Flujo de captura ya arrancado y canal de bloques.
+/// * `Err(SonoraError)` - Si no hay dispositivo de entrada o no se puede abrir.
+pub fn capture_microphone_at(rate: u32) -> Result<(Stream, Receiver<Vec<f32>>), SonoraError> {
+    let device = cpal::default_host()
+        .default_input_device()
+        .ok_or_else(|| SonoraError::Capture("no hay dispositivo de entrada".to_string()))?;
+    let supported = device
+        .default_input_config()
+        .map_err(|e| SonoraError::Capture(e.to_string()))?;
+    let format = supported.sample_format();
+    let config: StreamConfig = supported.into();
+
+    let (tx, rx) = unbounded();
+    let stream = match format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, rate, tx),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, rate, tx),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, rate, tx),
+        other => {
+            return Err(SonoraError::Capture(format!(
+                "formato de muestra no soportado: {other}"
+            )))
+        }
+    }
+    .map_err(|e| SonoraError::Capture(e.to_string()))?;
+    stream
+        .play()
+        .map_err(|e| SonoraError::Capture(e.to_string()))?;
+
+    Ok((stream, rx))
+}
+
+/// Construye el flujo de entrada para un tipo de muestra concreto.
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    rate: u32,
+    tx: Sender<Vec<f32>>,
+) -> Result<Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let mut resampler = StreamResampler::new(config.sample_rate.0, rate);
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let mono: Vec<f32> = data
+                .chunks_exact(channels)
+                .map(|frame| {
+                    frame.iter().map(|s| s.to_sample::<f32>()).sum::<f32>() / channels as f32
+                })
+                .collect();
+            let block = resampler.push(&mono);
+            if !block.is_empty() {
+                // Si el receptor ya no existe no hay nadie a quien avisar.
+                let _ = tx.send(block);
+            }
+        },
+        |e| tracing::error!(error = %e, "error en la captura de audio"),
+        None,
+    )
+}
+
+/// Captura del micrófono por defecto como [`AudioSource`] de duración fija.
+///
+/// Entrega audio mono a [`CANONICAL_SAMPLE_RATE`]; `read_frames` espera a que el dispositivo
+/// entregue muestras y devuelve 0 cuando se ha capturado la duración pedida. El flujo de
+/// `cpal` no siempre se puede mover entre hilos, así que el origen se lee en el hilo que lo
+/// abrió.
+pub struct MicSource {
+    _stream: Stream,
+    rx: Receiver<Vec<f32>>,
+    pending: Vec<f32>,
+    pos: usize,
+    remaining: usize,
+}
+
+impl MicSource {
+    /// Abre el micrófono por defecto y empieza a capturar.
+    ///
+    /// # Argumentos
+    ///
+    /// * `duration` - Tiempo de audio que entregará el origen antes de terminar.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<MicSource, SonoraError>` - El origen, o el error de `capture_microphone`.
+    pub fn open(duration: Duration) -> Result<Self, SonoraError> {
+        let (stream, rx) = capture_microphone()?;
+        Ok(Self {
+            _stream: stream,
+            rx,
+            pending: Vec::new(),
+            pos: 0,
+            remaining: (duration.as_secs_f64() * CANONICAL_SAMPLE_RATE as f64) as usize,
+        })
+    }
+}
+
+impl AudioSource for MicSource {
+    fn sample_rate(&self) -> u32 {
+        CANONICAL_SAMPLE_RATE
+    }
+
+    fn channels(&self) -> usize {
+        1
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        let wanted = buf.len().min(self.remaining);
+        if wanted == 0 {
+            return Ok(0);
+        }
+        if self.pos == self.pending.len() {
+            self.pending = self
+                .rx
+                .recv()
+                .map_err(|_| SonoraError::Capture("la captura se interrumpió".to_string()))?;
+            self.pos = 0;
+        }
+        let copied = copy_frames(&self.pending, &mut self.pos, &mut buf[..wanted], 1);
+        self.remaining -= copied;
+        Ok(copied)
+    }
+}
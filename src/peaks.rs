@@ -0,0 +1,144 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::config::FingerprintConfig;
+use crate::spectrogram::Spectrogram;
+
+/// Estructura para representar un pico detectado.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Peak {
+    pub time: f32,
+    pub frequency: f32,
+    /// Magnitud del bin en el espectrograma; ordena los picos por intensidad.
+    pub magnitude: f32,
+}
+
+/// Detector de picos del espectrograma.
+///
+/// Los picos son la entrada de los hashes de landmarks; cambiar de detector (umbral adaptativo,
+/// por bandas, filtro de máximo) no afecta al espectrograma ni a la generación de hashes.
+pub trait PeakDetector: Send + Sync {
+    /// Detecta los picos de un espectrograma.
+    ///
+    /// # Argumentos
+    ///
+    /// * `spectrogram` - Espectrograma calculado.
+    /// * `config` - Parámetros del pipeline.
+    ///
+    /// # Retorna
+    ///
+    /// * `Vec<Peak>` - Picos detectados, en cualquier orden.
+    fn detect(&self, spectrogram: &Spectrogram, config: &FingerprintConfig) -> Vec<Peak>;
+}
+
+/// Detector por defecto: máximos locales en un vecindario cuadrado, como `find_peaks`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NeighborhoodDetector;
+
+impl PeakDetector for NeighborhoodDetector {
+    fn detect(&self, spectrogram: &Spectrogram, config: &FingerprintConfig) -> Vec<Peak> {
+        find_peaks(spectrogram, config)
+    }
+}
+
+/// Encuentra picos en el espectrograma.
+///
+/// Un bin es pico si su magnitud alcanza `amp_min` y ningún bin de su vecindario
+/// `(2n+1)×(2n+1)` (recortado en los bordes) la supera. El máximo del vecindario se calcula
+/// de forma separable: primero un filtro de máximo a lo largo de la frecuencia, con una cola
+/// monótona en O(F) por ventana, y después el máximo de esas filas a lo largo del tiempo. La
+/// segunda pasada solo se evalúa en los bins que ya son máximo de su fila y superan `amp_min`,
+/// que son pocos, así que el coste queda en O(T·F) más un término pequeño por candidato, en
+/// lugar de recorrer el vecindario completo de cada bin.
+///
+/// # Argumentos
+///
+/// * `spectrogram` - Espectrograma calculado.
+/// * `config` - Parámetros del pipeline; usa el umbral `amp_min` y el tamaño del vecindario
+///   `neighborhood_size` para la detección de máximos locales.
+///
+/// # Retorna
+///
+/// * `Vec<Peak>` - Vector de picos detectados, ordenados por tiempo y luego por frecuencia.
+pub fn find_peaks(spectrogram: &Spectrogram, config: &FingerprintConfig) -> Vec<Peak> {
+    let amp_min = config.amp_min;
+    let neighborhood_size = config.neighborhood_size;
+    let num_freqs = spectrogram.frequencies.len();
+    let num_times = spectrogram.times.len();
+
+    // Máximo del vecindario a lo largo de la frecuencia, ventana a ventana.
+    let mut queue = Vec::new();
+    let row_max: Vec<Vec<f32>> = spectrogram
+        .magnitudes
+        .iter()
+        .map(|row| {
+            let mut out = vec![0.0f32; num_freqs];
+            sliding_max(&row[..num_freqs], neighborhood_size, &mut out, &mut queue);
+            out
+        })
+        .collect();
+
+    let mut peaks = Vec::new();
+    for t in 0..num_times {
+        let t_start = t.saturating_sub(neighborhood_size);
+        let t_end = (t + neighborhood_size).min(num_times - 1);
+
+        for f in 0..num_freqs {
+            let magnitude = spectrogram.magnitudes[t][f];
+            if magnitude < amp_min || magnitude < row_max[t][f] {
+                continue;
+            }
+
+            // Máximo a lo largo del tiempo de los máximos por fila.
+            if row_max[t_start..=t_end]
+                .iter()
+                .all(|row| row[f] <= magnitude)
+            {
+                peaks.push(Peak {
+                    time: spectrogram.times[t],
+                    frequency: spectrogram.frequencies[f],
+                    magnitude,
+                });
+            }
+        }
+    }
+
+    peaks
+}
+
+/// Máximo de una ventana deslizante `[i - radius, i + radius]`, recortada a los bordes.
+///
+/// Mantiene una cola monótona con los índices de los candidatos a máximo, de valores
+/// decrecientes; cada índice entra y sale una sola vez, así que el coste es lineal en la
+/// longitud.
+///
+/// # Argumentos
+///
+/// * `input` - Valores de entrada.
+/// * `radius` - Radio de la ventana.
+/// * `out` - Destino, de la misma longitud que `input`.
+/// * `queue` - Memoria de la cola, reutilizada entre llamadas para no reservar en cada fila.
+fn sliding_max(input: &[f32], radius: usize, out: &mut [f32], queue: &mut Vec<usize>) {
+    let len = input.len();
+    queue.clear();
+    queue.resize(len, 0);
+    let (mut head, mut tail) = (0, 0);
+
+    for i in 0..len + radius {
+        if i < len {
+            while tail > head && input[queue[tail - 1]] <= input[i] {
+                tail -= 1;
+            }
+            queue[tail] = i;
+            tail += 1;
+        }
+        if i >= radius {
+            let center = i - radius;
+            if queue[head] + radius < center {
+                head += 1;
+            }
+            out[center] = input[queue[head]];
+        }
+    }
+}
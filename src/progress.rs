@@ -0,0 +1,56 @@
+use crate::cancel::CancelToken;
+use crate::error::SonoraError;
+
+/// Avance de un cálculo de huellas, tal como lo recibe un [`ProgressSink`].
+///
+/// Los contadores son acumulados: cada aviso de una etapa sustituye al anterior de la misma.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// Audio leído del origen. `bytes` solo lo conocen los orígenes que decodifican un archivo.
+    Decoded { frames: u64, bytes: Option<u64> },
+    /// Ventanas de la FFT calculadas, de `total`.
+    Windows { done: usize, total: usize },
+    /// Picos detectados; solo lo avisan los esquemas de landmarks.
+    Peaks { count: usize },
+    /// Hashes generados; es el último aviso de un cálculo que termina bien.
+    Hashes { count: usize },
+}
+
+/// Destino de los avisos de avance, por ejemplo una barra de progreso.
+///
+/// Cualquier clausura `Fn(Progress)` que se pueda compartir entre hilos lo implementa. Se
+/// llama desde el hilo que calcula las huellas, así que debe volver enseguida.
+pub trait ProgressSink: Send + Sync {
+    /// Recibe un aviso de avance.
+    fn report(&self, progress: Progress);
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(Progress) + Send + Sync,
+{
+    fn report(&self, progress: Progress) {
+        self(progress)
+    }
+}
+
+/// Cancelación y avisos opcionales que el pipeline comprueba por el camino.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Hooks<'a> {
+    pub(crate) cancel: Option<&'a CancelToken>,
+    pub(crate) progress: Option<&'a dyn ProgressSink>,
+}
+
+impl Hooks<'_> {
+    /// Devuelve `Cancelled` si se pidió la cancelación.
+    pub(crate) fn check(&self) -> Result<(), SonoraError> {
+        self.cancel.map_or(Ok(()), CancelToken::check)
+    }
+
+    /// Pasa un aviso al destino, si lo hay.
+    pub(crate) fn report(&self, progress: Progress) {
+        if let Some(sink) = self.progress {
+            sink.report(progress);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::dsp::Decimator;
+
+/// Tasa de muestreo a la que se llevan todas las entradas antes de generar huellas, salvo que
+/// `FingerprintConfig::canonical_rate` indique otra.
+///
+/// 11025 Hz cubre hasta 5.5 kHz, suficiente para los picos del espectrograma, y hace que las
+/// huellas no dependan de la tasa de muestreo del archivo original.
+pub const CANONICAL_SAMPLE_RATE: u32 = 11025;
+
+/// Cruces por cero del núcleo sinc a cada lado de la muestra interpolada.
+const ZERO_CROSSINGS: f64 = 16.0;
+
+/// Cambia la tasa de muestreo de una señal mono con interpolación sinc enventanada.
+///
+/// El núcleo sinc se enventana con Hann y, al reducir la tasa, su corte baja a la nueva
+/// frecuencia de Nyquist para que el contenido alto no se pliegue sobre las bandas útiles.
+/// Cuando `from` es múltiplo entero de `to` (por ejemplo, de 44100 a 11025 Hz) no hace falta
+/// interpolar y el mismo filtro se aplica con un [`Decimator`], que solo lo evalúa en las
+/// muestras que se conservan.
+///
+/// # Argumentos
+///
+/// * `samples` - Muestras de la señal.
+/// * `from` - Tasa de muestreo de la entrada.
+/// * `to` - Tasa de muestreo deseada.
+///
+/// # Retorna
+///
+/// * `Vec<f32>` - Señal remuestreada.
+pub fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = to as f64 / from as f64;
+    let out_len = (samples.len() as f64 * ratio).floor() as usize;
+    if let Some(mut decimator) = integer_decimator(from, to) {
+        let mut out = Vec::with_capacity(out_len + 1);
+        decimator.process(samples, &mut out);
+        decimator.finish(&mut out);
+        out.truncate(out_len);
+        return out;
+    }
+
+    // Corte del filtro relativo al Nyquist de la entrada.
+    let cutoff = ratio.min(1.0);
+    let half_width = (ZERO_CROSSINGS / cutoff).ceil() as isize;
+    (0..out_len)
+        .map(|n| interpolate(samples, 0, n as f64 / ratio, cutoff, half_width))
+        .collect()
+}
+
+/// Diezmador con el núcleo de `resample` si `from` es un múltiplo entero de `to` mayor que él.
+///
+/// Con factor `M` el núcleo tiene `16 * M` muestras a cada lado, de las que la última cae en
+/// el cero de la ventana de Hann, así que bastan `2 * 16 * M - 1` coeficientes con el corte en
+/// la nueva frecuencia de Nyquist.
+fn integer_decimator(from: u32, to: u32) -> Option<Decimator> {
+    if to == 0 || from <= to || !from.is_multiple_of(to) {
+        return None;
+    }
+    let factor = (from / to) as usize;
+    Decimator::new(factor, 1.0, 2 * ZERO_CROSSINGS as usize * factor - 1).ok()
+}
+
+/// Remuestreador por bloques para señales que llegan troceadas, como la captura de audio o un
+/// archivo decodificado paquete a paquete.
+///
+/// Aplica el mismo núcleo que `resample` pero conserva entre bloques las muestras de entrada
+/// que aún necesita, así que los bordes de los bloques no introducen discontinuidades. Cada
+/// muestra de salida se emite cuando ya ha llegado todo su núcleo. Con una relación entera
+/// delega en un [`Decimator`], como `resample`.
+#[cfg(feature = "std")]
+pub(crate) struct StreamResampler {
+    decimator: Option<Decimator>,
+    ratio: f64,
+    cutoff: f64,
+    half_width: isize,
+    /// Muestras de entrada pendientes, a partir del índice absoluto `history_start`.
+    history: Vec<f32>,
+    history_start: usize,
+    /// Índice absoluto de la siguiente muestra de salida.
+    next_out: usize,
+}
+
+#[cfg(feature = "std")]
+impl StreamResampler {
+    pub(crate) fn new(from: u32, to: u32) -> Self {
+        let ratio = to as f64 / from as f64;
+        let cutoff = ratio.min(1.0);
+        Self {
+            decimator: integer_decimator(from, to),
+            ratio,
+            cutoff,
+            half_width: (ZERO_CROSSINGS / cutoff).ceil() as isize,
+            history: Vec::new(),
+            history_start: 0,
+            next_out: 0,
+        }
+    }
+
+    /// Añade un bloque de entrada y devuelve las muestras de salida que ya se pueden calcular.
+    pub(crate) fn push(&mut self, input: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        if let Some(decimator) = &mut self.decimator {
+            decimator.process(input, &mut out);
+            return out;
+        }
+
+        self.history.extend_from_slice(input);
+        let available_end = (self.history_start + self.history.len()) as isize;
+        loop {
+            let center = self.next_out as f64 / self.ratio;
+            if center.floor() as isize + self.half_width >= available_end {
+                break;
+            }
+            let local_center = center - self.history_start as f64;
+            out.push(interpolate(
+                &self.history,
+                self.history_start,
+                local_center,
+                self.cutoff,
+                self.half_width,
+            ));
+            self.next_out += 1;
+        }
+
+        // Descarta la entrada que ya no entra en ningún núcleo futuro.
+        let center = self.next_out as f64 / self.ratio;
+        let keep_from = (center.floor() as isize - self.half_width + 1).max(0) as usize;
+        let drop = keep_from
+            .saturating_sub(self.history_start)
+            .min(self.history.len());
+        self.history.drain(..drop);
+        self.history_start += drop;
+
+        out
+    }
+}
+
+/// Evalúa el núcleo sinc enventanado en `center`, en muestras relativas a `samples`.
+///
+/// `offset` es el índice absoluto de `samples[0]`: el núcleo se recorta al principio de la
+/// señal completa (índice absoluto 0) y al final de `samples`, como en `resample`.
+fn interpolate(samples: &[f32], offset: usize, center: f64, cutoff: f64, half_width: isize) -> f32 {
+    let last_index = samples.len() as isize - 1;
+    let first = (center.floor() as isize - half_width + 1).max(-(offset as isize));
+    let last = (center.floor() as isize + half_width).min(last_index);
+    let mut sum = 0.0;
+    for k in first.max(0)..=last {
+        let t = center - k as f64;
+        let window = 0.5 * (1.0 + (PI * t / half_width as f64).cos());
+        sum += samples[k as usize] as f64 * cutoff * sinc(cutoff * t) * window;
+    }
+    sum as f32
+}
+
+pub(crate) fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
@@ -0,0 +1,61 @@
+use core::fmt::Debug;
+
+use num_traits::{cast, Float, FromPrimitive, Signed};
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+/// Precisión con la que se calculan la ventana, la FFT y las magnitudes del espectrograma.
+///
+/// `f32` es la precisión por defecto y la más rápida; `f64` reduce el error de redondeo de la
+/// FFT a cambio de más memoria y tiempo. Solo la implementan esos dos tipos, que cumplen
+/// además los requisitos de `rustfft::FftNum`.
+pub trait FftFloat:
+    Float + FromPrimitive + Signed + Debug + Send + Sync + 'static + sealed::Sealed
+{
+}
+
+impl FftFloat for f32 {}
+impl FftFloat for f64 {}
+
+/// Convierte entre precisiones; entre `f32` y `f64` la conversión nunca falla.
+pub(crate) fn convert<T: FftFloat, U: FftFloat>(value: U) -> T {
+    cast(value).unwrap_or_else(T::nan)
+}
+
+/// Tipo de muestra de audio que acepta el espectrograma.
+///
+/// Las muestras en punto flotante se usan tal cual y las enteras se escalan a `[-1, 1)`, igual
+/// que al decodificar un archivo con symphonia.
+pub trait Sample: Copy + Send + Sync + 'static {
+    /// Convierte la muestra a la precisión `T`.
+    fn to_float<T: FftFloat>(self) -> T;
+}
+
+impl Sample for f32 {
+    fn to_float<T: FftFloat>(self) -> T {
+        convert(self)
+    }
+}
+
+impl Sample for f64 {
+    fn to_float<T: FftFloat>(self) -> T {
+        convert(self)
+    }
+}
+
+impl Sample for i16 {
+    fn to_float<T: FftFloat>(self) -> T {
+        convert(self as f64 / 32768.0)
+    }
+}
+
+impl Sample for i32 {
+    fn to_float<T: FftFloat>(self) -> T {
+        convert(self as f64 / 2147483648.0)
+    }
+}
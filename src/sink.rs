@@ -0,0 +1,45 @@
+use alloc::vec::Vec;
+
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+
+/// Destino de los hashes que produce el pipeline.
+///
+/// Desacopla el cálculo de adónde van las huellas: un vector en memoria, un archivo o cualquier
+/// escritor (`FingerprintWriter`), una base de datos (`DbInserter`) o una clausura que, por
+/// ejemplo, las envía por red. Los hashes llegan por lotes y en orden de tiempo del ancla; al
+/// terminar se llama a `finish` una sola vez.
+pub trait HashSink {
+    /// Recibe el siguiente lote de hashes.
+    ///
+    /// # Argumentos
+    ///
+    /// * `hashes` - Lote de hashes, en orden de tiempo del ancla.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<(), SonoraError>` - Un error detiene el cálculo y se devuelve tal cual.
+    fn accept(&mut self, hashes: &[HashEntry]) -> Result<(), SonoraError>;
+
+    /// Avisa de que no llegarán más hashes, para vaciar buffers o cerrar el destino. Por
+    /// defecto no hace nada.
+    fn finish(&mut self) -> Result<(), SonoraError> {
+        Ok(())
+    }
+}
+
+impl HashSink for Vec<HashEntry> {
+    fn accept(&mut self, hashes: &[HashEntry]) -> Result<(), SonoraError> {
+        self.extend_from_slice(hashes);
+        Ok(())
+    }
+}
+
+impl<F> HashSink for F
+where
+    F: FnMut(&[HashEntry]) -> Result<(), SonoraError>,
+{
+    fn accept(&mut self, hashes: &[HashEntry]) -> Result<(), SonoraError> {
+        self(hashes)
+    }
+}
@@ -0,0 +1,689 @@
+use std::fmt;
+use std::io::{ErrorKind, Read};
+use std::str::FromStr;
+use std::time::Duration;
+
+use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::probe::Hint;
+
+use crate::config::ChannelPolicy;
+use crate::decode::{DecodedAudio, TrackDecoder};
+use crate::error::SonoraError;
+use crate::sample::Sample;
+
+/// Tramas que el pipeline pide a un [`AudioSource`] en cada lectura.
+pub(crate) const BLOCK_FRAMES: usize = 4096;
+
+/// Origen de audio intercalado en `f32`, del que el pipeline lee tramas por bloques.
+///
+/// Desacopla el pipeline de dónde viene la señal: un archivo que se decodifica sobre la marcha
+/// ([`FileSource`]), PCM sin cabecera ([`RawSource`]), muestras ya en memoria
+/// ([`MemorySource`]) o una captura en vivo (`MicSource`, con la feature `mic`); [`Excerpt`]
+/// recorta cualquiera de ellos a un tramo.
+/// `fingerprint_source` y `stream_source` aceptan cualquiera de ellos, también en una `Box`.
+pub trait AudioSource {
+    /// Tasa de muestreo de las tramas, en Hz.
+    fn sample_rate(&self) -> u32;
+
+    /// Número de canales de cada trama.
+    fn channels(&self) -> usize;
+
+    /// Lee tramas intercaladas en `buf`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `buf` - Destino; se llenan como mucho `buf.len() / channels()` tramas completas.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<usize, SonoraError>` - Tramas escritas al principio de `buf`; 0 indica el
+    ///   final del audio.
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError>;
+
+    /// Bytes del archivo consumidos hasta ahora, para los orígenes que decodifican uno.
+    fn bytes_read(&self) -> Option<u64> {
+        None
+    }
+
+    /// Paquetes corruptos omitidos hasta ahora, para los orígenes que decodifican un archivo.
+    fn skipped_packets(&self) -> u64 {
+        0
+    }
+
+    /// Salta a la trama `frame`, contada desde el principio del audio, sin leer lo anterior.
+    ///
+    /// # Argumentos
+    ///
+    /// * `frame` - Trama en la que sigue la lectura.
+    ///
+    /// # Retorna
+    ///
+    /// * `bool` - `false` si el origen no sabe saltar; entonces sigue donde estaba y quien
+    ///   llama tiene que leer y descartar, como hace [`Excerpt`]. Es lo que hacen por defecto
+    ///   los orígenes.
+    fn seek(&mut self, frame: u64) -> bool {
+        let _ = frame;
+        false
+    }
+}
+
+/// Copia en `buf` todas las tramas completas que quepan de `pending[*pos..]` y avanza `pos`.
+///
+/// Devuelve el número de muestras copiadas.
+pub(crate) fn copy_frames(
+    pending: &[f32],
+    pos: &mut usize,
+    buf: &mut [f32],
+    channels: usize,
+) -> usize {
+    let available = pending.len() - *pos;
+    let len = available.min(buf.len()) / channels * channels;
+    buf[..len].copy_from_slice(&pending[*pos..*pos + len]);
+    *pos += len;
+    len
+}
+
+/// Archivo de audio (o cualquier flujo de bytes) decodificado paquete a paquete.
+///
+/// Solo guarda en memoria el paquete en curso, así que sirve para archivos de cualquier
+/// duración.
+pub struct FileSource {
+    track: TrackDecoder,
+    pending: Vec<f32>,
+    pos: usize,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl FileSource {
+    /// Abre un archivo de audio, detectando el contenedor con la extensión como pista.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta al archivo de audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<FileSource, SonoraError>` - El origen, o el error si el archivo no se puede
+    ///   abrir, su formato no se reconoce o no indica canales ni tasa de muestreo.
+    pub fn open(path: &str) -> Result<Self, SonoraError> {
+        Self::new(TrackDecoder::open_file(path, None)?)
+    }
+
+    /// Abre una pista de audio concreta de un contenedor con varias, como las de un MKV o un
+    /// MP4 con varios idiomas.
+    ///
+    /// # Argumentos
+    ///
+    /// * `path` - Ruta al archivo de audio.
+    /// * `track` - Índice de la pista entre las pistas de audio del contenedor, empezando en 0;
+    ///   la 0 suele ser la que abre `open`.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<FileSource, SonoraError>` - El origen, `TrackNotFound` si el contenedor no
+    ///   tiene esa pista, o el error como en `open`.
+    pub fn open_track(path: &str, track: usize) -> Result<Self, SonoraError> {
+        Self::new(TrackDecoder::open_file(path, Some(track))?)
+    }
+
+    /// Decodifica audio leído de cualquier origen; el contenedor se detecta por el contenido.
+    ///
+    /// # Argumentos
+    ///
+    /// * `reader` - Origen de los bytes del archivo de audio.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<FileSource, SonoraError>` - El origen, o el error como en `open`.
+    pub fn from_reader<R>(reader: R) -> Result<Self, SonoraError>
+    where
+        R: Read + Send + Sync + 'static,
+    {
+        let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
+        Self::new(TrackDecoder::open(mss, &Hint::new(), None)?)
+    }
+
+    /// Como `from_reader`, pero con la extensión del archivo original como pista para detectar
+    /// el contenedor, como hace `open` con la ruta. Sirve para flujos cuyo nombre se conoce,
+    /// como una descarga, cuando los primeros bytes no bastan para reconocer el formato.
+    ///
+    /// # Argumentos
+    ///
+    /// * `reader` - Origen de los bytes del archivo de audio.
+    /// * `extension` - Extensión sin el punto, por ejemplo `"mp3"`.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<FileSource, SonoraError>` - El origen, o el error como en `open`.
+    pub fn from_reader_with_extension<R>(reader: R, extension: &str) -> Result<Self, SonoraError>
+    where
+        R: Read + Send + Sync + 'static,
+    {
+        let mss = MediaSourceStream::new(Box::new(ReadOnlySource::new(reader)), Default::default());
+        let mut hint = Hint::new();
+        hint.with_extension(extension);
+        Self::new(TrackDecoder::open(mss, &hint, None)?)
+    }
+
+    fn new(track: TrackDecoder) -> Result<Self, SonoraError> {
+        let mut source = Self {
+            track,
+            pending: Vec::new(),
+            pos: 0,
+            channels: 0,
+            sample_rate: 0,
+        };
+        // Algunos contenedores no declaran el formato en la cabecera: se toma del primer paquete.
+        if source.track.channels == 0 || source.track.sample_rate == 0 {
+            source.refill()?;
+        }
+        if source.track.channels == 0 {
+            return Err(SonoraError::NoChannels);
+        }
+        if source.track.sample_rate == 0 {
+            return Err(SonoraError::UnknownSampleRate);
+        }
+        source.channels = source.track.channels;
+        source.sample_rate = source.track.sample_rate;
+        Ok(source)
+    }
+
+    /// Sustituye las muestras pendientes por las del siguiente paquete con audio; `false` al
+    /// final del flujo.
+    fn refill(&mut self) -> Result<bool, SonoraError> {
+        self.pending.clear();
+        self.pos = 0;
+        while self.pending.is_empty() {
+            match self.track.read_packet(&mut self.pending) {
+                None => return Ok(false),
+                Some(result) => result?,
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl AudioSource for FileSource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        let mut written = 0;
+        while buf.len() - written >= self.channels {
+            if self.pos == self.pending.len() && !self.refill()? {
+                break;
+            }
+            let copied = copy_frames(
+                &self.pending,
+                &mut self.pos,
+                &mut buf[written..],
+                self.channels,
+            );
+            if copied == 0 {
+                // Trama incompleta al final del paquete: se descarta.
+                self.pos = self.pending.len();
+            }
+            written += copied;
+        }
+        Ok(written / self.channels)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.track.bytes_read)
+    }
+
+    fn skipped_packets(&self) -> u64 {
+        self.track.skipped_packets
+    }
+
+    fn seek(&mut self, frame: u64) -> bool {
+        if !self.track.seek(frame) {
+            return false;
+        }
+        self.pending.clear();
+        self.pos = 0;
+        true
+    }
+}
+
+/// Audio intercalado que ya está en memoria.
+#[derive(Debug, Clone)]
+pub struct MemorySource {
+    samples: Vec<f32>,
+    pos: usize,
+    channels: usize,
+    sample_rate: u32,
+}
+
+impl MemorySource {
+    /// Crea un origen a partir de muestras intercaladas.
+    ///
+    /// # Argumentos
+    ///
+    /// * `samples` - Muestras intercaladas por canal; una trama incompleta al final se ignora.
+    /// * `channels` - Número de canales; 0 se trata como mono.
+    /// * `sample_rate` - Tasa de muestreo, en Hz.
+    pub fn new(samples: Vec<f32>, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            samples,
+            pos: 0,
+            channels: channels.max(1),
+            sample_rate,
+        }
+    }
+
+    /// Como `new`, pero convierte muestras de cualquier tipo [`Sample`], por ejemplo `i16`.
+    ///
+    /// # Argumentos
+    ///
+    /// * `samples` - Muestras intercaladas por canal.
+    /// * `channels` - Número de canales; 0 se trata como mono.
+    /// * `sample_rate` - Tasa de muestreo, en Hz.
+    pub fn from_samples<S: Sample>(samples: &[S], channels: usize, sample_rate: u32) -> Self {
+        let samples = samples.iter().map(|s| s.to_float()).collect();
+        Self::new(samples, channels, sample_rate)
+    }
+}
+
+impl From<DecodedAudio> for MemorySource {
+    fn from(audio: DecodedAudio) -> Self {
+        Self::new(audio.samples, audio.channels, audio.sample_rate)
+    }
+}
+
+impl AudioSource for MemorySource {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        Ok(copy_frames(&self.samples, &mut self.pos, buf, self.channels) / self.channels)
+    }
+
+    fn seek(&mut self, frame: u64) -> bool {
+        let frames = (self.samples.len() / self.channels) as u64;
+        self.pos = frame.min(frames) as usize * self.channels;
+        true
+    }
+}
+
+impl<S: AudioSource + ?Sized> AudioSource for Box<S> {
+    fn sample_rate(&self) -> u32 {
+        (**self).sample_rate()
+    }
+
+    fn channels(&self) -> usize {
+        (**self).channels()
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        (**self).read_frames(buf)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        (**self).bytes_read()
+    }
+
+    fn skipped_packets(&self) -> u64 {
+        (**self).skipped_packets()
+    }
+
+    fn seek(&mut self, frame: u64) -> bool {
+        (**self).seek(frame)
+    }
+}
+
+/// Tramo de otro origen: empieza en `start` y dura como mucho `duration`.
+///
+/// Salta al principio del tramo con `AudioSource::seek` cuando el origen lo admite, como un
+/// archivo con un contenedor que sabe saltar, y si no lee y descarta hasta llegar a él. Los
+/// tiempos de las huellas del tramo cuentan desde `start`.
+#[derive(Debug)]
+pub struct Excerpt<S> {
+    inner: S,
+    /// Tramas que aún hay que leer y descartar antes del tramo.
+    skip: u64,
+    /// Tramas del tramo que quedan por entregar, sin límite con `None`.
+    remaining: Option<u64>,
+}
+
+impl<S: AudioSource> Excerpt<S> {
+    /// Crea el tramo y salta a su principio.
+    ///
+    /// # Argumentos
+    ///
+    /// * `inner` - Origen completo, sin leer todavía.
+    /// * `start` - Inicio del tramo desde el principio del audio.
+    /// * `duration` - Duración máxima del tramo; con `None`, hasta el final.
+    pub fn new(mut inner: S, start: Duration, duration: Option<Duration>) -> Self {
+        let rate = inner.sample_rate() as f64;
+        let start = (start.as_secs_f64() * rate).round() as u64;
+        let skip = if start == 0 || inner.seek(start) {
+            0
+        } else {
+            start
+        };
+        Self {
+            inner,
+            skip,
+            remaining: duration.map(|duration| (duration.as_secs_f64() * rate).round() as u64),
+        }
+    }
+}
+
+impl<S: AudioSource> AudioSource for Excerpt<S> {
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn channels(&self) -> usize {
+        self.inner.channels()
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        let channels = self.inner.channels().max(1);
+        let capacity = buf.len() / channels;
+        while self.skip > 0 {
+            let frames = capacity.min(self.skip as usize);
+            let read = self.inner.read_frames(&mut buf[..frames * channels])?;
+            if read == 0 {
+                return Ok(0);
+            }
+            self.skip -= read as u64;
+        }
+
+        let frames = match self.remaining {
+            Some(remaining) => capacity.min(remaining as usize),
+            None => capacity,
+        };
+        if frames == 0 {
+            return Ok(0);
+        }
+        let read = self.inner.read_frames(&mut buf[..frames * channels])?;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= read as u64;
+        }
+        Ok(read)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.inner.bytes_read()
+    }
+
+    fn skipped_packets(&self) -> u64 {
+        self.inner.skipped_packets()
+    }
+}
+
+/// Origen que reduce los canales de otro según una [`ChannelPolicy`].
+///
+/// Con `Mix` es transparente y deja la mezcla y la normalización a cargo del pipeline, como
+/// siempre; con el resto entrega un único canal.
+pub(crate) struct ChannelSource<S> {
+    inner: S,
+    policy: ChannelPolicy,
+    frames: Vec<f32>,
+}
+
+impl<S: AudioSource> ChannelSource<S> {
+    /// Envuelve `inner`; falla con `InvalidConfig` si la política es `PerChannel`, que no se
+    /// reduce a una sola señal.
+    pub(crate) fn new(inner: S, policy: ChannelPolicy) -> Result<Self, SonoraError> {
+        if policy == ChannelPolicy::PerChannel {
+            return Err(SonoraError::InvalidConfig(
+                "la política per-channel da una huella por canal: usa fingerprint_channels"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            inner,
+            policy,
+            frames: Vec::new(),
+        })
+    }
+}
+
+impl<S: AudioSource> AudioSource for ChannelSource<S> {
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn channels(&self) -> usize {
+        match self.policy {
+            ChannelPolicy::Mix => self.inner.channels(),
+            _ => self.inner.channels().min(1),
+        }
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        let channels = self.inner.channels();
+        if self.policy == ChannelPolicy::Mix || channels == 0 {
+            return self.inner.read_frames(buf);
+        }
+        self.frames.resize(buf.len() * channels, 0.0);
+        let read = self.inner.read_frames(&mut self.frames)?;
+        // Con menos de dos canales, el derecho es el mismo que el izquierdo.
+        let right = 1.min(channels - 1);
+        let frames = self.frames[..read * channels].chunks_exact(channels);
+        for (out, frame) in buf.iter_mut().zip(frames) {
+            *out = match self.policy {
+                ChannelPolicy::Left => frame[0],
+                ChannelPolicy::Right => frame[right],
+                ChannelPolicy::Mid => (frame[0] + frame[right]) / 2.0,
+                ChannelPolicy::Side => (frame[0] - frame[right]) / 2.0,
+                ChannelPolicy::Mix | ChannelPolicy::PerChannel => unreachable!(),
+            };
+        }
+        Ok(read)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.inner.bytes_read()
+    }
+
+    fn skipped_packets(&self) -> u64 {
+        self.inner.skipped_packets()
+    }
+
+    fn seek(&mut self, frame: u64) -> bool {
+        self.inner.seek(frame)
+    }
+}
+
+/// Codificación de las muestras de un flujo PCM sin cabecera; los nombres son los de ffmpeg.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PcmFormat {
+    /// Enteros de 8 bits sin signo, con el silencio en 128.
+    U8,
+    /// Enteros de 16 bits con signo, little-endian.
+    #[default]
+    S16Le,
+    /// Enteros de 16 bits con signo, big-endian.
+    S16Be,
+    /// Enteros de 24 bits con signo en 3 bytes, little-endian.
+    S24Le,
+    /// Enteros de 32 bits con signo, little-endian.
+    S32Le,
+    /// Flotantes de 32 bits en `[-1.0, 1.0]`, little-endian.
+    F32Le,
+    /// Flotantes de 64 bits en `[-1.0, 1.0]`, little-endian.
+    F64Le,
+}
+
+impl PcmFormat {
+    /// Todas las codificaciones, en el orden en que se documentan.
+    pub const ALL: [PcmFormat; 7] = [
+        PcmFormat::U8,
+        PcmFormat::S16Le,
+        PcmFormat::S16Be,
+        PcmFormat::S24Le,
+        PcmFormat::S32Le,
+        PcmFormat::F32Le,
+        PcmFormat::F64Le,
+    ];
+
+    /// Nombre de la codificación en la línea de comandos, el mismo que usa ffmpeg con `-f`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PcmFormat::U8 => "u8",
+            PcmFormat::S16Le => "s16le",
+            PcmFormat::S16Be => "s16be",
+            PcmFormat::S24Le => "s24le",
+            PcmFormat::S32Le => "s32le",
+            PcmFormat::F32Le => "f32le",
+            PcmFormat::F64Le => "f64le",
+        }
+    }
+
+    /// Bytes que ocupa cada muestra.
+    pub fn bytes_per_sample(self) -> usize {
+        match self {
+            PcmFormat::U8 => 1,
+            PcmFormat::S16Le | PcmFormat::S16Be => 2,
+            PcmFormat::S24Le => 3,
+            PcmFormat::S32Le | PcmFormat::F32Le => 4,
+            PcmFormat::F64Le => 8,
+        }
+    }
+
+    /// Convierte una muestra de `bytes_per_sample` bytes a `f32` en `[-1.0, 1.0]`.
+    fn decode(self, bytes: &[u8]) -> f32 {
+        match self {
+            PcmFormat::U8 => (bytes[0] as f32 - 128.0) / 128.0,
+            PcmFormat::S16Le => i16::from_le_bytes([bytes[0], bytes[1]]).to_float(),
+            PcmFormat::S16Be => i16::from_be_bytes([bytes[0], bytes[1]]).to_float(),
+            // Los 24 bits van a la parte alta de un `i32` para conservar el signo.
+            PcmFormat::S24Le => i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]).to_float(),
+            PcmFormat::S32Le => {
+                i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).to_float()
+            }
+            PcmFormat::F32Le => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            PcmFormat::F64Le => {
+                let mut raw = [0; 8];
+                raw.copy_from_slice(&bytes[..8]);
+                f64::from_le_bytes(raw) as f32
+            }
+        }
+    }
+}
+
+impl fmt::Display for PcmFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PcmFormat {
+    type Err = SonoraError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PcmFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| SonoraError::InvalidConfig(format!("formato PCM desconocido: {s}")))
+    }
+}
+
+/// Flujo PCM intercalado sin cabecera, como el que producen `ffmpeg -f s16le -`, sox o un SDR.
+///
+/// Como no hay cabecera, la codificación, los canales y la tasa se indican al crearlo. Las
+/// muestras se leen del lector a medida que el pipeline las pide; una trama incompleta al final
+/// del flujo se descarta.
+#[derive(Debug)]
+pub struct RawSource<R> {
+    reader: R,
+    format: PcmFormat,
+    channels: usize,
+    sample_rate: u32,
+    bytes: Vec<u8>,
+    bytes_read: u64,
+}
+
+impl<R: Read> RawSource<R> {
+    /// Crea el origen.
+    ///
+    /// # Argumentos
+    ///
+    /// * `reader` - Origen de los bytes; conviene que tenga buffer, como un `BufReader`.
+    /// * `format` - Codificación de las muestras.
+    /// * `channels` - Número de canales intercalados.
+    /// * `sample_rate` - Tasa de muestreo, en Hz.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<RawSource<R>, SonoraError>` - El origen, o `NoChannels` o `UnknownSampleRate`
+    ///   si los canales o la tasa son 0.
+    pub fn new(
+        reader: R,
+        format: PcmFormat,
+        channels: usize,
+        sample_rate: u32,
+    ) -> Result<Self, SonoraError> {
+        if channels == 0 {
+            return Err(SonoraError::NoChannels);
+        }
+        if sample_rate == 0 {
+            return Err(SonoraError::UnknownSampleRate);
+        }
+        Ok(Self {
+            reader,
+            format,
+            channels,
+            sample_rate,
+            bytes: Vec::new(),
+            bytes_read: 0,
+        })
+    }
+}
+
+impl<R: Read> AudioSource for RawSource<R> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> usize {
+        self.channels
+    }
+
+    fn read_frames(&mut self, buf: &mut [f32]) -> Result<usize, SonoraError> {
+        let sample_bytes = self.format.bytes_per_sample();
+        let frame_bytes = sample_bytes * self.channels;
+        self.bytes
+            .resize(buf.len() / self.channels * frame_bytes, 0);
+
+        // Un `read` puede devolver menos de lo pedido sin que el flujo haya terminado.
+        let mut filled = 0;
+        while filled < self.bytes.len() {
+            match self.reader.read(&mut self.bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        self.bytes_read += filled as u64;
+
+        let frames = filled / frame_bytes;
+        for (dst, sample) in buf
+            .iter_mut()
+            .zip(self.bytes[..frames * frame_bytes].chunks_exact(sample_bytes))
+        {
+            *dst = self.format.decode(sample);
+        }
+        Ok(frames)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.bytes_read)
+    }
+}
@@ -0,0 +1,346 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+#[cfg(feature = "std")]
+use std::any::{Any, TypeId};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, OnceLock};
+
+use num_complex::Complex;
+#[cfg(not(feature = "std"))]
+use num_traits::Float;
+
+use crate::config::FingerprintConfig;
+use crate::error::SonoraError;
+use crate::progress::{Hooks, Progress};
+use crate::sample::{convert, FftFloat, Sample};
+#[cfg(feature = "std")]
+use crate::transform::RustFftTransform;
+use crate::transform::Transform;
+
+/// Estructura para almacenar el espectrograma.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spectrogram {
+    pub frequencies: Vec<f32>,
+    pub times: Vec<f32>,
+    pub magnitudes: Vec<Vec<f32>>,
+    /// Tasa de muestreo de la señal original.
+    pub sample_rate: usize,
+    /// Tamaño de la ventana para FFT.
+    pub window_size: usize,
+    /// Salto entre ventanas consecutivas, en muestras.
+    pub hop_size: usize,
+}
+
+impl Spectrogram {
+    /// Ventanas por segundo del eje temporal.
+    pub fn frame_rate(&self) -> f32 {
+        self.sample_rate as f32 / self.hop_size as f32
+    }
+
+    /// Separación en Hz entre bins consecutivos del eje de frecuencias.
+    pub fn freq_resolution(&self) -> f32 {
+        self.sample_rate as f32 / self.window_size as f32
+    }
+}
+
+/// Genera una ventana de Hann.
+///
+/// # Argumentos
+///
+/// * `size` - Tamaño de la ventana.
+///
+/// # Retorna
+///
+/// * `Vec<f32>` - Ventana de Hann.
+pub fn hann_window(size: usize) -> Vec<f32> {
+    window_in(WindowFn::Hann, size)
+}
+
+/// Función de ventana aplicada a cada ventana antes de la FFT.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum WindowFn {
+    /// Hann: buen compromiso entre resolución y fugas espectrales.
+    #[default]
+    Hann,
+    /// Hamming: lóbulo principal parecido al de Hann con el primer lóbulo lateral más bajo.
+    Hamming,
+    /// Blackman: lóbulos laterales mucho más bajos a cambio de un lóbulo principal más ancho.
+    Blackman,
+    /// Rectangular: sin ventana; útil para depurar.
+    Rectangular,
+}
+
+/// Genera los coeficientes de una función de ventana.
+///
+/// Todas las ventanas son periódicas (el denominador es `size` y no `size - 1`), como la de
+/// Hann que ya usaba el espectrograma.
+///
+/// # Argumentos
+///
+/// * `kind` - Función de ventana.
+/// * `size` - Tamaño de la ventana.
+///
+/// # Retorna
+///
+/// * `Vec<f32>` - Coeficientes de la ventana.
+pub fn window(kind: WindowFn, size: usize) -> Vec<f32> {
+    window_in(kind, size)
+}
+
+/// Coeficientes de `window` calculados en la precisión `T`.
+fn window_in<T: FftFloat>(kind: WindowFn, size: usize) -> Vec<T> {
+    let c = |value: f64| -> T { convert(value) };
+    let phase = |i: usize| c(2.0) * c(PI) * c(i as f64) / c(size as f64);
+    match kind {
+        WindowFn::Hann => (0..size)
+            .map(|i| c(0.5) * (T::one() - phase(i).cos()))
+            .collect(),
+        WindowFn::Hamming => (0..size)
+            .map(|i| c(0.54) - c(0.46) * phase(i).cos())
+            .collect(),
+        WindowFn::Blackman => (0..size)
+            .map(|i| c(0.42) - c(0.5) * phase(i).cos() + c(0.08) * (c(2.0) * phase(i)).cos())
+            .collect(),
+        WindowFn::Rectangular => vec![T::one(); size],
+    }
+}
+
+/// Devuelve la ventana del tipo y tamaño indicados, reutilizando los coeficientes ya calculados.
+///
+/// La caché es global y segura entre hilos, de modo que procesar muchos archivos con la misma
+/// ventana solo evalúa los cosenos una vez; `calculate_spectrogram` la usa en cada llamada.
+///
+/// # Argumentos
+///
+/// * `kind` - Función de ventana.
+/// * `size` - Tamaño de la ventana.
+///
+/// # Retorna
+///
+/// * `Arc<Vec<f32>>` - Coeficientes de `window`, compartidos con las demás llamadas con el
+///   mismo tipo y tamaño.
+#[cfg(feature = "std")]
+pub fn cached_window(kind: WindowFn, size: usize) -> Arc<Vec<f32>> {
+    cached_window_in(kind, size)
+}
+
+/// Coeficientes de `cached_window` en la precisión `T`; la caché distingue las precisiones.
+#[cfg(feature = "std")]
+fn cached_window_in<T: FftFloat>(kind: WindowFn, size: usize) -> Arc<Vec<T>> {
+    type WindowCache = HashMap<(TypeId, WindowFn, usize), Arc<dyn Any + Send + Sync>>;
+    static CACHE: OnceLock<Mutex<WindowCache>> = OnceLock::new();
+
+    let mut cache = CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let coefficients = cache
+        .entry((TypeId::of::<T>(), kind, size))
+        .or_insert_with(|| Arc::new(window_in::<T>(kind, size)))
+        .clone();
+    match coefficients.downcast() {
+        Ok(coefficients) => coefficients,
+        Err(_) => unreachable!("la clave incluye el tipo de los coeficientes"),
+    }
+}
+
+/// Sin `std` no hay una caché global: los coeficientes se calculan en cada espectrograma.
+#[cfg(not(feature = "std"))]
+fn cached_window_in<T: FftFloat>(kind: WindowFn, size: usize) -> Vec<T> {
+    window_in(kind, size)
+}
+
+/// Calcula el espectrograma utilizando FFT.
+///
+/// Usa `window_size`, `overlap`, `window_fn` y `remove_dc` de la configuración. El
+/// solapamiento debe ser menor que la ventana; con `overlap == 0` las ventanas son contiguas
+/// (salto igual a `window_size`), como en los binarios de bandas, y se obtienen
+/// `samples.len() / window_size` ventanas. Con `remove_dc` se resta la media de cada ventana
+/// antes de aplicar la ventana para que un offset de DC no sesgue el bin 0 ni se filtre a los
+/// bins bajos.
+///
+/// Las muestras pueden ser de cualquier tipo [`Sample`] (`f32`, `f64`, `i16` o `i32`) y el
+/// cálculo se hace en `f32`; `calculate_spectrogram_with` permite elegir la precisión.
+///
+/// # Argumentos
+///
+/// * `samples` - Vector de muestras de audio.
+/// * `sample_rate` - Tasa de muestreo.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Spectrogram` - Espectrograma calculado.
+///
+/// # Panics
+///
+/// Si `overlap` no es menor que `window_size`; las funciones de huellas lo comprueban antes y
+/// devuelven `InvalidConfig`.
+#[cfg(feature = "std")]
+pub fn calculate_spectrogram<S: Sample>(
+    samples: &[S],
+    sample_rate: usize,
+    config: &FingerprintConfig,
+) -> Spectrogram {
+    calculate_spectrogram_with(
+        samples,
+        sample_rate,
+        config,
+        &RustFftTransform::new(config.window_size),
+    )
+}
+
+/// Calcula el espectrograma con una implementación de FFT a elección.
+///
+/// La ventana, la FFT y las magnitudes se calculan en la precisión de `transform` (`f32` o
+/// `f64`); el espectrograma resultante guarda siempre las magnitudes en `f32`.
+///
+/// # Argumentos
+///
+/// * `samples` - Vector de muestras de audio.
+/// * `sample_rate` - Tasa de muestreo.
+/// * `config` - Parámetros del pipeline.
+/// * `transform` - FFT de `config.window_size` puntos.
+///
+/// # Retorna
+///
+/// * `Spectrogram` - Espectrograma calculado.
+///
+/// # Panics
+///
+/// Si `overlap` no es menor que `window_size`; las funciones de huellas lo comprueban antes y
+/// devuelven `InvalidConfig`.
+pub fn calculate_spectrogram_with<S: Sample, T: FftFloat>(
+    samples: &[S],
+    sample_rate: usize,
+    config: &FingerprintConfig,
+    transform: &dyn Transform<T>,
+) -> Spectrogram {
+    match spectrogram_with_hooks(samples, sample_rate, config, transform, Hooks::default()) {
+        Ok(spectrogram) => spectrogram,
+        Err(SonoraError::InvalidConfig(reason)) => panic!("{reason}"),
+        Err(_) => unreachable!("sin token no se puede cancelar"),
+    }
+}
+
+/// Ventanas entre dos avisos de avance del espectrograma.
+const PROGRESS_WINDOWS: usize = 256;
+
+/// Como `calculate_spectrogram_with`, pero comprueba la cancelación antes de cada ventana y
+/// avisa del avance cada `PROGRESS_WINDOWS` ventanas y al terminar.
+pub(crate) fn spectrogram_with_hooks<S: Sample, T: FftFloat>(
+    samples: &[S],
+    sample_rate: usize,
+    config: &FingerprintConfig,
+    transform: &dyn Transform<T>,
+    hooks: Hooks<'_>,
+) -> Result<Spectrogram, SonoraError> {
+    config.check_window()?;
+    let window_size = config.window_size;
+    let hop_size = window_size - config.overlap;
+    let num_windows = if samples.len() < window_size {
+        0
+    } else {
+        ((samples.len() - window_size) / hop_size) + 1
+    };
+
+    let _span = tracing::debug_span!("fft", windows = num_windows, window_size).entered();
+    let window = cached_window_in::<T>(config.window_fn, window_size);
+
+    let mut magnitudes = Vec::with_capacity(num_windows);
+    let mut frequencies = Vec::new();
+    let mut times = Vec::new();
+
+    for i in 0..num_windows {
+        hooks.check()?;
+        if i > 0 && i % PROGRESS_WINDOWS == 0 {
+            hooks.report(Progress::Windows {
+                done: i,
+                total: num_windows,
+            });
+        }
+        let start = i * hop_size;
+        let end = start + window_size;
+        let frame: Vec<T> = samples[start..end].iter().map(|s| s.to_float()).collect();
+        let mean = if config.remove_dc {
+            frame.iter().fold(T::zero(), |sum, &s| sum + s) / convert(window_size as f64)
+        } else {
+            T::zero()
+        };
+        let mut buffer: Vec<Complex<T>> = frame
+            .iter()
+            .zip(window.iter())
+            .map(|(&s, &w)| Complex::new((s - mean) * w, T::zero()))
+            .collect();
+
+        transform.forward(&mut buffer);
+
+        // Calcula magnitudes.
+        let magnitude: Vec<f32> = buffer
+            .iter()
+            .take(window_size / 2)
+            .map(|c| convert(c.norm()))
+            .collect();
+        magnitudes.push(magnitude);
+
+        // Solo calcular frecuencias y tiempos una vez.
+        if frequencies.is_empty() {
+            let freq_res = sample_rate as f32 / window_size as f32;
+            frequencies = (0..(window_size / 2))
+                .map(|i| i as f32 * freq_res)
+                .collect();
+        }
+
+        let time = start as f32 / sample_rate as f32;
+        times.push(time);
+    }
+
+    hooks.report(Progress::Windows {
+        done: num_windows,
+        total: num_windows,
+    });
+
+    Ok(Spectrogram {
+        frequencies,
+        times,
+        magnitudes,
+        sample_rate,
+        window_size,
+        hop_size,
+    })
+}
+
+/// Refuerza las frecuencias fundamentales con el espectro de producto armónico (HPS).
+///
+/// Para cada ventana multiplica la magnitud de cada bin por las de sus armónicos (las copias
+/// del espectro submuestreadas por 2, 3, ..., `harmonics`) y toma la raíz `harmonics`-ésima
+/// del producto, de modo que la escala de las magnitudes se conserva y `amp_min` sigue siendo
+/// comparable. Los bins cuyos armónicos superiores quedan fuera del espectro usan solo los
+/// armónicos disponibles. Así los picos se agrupan en notas en lugar de en ruido inarmónico.
+///
+/// # Argumentos
+///
+/// * `spectrogram` - Espectrograma a modificar en sitio.
+/// * `harmonics` - Número de armónicos a combinar; con 0 o 1 el espectrograma no cambia.
+pub fn harmonic_product_spectrum(spectrogram: &mut Spectrogram, harmonics: usize) {
+    if harmonics <= 1 {
+        return;
+    }
+
+    for magnitude in spectrogram.magnitudes.iter_mut() {
+        let num_bins = magnitude.len();
+        let emphasized: Vec<f32> = (0..num_bins)
+            .map(|f| {
+                let available = (1..=harmonics).take_while(|h| f * h < num_bins);
+                let (product, count) =
+                    available.fold((1.0f32, 0), |(p, n), h| (p * magnitude[f * h], n + 1));
+                product.powf(1.0 / count as f32)
+            })
+            .collect();
+        *magnitude = emphasized;
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::VecDeque;
+use std::thread;
+
+use crossbeam::channel::{bounded, Receiver};
+
+use crate::config::FingerprintConfig;
+use crate::decode::downmix_to_mono;
+use crate::error::SonoraError;
+use crate::hash::HashEntry;
+use crate::hasher::{Hasher, LandmarkHasher};
+use crate::peaks::NeighborhoodDetector;
+use crate::progress::{Hooks, Progress};
+use crate::resample::StreamResampler;
+use crate::source::{AudioSource, ChannelSource, FileSource, BLOCK_FRAMES};
+use crate::spectrogram::calculate_spectrogram;
+
+/// Segundos de audio nuevo que se acumulan antes de volver a calcular huellas.
+const STREAM_STEP_SECONDS: f32 = 1.0;
+
+/// Bloques que pueden esperar en el canal de `stream_source`; acota la memoria cuando la
+/// lectura va por delante del cálculo de huellas. `max_memory` puede reducirlo.
+const DECODE_QUEUE: usize = 32;
+
+/// Genera las huellas de un archivo de audio de forma perezosa, con memoria constante.
+///
+/// Es `stream_source` sobre un [`FileSource`]: el archivo se decodifica paquete a paquete, así
+/// que sirve para grabaciones de varias horas. Como en `stream_fingerprints`, la señal no se
+/// normaliza por su máximo (haría falta leer el archivo entero), así que los hashes pueden
+/// diferir de los de `fingerprint_file` en los picos cercanos a `amp_min`.
+///
+/// # Argumentos
+///
+/// * `path` - Ruta al archivo de audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `Result<FingerprintStream, SonoraError>` - Iterador de hashes en orden de tiempo del
+///   ancla, o el error si el archivo no se puede abrir, su formato no se reconoce o la
+///   ventana no admite el solapamiento (`InvalidConfig`).
+pub fn fingerprint_file_stream(
+    path: &str,
+    config: &FingerprintConfig,
+) -> Result<FingerprintStream, SonoraError> {
+    config.check_window()?;
+    Ok(stream_source(FileSource::open(path)?, config))
+}
+
+/// Genera huellas de forma perezosa a partir de cualquier [`AudioSource`].
+///
+/// El origen se lee por bloques en un hilo propio, se reduce a mono según `channel_policy` y
+/// se remuestrea a `canonical_rate`, y el resultado alimenta a `stream_fingerprints`.
+/// Solo se guardan en memoria unos pocos bloques y la zona pendiente del espectrograma. Si el
+/// iterador se descarta antes del final, el hilo se detiene en el siguiente bloque; un error de
+/// lectura termina el flujo, y con la política `PerChannel` o una ventana que no admite el
+/// solapamiento el flujo termina sin hashes.
+///
+/// # Argumentos
+///
+/// * `source` - Origen del audio.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `FingerprintStream` - Iterador de hashes en orden de tiempo del ancla.
+pub fn stream_source<S>(source: S, config: &FingerprintConfig) -> FingerprintStream
+where
+    S: AudioSource + Send + 'static,
+{
+    let block_bytes = BLOCK_FRAMES * std::mem::size_of::<f32>();
+    let (tx, rx) = bounded(config.budget_items(block_bytes, DECODE_QUEUE));
+    let (policy, rate) = (config.channel_policy, config.canonical_rate);
+
+    thread::spawn(move || {
+        let mut source = match ChannelSource::new(source, policy) {
+            Ok(source) => source,
+            Err(err) => {
+                tracing::error!(%err, "no se puede reducir el origen a mono");
+                return;
+            }
+        };
+        let channels = source.channels().max(1);
+        let mut resampler = StreamResampler::new(source.sample_rate(), rate);
+        let mut frames = vec![0.0; BLOCK_FRAMES * channels];
+        let mut mono = Vec::new();
+        loop {
+            let read = match source.read_frames(&mut frames) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) => {
+                    tracing::error!(%err, "decodificación abortada; huellas incompletas");
+                    break;
+                }
+            };
+            downmix_to_mono(&frames[..read * channels], channels, &mut mono);
+            let block = resampler.push(&mono);
+            mono.clear();
+            tracing::trace!(
+                queue_depth = tx.len(),
+                frames = block.len(),
+                "bloque decodificado"
+            );
+            if !block.is_empty() && tx.send(block).is_err() {
+                return;
+            }
+        }
+    });
+
+    stream_fingerprints(rx, config)
+}
+
+/// Genera huellas de forma incremental a partir de bloques de audio que llegan por un canal.
+///
+/// Los bloques son muestras mono a `canonical_rate` en `[-1.0, 1.0]` y pueden tener
+/// cualquier tamaño: las muestras sobrantes que no completan una ventana se guardan para el
+/// siguiente bloque, de modo que la FFT se desliza sin cortes entre bloques. Cada vez que se
+/// acumula un segundo de audio nuevo se recalcula el espectrograma de la zona pendiente y se
+/// emiten los hashes cuyos anclas ya no pueden cambiar: los que tienen por delante el
+/// vecindario de picos y `max_delta_t` (o el `max_dt` de la zona objetivo) completos. Los
+/// tiempos son absolutos desde la primera muestra recibida. Cuando el emisor cierra el canal
+/// se emite todo lo que queda.
+///
+/// Los hashes coinciden con los de `fingerprint_from_spectrogram` sobre la señal completa salvo
+/// con `anchor_stride > 1`, cuyo ranking por intensidad se hace por tramo y no sobre todo el
+/// audio, y con `dedup`, que solo colapsa las repeticiones dentro de cada tramo. A diferencia
+/// de `fingerprint_file`, la señal no se normaliza por su máximo.
+///
+/// # Argumentos
+///
+/// * `rx` - Canal por el que llegan los bloques de muestras.
+/// * `config` - Parámetros del pipeline.
+///
+/// # Retorna
+///
+/// * `FingerprintStream` - Hashes en orden de tiempo del ancla; el iterador se bloquea
+///   esperando audio y termina cuando el canal se cierra, o sin hashes si la ventana no admite
+///   el solapamiento.
+pub fn stream_fingerprints(
+    rx: Receiver<Vec<f32>>,
+    config: &FingerprintConfig,
+) -> FingerprintStream {
+    let state = StreamState::new(config)
+        .inspect_err(|err| tracing::error!(%err, "el flujo termina sin hashes"))
+        .ok();
+    FingerprintStream {
+        rx,
+        state,
+        pending: VecDeque::new(),
+        finished: false,
+    }
+}
+
+/// Pipeline de `fingerprint_source` cuando la configuración tiene `max_memory`: lee el origen
+/// por bloques y calcula las huellas con la zona pendiente del espectrograma, como
+/// `stream_source`, pero en el hilo que llama y con el esquema de hash `hasher`. Solo los
+/// hashes crecen con la duración del audio.
+pub(crate) fn run_streaming<S: AudioSource>(
+    mut source: S,
+    config: &FingerprintConfig,
+    hasher: &dyn Hasher,
+    hooks: Hooks<'_>,
+) -> Result<Vec<HashEntry>, SonoraError> {
+    let channels = source.channels();
+    if channels == 0 {
+        return Err(SonoraError::NoChannels);
+    }
+    let span = tracing::debug_span!(
+        "stream",
+        channels,
+        sample_rate = source.sample_rate(),
+        frames = tracing::field::Empty,
+        skipped_packets = tracing::field::Empty,
+        hashes = tracing::field::Empty
+    )
+    .entered();
+    let mut resampler = StreamResampler::new(source.sample_rate(), config.canonical_rate);
+    let mut state = StreamState::new(config)?;
+    let mut frames = vec![0.0; BLOCK_FRAMES * channels];
+    let mut mono = Vec::new();
+    let mut decoded = 0;
+    let mut hashes = Vec::new();
+    loop {
+        hooks.check()?;
+        let read = source.read_frames(&mut frames)?;
+        if read == 0 {
+            break;
+        }
+        decoded += read as u64;
+        hooks.report(Progress::Decoded {
+            frames: decoded,
+            bytes: source.bytes_read(),
+        });
+        downmix_to_mono(&frames[..read * channels], channels, &mut mono);
+        let block = resampler.push(&mono);
+        mono.clear();
+        state.push(&block, hasher, &mut hashes)?;
+    }
+    state.finish(hasher, &mut hashes)?;
+    span.record("frames", decoded);
+    span.record("skipped_packets", source.skipped_packets());
+    span.record("hashes", hashes.len());
+
+    hooks.report(Progress::Hashes {
+        count: hashes.len(),
+    });
+    Ok(hashes)
+}
+
+/// Iterador de huellas de `stream_fingerprints` y `fingerprint_file_stream`.
+///
+/// Produce cada [`HashEntry`] (tiempo del ancla y hash) en cuanto deja de poder cambiar, con
+/// los tiempos en segundos desde el inicio del audio.
+pub struct FingerprintStream {
+    rx: Receiver<Vec<f32>>,
+    /// `None` si la configuración no es válida; el flujo no produce nada.
+    state: Option<StreamState>,
+    pending: VecDeque<HashEntry>,
+    finished: bool,
+}
+
+impl Iterator for FingerprintStream {
+    type Item = HashEntry;
+
+    fn next(&mut self) -> Option<HashEntry> {
+        // Los tiempos y las frecuencias salen de índices de ventana y de bin, nunca son NaN.
+        const NO_NAN: &str = "el espectrograma propio no tiene tiempos ni frecuencias NaN";
+        let hasher = LandmarkHasher::new(NeighborhoodDetector);
+        let state = self.state.as_mut()?;
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(entry);
+            }
+            if self.finished {
+                return None;
+            }
+
+            match self.rx.recv() {
+                Ok(block) => state
+                    .push(&block, &hasher, &mut self.pending)
+                    .expect(NO_NAN),
+                Err(_) => {
+                    self.finished = true;
+                    state.finish(&hasher, &mut self.pending).expect(NO_NAN);
+                }
+            }
+        }
+    }
+}
+
+/// Huellas de una grabación que crece, como la de un indexado en directo, sin volver a
+/// calcularla desde el principio.
+///
+/// Cada llamada a `push` recibe solo las muestras añadidas desde la anterior y devuelve solo los
+/// hashes nuevos. La ventana de la FFT se desliza sin cortes entre llamadas y los hashes se
+/// retienen hasta que sus anclas ya no pueden cambiar, igual que en `stream_fingerprints`, así
+/// que trocear el audio no cambia el resultado; `finish` entrega los que quedan al terminar la
+/// grabación. Los tiempos son absolutos desde la primera muestra.
+///
+/// Como en `stream_fingerprints`, la señal no se normaliza por su máximo, y con
+/// `anchor_stride > 1` o `dedup` los hashes dependen de los tramos en que se calculan.
+pub struct IncrementalFingerprinter {
+    resampler: StreamResampler,
+    state: StreamState,
+    hasher: LandmarkHasher<NeighborhoodDetector>,
+}
+
+impl IncrementalFingerprinter {
+    /// Crea el cálculo incremental de una grabación vacía.
+    ///
+    /// # Argumentos
+    ///
+    /// * `config` - Parámetros del pipeline.
+    /// * `sample_rate` - Tasa de las muestras que se añadirán; se remuestrean a
+    ///   `canonical_rate`.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<IncrementalFingerprinter, SonoraError>` - El cálculo, o `InvalidConfig` si la
+    ///   ventana no admite el solapamiento.
+    pub fn new(config: &FingerprintConfig, sample_rate: u32) -> Result<Self, SonoraError> {
+        Ok(Self {
+            resampler: StreamResampler::new(sample_rate, config.canonical_rate),
+            state: StreamState::new(config)?,
+            hasher: LandmarkHasher::new(NeighborhoodDetector),
+        })
+    }
+
+    /// Añade muestras al final de la grabación.
+    ///
+    /// # Argumentos
+    ///
+    /// * `samples` - Muestras mono nuevas en `[-1.0, 1.0]`, de cualquier tamaño.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes que ya son definitivos y no se habían
+    ///   devuelto, en orden de tiempo del ancla; vacío hasta que se acumula un segundo de audio
+    ///   nuevo.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Vec<HashEntry>, SonoraError> {
+        let block = self.resampler.push(samples);
+        let mut hashes = Vec::new();
+        self.state.push(&block, &self.hasher, &mut hashes)?;
+        Ok(hashes)
+    }
+
+    /// Termina la grabación.
+    ///
+    /// # Retorna
+    ///
+    /// * `Result<Vec<HashEntry>, SonoraError>` - Hashes que faltaban por devolver, en orden de
+    ///   tiempo del ancla.
+    pub fn finish(mut self) -> Result<Vec<HashEntry>, SonoraError> {
+        let mut hashes = Vec::new();
+        self.state.finish(&self.hasher, &mut hashes)?;
+        Ok(hashes)
+    }
+}
+
+/// Zona pendiente del espectrograma de un cálculo incremental, con las muestras aún necesarias
+/// para emitir los hashes que faltan.
+struct StreamState {
+    config: FingerprintConfig,
+    hop_size: usize,
+    /// Ventanas que deben existir después de un ancla para emitirlo.
+    lookahead: usize,
+    /// Ventanas nuevas que se esperan antes de recalcular.
+    step_frames: usize,
+    /// Muestras aún necesarias, a partir de la ventana absoluta `buffer_start`.
+    buffer: Vec<f32>,
+    buffer_start: usize,
+    /// Primera ventana absoluta cuyos anclas no se han emitido.
+    emitted_until: usize,
+}
+
+impl StreamState {
+    fn new(config: &FingerprintConfig) -> Result<Self, SonoraError> {
+        config.check_window()?;
+        let hop_size = config.window_size - config.overlap;
+        let frame_rate = config.canonical_rate as f32 / hop_size as f32;
+        // Un ancla es definitiva cuando existen sus destinos y el vecindario de estos.
+        let max_delta_t = config
+            .target_zone
+            .map_or(config.max_delta_t, |zone| zone.max_dt);
+        let lookahead =
+            (max_delta_t * frame_rate).ceil() as usize + 2 * config.neighborhood_size + 1;
+
+        Ok(Self {
+            config: config.clone(),
+            hop_size,
+            lookahead,
+            step_frames: (STREAM_STEP_SECONDS * frame_rate).ceil() as usize,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            emitted_until: 0,
+        })
+    }
+
+    /// Añade un bloque de muestras y entrega a `out` los hashes que ya son definitivos.
+    fn push(
+        &mut self,
+        block: &[f32],
+        hasher: &dyn Hasher,
+        out: &mut impl Extend<HashEntry>,
+    ) -> Result<(), SonoraError> {
+        self.buffer.extend_from_slice(block);
+        let ready = self.available_frames().saturating_sub(self.lookahead);
+        if ready >= self.emitted_until + self.step_frames {
+            self.emit_until(ready, hasher, out)?;
+        }
+        Ok(())
+    }
+
+    /// Entrega a `out` todos los hashes que faltan; no llegarán más muestras.
+    fn finish(
+        &mut self,
+        hasher: &dyn Hasher,
+        out: &mut impl Extend<HashEntry>,
+    ) -> Result<(), SonoraError> {
+        let end = self.available_frames();
+        self.emit_until(end, hasher, out)
+    }
+
+    /// Número de ventanas completas disponibles, en índice absoluto.
+    fn available_frames(&self) -> usize {
+        let window_size = self.config.window_size;
+        if self.buffer.len() < window_size {
+            return self.buffer_start;
+        }
+        self.buffer_start + (self.buffer.len() - window_size) / self.hop_size + 1
+    }
+
+    /// Calcula los hashes de la zona pendiente con anclas anteriores a la ventana `limit`.
+    fn emit_until(
+        &mut self,
+        limit: usize,
+        hasher: &dyn Hasher,
+        out: &mut impl Extend<HashEntry>,
+    ) -> Result<(), SonoraError> {
+        if limit <= self.emitted_until {
+            return Ok(());
+        }
+        let _span = tracing::trace_span!(
+            "emit",
+            from = self.emitted_until,
+            until = limit,
+            buffered = self.buffer.len()
+        )
+        .entered();
+
+        let spectrogram = calculate_spectrogram(
+            &self.buffer,
+            self.config.canonical_rate as usize,
+            &self.config,
+        );
+        let frame_rate = spectrogram.frame_rate();
+        let offset = self.buffer_start as f32 / frame_rate;
+        let (buffer_start, emitted_until) = (self.buffer_start, self.emitted_until);
+        out.extend(
+            hasher
+                .hash(&spectrogram, &self.config)?
+                .into_iter()
+                .filter_map(|mut entry| {
+                    let frame = buffer_start + (entry.time * frame_rate).round() as usize;
+                    (frame >= emitted_until && frame < limit).then(|| {
+                        entry.time += offset;
+                        entry
+                    })
+                }),
+        );
+        self.emitted_until = limit;
+
+        // Conserva el vecindario de picos anterior a la primera ventana pendiente, y al menos
+        // una ventana para que el flujo espectral de esta tenga con qué compararse.
+        let keep_from = limit
+            .saturating_sub(self.config.neighborhood_size.max(1))
+            .max(self.buffer_start);
+        let drop = ((keep_from - self.buffer_start) * self.hop_size).min(self.buffer.len());
+        self.buffer.drain(..drop);
+        self.buffer_start = keep_from;
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(feature = "std")]
+use std::sync::Arc;
+
+use num_complex::Complex;
+#[cfg(feature = "std")]
+use rustfft::{Fft, FftNum, FftPlanner};
+
+/// Transformada directa usada para calcular el espectrograma.
+///
+/// Permite cambiar la implementación de la FFT (otra biblioteca, una GPU) sin tocar el resto
+/// del pipeline. Una implementación recibe siempre buffers del tamaño para el que se creó, en
+/// la precisión `T` (por defecto `f32`).
+pub trait Transform<T = f32>: Send + Sync {
+    /// Calcula en sitio la transformada directa de `buffer`.
+    fn forward(&self, buffer: &mut [Complex<T>]);
+}
+
+/// Implementación por defecto de [`Transform`] basada en `rustfft`; necesita la feature `std`.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct RustFftTransform<T = f32> {
+    fft: Arc<dyn Fft<T>>,
+}
+
+#[cfg(feature = "std")]
+impl RustFftTransform {
+    /// Planifica una FFT directa de `size` puntos en `f32`.
+    pub fn new(size: usize) -> Self {
+        Self::with_precision(size)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: FftNum> RustFftTransform<T> {
+    /// Planifica una FFT directa de `size` puntos en la precisión `T`, por ejemplo
+    /// `RustFftTransform::<f64>::with_precision(1024)`.
+    pub fn with_precision(size: usize) -> Self {
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(size),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: FftNum> Transform<T> for RustFftTransform<T> {
+    fn forward(&self, buffer: &mut [Complex<T>]) {
+        self.fft.process(buffer);
+    }
+}
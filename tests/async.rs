@@ -0,0 +1,59 @@
+//! API asíncrona sobre tokio frente a la síncrona.
+#![cfg(feature = "tokio")]
+
+use std::f32::consts::PI;
+
+use sonora::{
+    stream_source, stream_source_async, FingerprintConfig, Fingerprinter, MemorySource,
+    SonoraError, CANONICAL_SAMPLE_RATE,
+};
+
+/// Cinco segundos de una escala de senos a la tasa canónica.
+fn scale() -> MemorySource {
+    let rate = CANONICAL_SAMPLE_RATE as usize;
+    let samples = (0..5 * rate)
+        .map(|n| {
+            let t = n as f32 / rate as f32;
+            let frequency = 330.0 * 2f32.powf((n / (rate / 4) % 12) as f32 / 12.0);
+            0.5 * (2.0 * PI * frequency * t).sin()
+        })
+        .collect();
+    MemorySource::new(samples, 1, CANONICAL_SAMPLE_RATE)
+}
+
+#[tokio::test]
+async fn async_stream_yields_the_same_hashes() {
+    let config = FingerprintConfig::default();
+    let expected: Vec<_> = stream_source(scale(), &config)
+        .map(|entry| (entry.hash, entry.time))
+        .collect();
+
+    let mut stream = stream_source_async(scale(), &config).await;
+    let mut streamed = Vec::new();
+    while let Some(entry) = stream.next().await {
+        streamed.push((entry.hash, entry.time));
+    }
+
+    assert!(!expected.is_empty());
+    assert_eq!(streamed, expected);
+}
+
+#[tokio::test]
+async fn fingerprinter_runs_off_the_runtime() {
+    let fingerprinter = Fingerprinter::builder().build().unwrap();
+
+    let expected = fingerprinter.fingerprint_source(scale()).unwrap();
+    let hashes = fingerprinter
+        .fingerprint_source_async(scale())
+        .await
+        .unwrap();
+    assert_eq!(
+        hashes.iter().map(|e| e.hash).collect::<Vec<_>>(),
+        expected.iter().map(|e| e.hash).collect::<Vec<_>>()
+    );
+
+    let missing = fingerprinter
+        .fingerprint_async("/no/existe/sonora.wav")
+        .await;
+    assert!(matches!(missing, Err(SonoraError::Io(_))));
+}
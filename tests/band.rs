@@ -0,0 +1,297 @@
+//! Reparto de los bins entre bandas en los motores de hash por bandas.
+
+use sonora::{
+    band_fingerprints, band_hash, band_index, band_lookup, chunk_count, pack_band_fields,
+    update_band_max, Bands, Engine, FingerprintConfig, Fingerprinter, MemorySource, SonoraError,
+};
+
+/// Bin ganador de cada banda para un espectro de magnitudes indexado por bin.
+fn band_maxima(magnitudes: &[f32], config: &FingerprintConfig) -> Vec<usize> {
+    let lookup = band_lookup(config);
+    let mut points = vec![0; config.max_freq + 1];
+    let mut scores = vec![0.0; config.max_freq + 1];
+    for freq in config.min_freq..=config.max_freq {
+        update_band_max(
+            &mut points,
+            &mut scores,
+            lookup[freq],
+            freq,
+            magnitudes[freq],
+        );
+    }
+    points
+}
+
+#[test]
+fn band_edges_are_inclusive() {
+    let config = FingerprintConfig::default();
+
+    assert_eq!(band_index(config.min_freq, &config), 40);
+    assert_eq!(band_index(40, &config), 40);
+    assert_eq!(band_index(41, &config), 80);
+    assert_eq!(band_index(180, &config), 180);
+    assert_eq!(band_index(181, &config), 300);
+    assert_eq!(band_index(config.max_freq, &config), 300);
+}
+
+#[test]
+fn every_band_receives_candidates() {
+    let config = FingerprintConfig::default();
+    // Un pico en cada borde de la primera y la última banda y en el interior de las demás.
+    let winners = [config.min_freq, 60, 100, 150, config.max_freq];
+    let mut magnitudes = vec![0.1; config.max_freq + 1];
+    for &bin in &winners {
+        magnitudes[bin] = 1.0;
+    }
+
+    let points = band_maxima(&magnitudes, &config);
+
+    for (&band, &bin) in config.bands.iter().zip(&winners) {
+        assert_eq!(points[band], bin, "banda {band}");
+    }
+}
+
+#[test]
+fn top_band_changes_the_hash() {
+    let config = FingerprintConfig::default();
+    let mut magnitudes = vec![0.1; config.max_freq + 1];
+    magnitudes[200] = 1.0;
+    let low = band_hash(&band_maxima(&magnitudes, &config), &config).unwrap();
+    magnitudes[config.max_freq] = 2.0;
+    let high = band_hash(&band_maxima(&magnitudes, &config), &config).unwrap();
+
+    assert_ne!(low, high);
+}
+
+#[test]
+fn uniform_bands_drive_lookup_and_packing() {
+    let bands = Bands::uniform(40, 300, 10).unwrap();
+    assert_eq!(bands.len(), 10);
+    assert_eq!(bands.last(), Some(&300));
+    assert!(Bands::new(vec![40, 40, 300]).is_err());
+    assert!(Bands::uniform(10, 12, 4).is_err());
+
+    let config = Fingerprinter::builder()
+        .bands(bands.clone())
+        .fuz_factor(8)
+        .build()
+        .unwrap()
+        .config()
+        .clone();
+    let lookup = band_lookup(&config);
+    for pair in bands.windows(2) {
+        assert_eq!(lookup[pair[0]], pair[0]);
+        assert_eq!(lookup[pair[0] + 1], pair[1]);
+    }
+
+    // Un pico en el primer bin de cada banda: cada campo del hash recoge el suyo.
+    let starts: Vec<usize> = std::iter::once(config.min_freq)
+        .chain(bands.iter().map(|&edge| edge + 1))
+        .take(bands.len())
+        .collect();
+    let mut magnitudes = vec![0.1; config.max_freq + 1];
+    for &bin in &starts {
+        magnitudes[bin] = 1.0;
+    }
+    let points = band_maxima(&magnitudes, &config);
+    let fields: Vec<usize> = starts.iter().map(|&bin| bin / 8).collect();
+    assert_eq!(
+        band_hash(&points, &config).unwrap(),
+        pack_band_fields(&fields, 300 / 8).unwrap()
+    );
+
+    // Diez campos de 9 bits no caben en el hash sin tolerancia.
+    let too_wide = Fingerprinter::builder().bands(bands).fuz_factor(1).build();
+    assert!(matches!(too_wide, Err(SonoraError::InvalidConfig(_))));
+}
+
+#[test]
+fn incoherent_bands_are_errors_instead_of_panics() {
+    let configs = [
+        // La última banda queda por encima de `max_freq`.
+        FingerprintConfig {
+            max_freq: 200,
+            ..FingerprintConfig::default()
+        },
+        FingerprintConfig {
+            fuz_factor: 0,
+            ..FingerprintConfig::default()
+        },
+        FingerprintConfig {
+            bands: Bands::uniform(1, 300, 10).unwrap(),
+            fuz_factor: 1,
+            ..FingerprintConfig::default()
+        },
+    ];
+    for config in configs {
+        let built = Fingerprinter::builder().config(config.clone()).build();
+        assert!(matches!(built, Err(SonoraError::InvalidConfig(_))));
+
+        let source = MemorySource::new(vec![0.5; 44100], 1, 44100);
+        let mut hashes = Vec::new();
+        for engine in Engine::ALL {
+            let result = band_fingerprints(source.clone(), &config, engine, &mut hashes);
+            assert!(
+                matches!(result, Err(SonoraError::InvalidConfig(_))),
+                "{engine}"
+            );
+        }
+    }
+
+    assert!(matches!(
+        pack_band_fields(&[1; 10], 511),
+        Err(SonoraError::InvalidConfig(_))
+    ));
+    let short = FingerprintConfig {
+        max_freq: 200,
+        ..FingerprintConfig::default()
+    };
+    assert!(band_hash(&[0; 201], &short).is_err());
+}
+
+#[test]
+fn chunk_dc_removal_can_be_turned_off() {
+    let rate = 44100;
+    // Un tono centrado en un bin de cada banda, sin pasar por el bin 0, y sin bloque final
+    // relleno: 4096 + 9 * 2048 muestras.
+    let tones = |offset: f32| -> Vec<f32> {
+        (0..4096 + 9 * 2048)
+            .map(|n| {
+                let phase = 2.0 * std::f32::consts::PI * n as f32 / 4096.0;
+                offset
+                    + [20.0, 60.0, 100.0, 150.0, 250.0]
+                        .iter()
+                        .map(|&bin| 0.08 * (phase * bin).sin())
+                        .sum::<f32>()
+            })
+            .collect()
+    };
+    // Con `min_freq` en 0 el bin de la DC compite en la primera banda.
+    let hashes = |samples: Vec<f32>, chunk_remove_dc: bool| {
+        let config = FingerprintConfig {
+            min_freq: 0,
+            chunk_hop: 2048,
+            chunk_remove_dc,
+            ..FingerprintConfig::default()
+        };
+        let mut hashes = Vec::new();
+        let source = MemorySource::new(samples, 1, rate);
+        band_fingerprints(source, &config, Engine::Sequential, &mut hashes).unwrap();
+        hashes.iter().map(|entry| entry.hash).collect::<Vec<_>>()
+    };
+
+    assert!(FingerprintConfig::default().chunk_remove_dc);
+    assert_eq!(hashes(tones(0.5), true), hashes(tones(0.0), true));
+    assert_ne!(hashes(tones(0.5), false), hashes(tones(0.0), false));
+    assert_eq!(hashes(tones(0.0), false), hashes(tones(0.0), true));
+}
+
+#[test]
+fn defaults_match_the_original_band_constants() {
+    // CHUNK_SIZE, FUZ_FACTOR, MIN_FREQ, MAX_FREQ y las bandas de los binarios originales, con
+    // bloques contiguos.
+    let config = FingerprintConfig::default();
+    assert_eq!((config.chunk_size, config.chunk_hop), (4096, 4096));
+    assert_eq!(config.fuz_factor, 2);
+    assert_eq!((config.min_freq, config.max_freq), (40, 300));
+    assert_eq!(*config.bands, [40, 80, 120, 180, 300]);
+    assert_eq!((config.fan_value, config.neighborhood_size), (15, 20));
+    assert_eq!(config.max_delta_t, 5.0);
+
+    let lookup = band_lookup(&config);
+    assert_eq!((lookup[40], lookup[41], lookup[300]), (40, 80, 300));
+}
+
+#[test]
+fn large_band_indices_do_not_collide_across_fields() {
+    // Con el empaquetado decimal antiguo, un bin 100 en la primera banda valía lo mismo que un
+    // bin 1 en la segunda.
+    let max = 2047;
+    assert_ne!(
+        pack_band_fields(&[100, 0, 0, 0], max).unwrap(),
+        pack_band_fields(&[0, 1, 0, 0], max).unwrap()
+    );
+
+    // Cada campo ocupa sus 11 bits y se recupera intacto, sea cual sea el de al lado.
+    let values = [0, 1, 99, 100, 999, 1000, 1024, 2047];
+    let mut seen = std::collections::HashSet::new();
+    for &a in &values {
+        for &b in &values {
+            for &c in &values {
+                let hash = pack_band_fields(&[a, b, c, max], max).unwrap();
+                let unpacked: Vec<usize> = (0..4).map(|i| (hash >> (11 * i)) & max).collect();
+                assert_eq!(unpacked, [a, b, c, max]);
+                assert!(seen.insert(hash));
+            }
+        }
+    }
+}
+
+#[test]
+fn equal_magnitudes_always_pick_the_lowest_bin() {
+    let config = FingerprintConfig::default();
+    let flat = vec![1.0; config.max_freq + 1];
+    let points = band_maxima(&flat, &config);
+    let picked: Vec<usize> = config.bands.iter().map(|&band| points[band]).collect();
+    assert_eq!(picked, [40, 41, 81, 121, 181]);
+
+    // El resultado no depende del orden en que se visitan los bins.
+    let lookup = band_lookup(&config);
+    let mut reversed_points = vec![0; config.max_freq + 1];
+    let mut scores = vec![0.0; config.max_freq + 1];
+    for freq in (config.min_freq..=config.max_freq).rev() {
+        update_band_max(&mut reversed_points, &mut scores, lookup[freq], freq, 1.0);
+    }
+    assert_eq!(reversed_points, points);
+}
+
+#[test]
+fn overlapping_hops_agree_across_engines() {
+    let rate = 44100;
+    // Ruido pseudoaleatorio que no acaba en un bloque entero.
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let samples: Vec<f32> = (0..4096 * 5 + 1000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 2001) as f32 / 1000.0 - 1.0
+        })
+        .collect();
+    let run = |chunk_hop: usize, engine: Engine| {
+        let config = FingerprintConfig {
+            chunk_hop,
+            ..FingerprintConfig::default()
+        };
+        let mut hashes = Vec::new();
+        let source = MemorySource::new(samples.clone(), 1, rate);
+        band_fingerprints(source, &config, engine, &mut hashes).unwrap();
+        assert_eq!(
+            hashes.len(),
+            chunk_count(samples.len(), &config),
+            "{engine}"
+        );
+        hashes
+    };
+
+    let overlapping = run(1024, Engine::Sequential);
+    for (i, entry) in overlapping.iter().enumerate() {
+        assert!((entry.time - (i * 1024) as f32 / rate as f32).abs() < 1e-6);
+    }
+    for engine in Engine::ALL {
+        let hashes = run(1024, engine);
+        assert!(
+            hashes
+                .iter()
+                .zip(&overlapping)
+                .all(|(a, b)| (a.hash, a.time) == (b.hash, b.time)),
+            "{engine}"
+        );
+    }
+
+    // Cada cuarto bloque solapado es un bloque de la división sin solapamiento.
+    let contiguous = run(4096, Engine::Sequential);
+    for (a, b) in overlapping.iter().step_by(4).zip(&contiguous) {
+        assert_eq!((a.hash, a.time), (b.hash, b.time));
+    }
+}
@@ -0,0 +1,138 @@
+//! Indexado de directorios con punto de control.
+
+mod common;
+
+use std::f32::consts::PI;
+use std::fs;
+use std::path::Path;
+
+use sonora::{
+    evaluate, fingerprint_file, index_dir, Checkpoint, FingerprintConfig, FingerprintDb,
+    SonoraError,
+};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// WAV mono de 16 bits de `seconds` segundos con una escala de notas con cuatro armónicos
+/// que empieza en `base` Hz.
+fn wav_bytes(base: f32, seconds: usize) -> Vec<u8> {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let note = base * 2f32.powf((n / (SAMPLE_RATE as usize / 4) % 12) as f32 / 12.0);
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let sample: f32 = (1..=4)
+                .map(|h| (2.0 * PI * note * h as f32 * t).sin() / (2 * h) as f32)
+                .sum();
+            (sample * 20000.0) as i16
+        })
+        .collect();
+    common::encode_wav(1, SAMPLE_RATE, &samples)
+}
+
+fn write_checkpoint(path: &Path, next_song_id: u32, done: &[(u32, &Path)]) {
+    let mut text = format!("SNCP 1\nnext {}\n", next_song_id);
+    for (id, file) in done {
+        text.push_str(&format!("{}\t{}\n", id, file.display()));
+    }
+    fs::write(path, text).unwrap();
+}
+
+#[test]
+fn resume_skips_files_in_the_checkpoint() {
+    let dir = std::env::temp_dir().join(format!("sonora_batch_{}", std::process::id()));
+    let audio = dir.join("audio");
+    let db_path = dir.join("songs.db");
+    let checkpoint_path = Checkpoint::path_for(&db_path);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&audio).unwrap();
+    let config = FingerprintConfig::default();
+
+    // Un lote completo con dos archivos no deja punto de control.
+    fs::write(audio.join("a.wav"), wav_bytes(220.0, 2)).unwrap();
+    fs::write(audio.join("b.wav"), wav_bytes(330.0, 2)).unwrap();
+    let first = index_dir(&audio, &db_path, &config, false).unwrap();
+    assert_eq!(first.succeeded.len(), 2);
+    assert!(!checkpoint_path.exists());
+
+    // Se simula que el lote se cortó tras esos dos archivos y después apareció un tercero.
+    fs::write(audio.join("c.wav"), wav_bytes(440.0, 2)).unwrap();
+    let done: Vec<_> = first
+        .succeeded
+        .iter()
+        .map(|(id, path)| (*id, path.as_path()))
+        .collect();
+    write_checkpoint(&checkpoint_path, 2, &done);
+    assert_eq!(Checkpoint::load(&checkpoint_path).unwrap().len(), 2);
+
+    let resumed = index_dir(&audio, &db_path, &config, true).unwrap();
+    assert_eq!(resumed.resumed, first.succeeded);
+    assert_eq!(resumed.succeeded, vec![(2, audio.join("c.wav"))]);
+    assert!(!checkpoint_path.exists());
+    assert_eq!(FingerprintDb::load(&db_path).unwrap().next_song_id(), 3);
+
+    // Un punto de control que no corresponde a la base se rechaza sin tocarla.
+    write_checkpoint(&checkpoint_path, 2, &done);
+    let stale = index_dir(&audio, &db_path, &config, true);
+    assert!(matches!(stale, Err(SonoraError::InvalidCheckpoint)));
+    assert_eq!(FingerprintDb::load(&db_path).unwrap().next_song_id(), 3);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn evaluation_sweeps_thresholds_over_a_labelled_set() {
+    let dir = std::env::temp_dir().join(format!("sonora_eval_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let config = FingerprintConfig::default();
+    let file = |name: &str, base: f32| {
+        let path = dir.join(name);
+        fs::write(&path, wav_bytes(base, 10)).unwrap();
+        path
+    };
+
+    let mut db = FingerprintDb::new();
+    for (song_id, base) in [(0, 220.0), (1, 330.0)] {
+        let path = file(&format!("song_{song_id}.wav"), base);
+        let hashes = fingerprint_file(path.to_str().unwrap(), &config).unwrap();
+        db.insert(song_id, &hashes);
+    }
+    // Dos consultas de canciones indexadas y una escala que no está en la base: un semitono
+    // por encima de la primera, con la que comparte unos pocos landmarks.
+    let queries = [
+        (file("query_0.wav", 220.0), Some(0)),
+        (file("query_1.wav", 330.0), Some(1)),
+        (file("unknown.wav", 233.08), None),
+    ];
+    let report = evaluate(&queries, &db, &config).unwrap();
+    let _ = fs::remove_dir_all(&dir);
+
+    assert_eq!(report.outcomes.len(), 3);
+    for outcome in &report.outcomes[..2] {
+        assert_eq!(
+            outcome.top_match.map(|(song_id, _)| song_id),
+            outcome.expected
+        );
+    }
+    let points = &report.points;
+    assert!(points
+        .windows(2)
+        .all(|pair| pair[0].threshold < pair[1].threshold));
+    assert!(points
+        .windows(2)
+        .all(|pair| pair[0].recall >= pair[1].recall));
+    assert_eq!(points[0].recall, 1.0);
+    assert!(points[0].precision < 1.0);
+    // Con el umbral justo por debajo de la peor consulta conocida no queda ningún falso
+    // positivo ni se pierde ningún acierto.
+    let weakest = report.outcomes[..2]
+        .iter()
+        .filter_map(|outcome| outcome.top_match.map(|(_, score)| score))
+        .min()
+        .unwrap();
+    let tuned = points
+        .iter()
+        .find(|point| point.threshold == weakest)
+        .unwrap();
+    assert_eq!((tuned.precision, tuned.recall), (1.0, 1.0));
+}
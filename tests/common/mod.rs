@@ -0,0 +1,52 @@
+//! Utilidades compartidas por las pruebas y los benchmarks.
+
+// Cada prueba compila su propia copia del módulo y no todas usan todas las funciones.
+#![allow(dead_code)]
+
+use std::io;
+use std::path::Path;
+
+/// Codifica muestras de 16 bits como un WAV PCM.
+///
+/// # Argumentos
+///
+/// * `channels` - Número de canales.
+/// * `rate` - Tasa de muestreo en Hz.
+/// * `samples` - Muestras intercaladas por canal.
+///
+/// # Retorna
+///
+/// * `Vec<u8>` - El archivo completo, cabecera incluida.
+pub fn encode_wav(channels: u16, rate: u32, samples: &[i16]) -> Vec<u8> {
+    let block_align = channels * 2;
+    let data_len = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&rate.to_le_bytes());
+    bytes.extend_from_slice(&(rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+/// Escribe muestras de 16 bits en `path` como un WAV PCM.
+///
+/// # Argumentos
+///
+/// * `path` - Archivo de destino.
+/// * `channels` - Número de canales.
+/// * `rate` - Tasa de muestreo en Hz.
+/// * `samples` - Muestras intercaladas por canal.
+pub fn write_wav(path: &Path, channels: u16, rate: u32, samples: &[i16]) -> io::Result<()> {
+    std::fs::write(path, encode_wav(channels, rate, samples))
+}
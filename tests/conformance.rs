@@ -0,0 +1,173 @@
+//! Conformidad entre los motores de hash por bandas: todos los `Engine` deben escribir
+//! exactamente la misma salida para la misma entrada, y la misma señal debe dar la misma huella
+//! con cualquier número de canales.
+
+mod common;
+
+use std::f32::consts::PI;
+use std::path::Path;
+
+use sonora::{
+    band_fingerprints, fingerprint_file, Engine, FileSource, FingerprintConfig, FingerprintFormat,
+    FingerprintWriter, HashEntry, MemorySource,
+};
+
+const SAMPLE_RATE: u32 = 44100;
+
+/// Cabecera y salida esperada de los primeros bloques de `stereo_wav`; cambia solo si cambia a
+/// propósito la matemática de los motores.
+const GOLDEN_HEAD: &[&str] = &[
+    r#"{"version":4}"#,
+    r#"{"chunk":0,"hash":"0000005a3c281e14","time":0}"#,
+    r#"{"chunk":1,"hash":"0000005a3c281e14","time":0.09287982}"#,
+    r#"{"chunk":2,"hash":"0000005c3e282014","time":0.18575963}"#,
+    r#"{"chunk":3,"hash":"0000005a3c282014","time":0.27863947}"#,
+    r#"{"chunk":4,"hash":"0000005b3c282214","time":0.37151927}"#,
+    r#"{"chunk":5,"hash":"0000005a3c282214","time":0.4643991}"#,
+    r#"{"chunk":6,"hash":"0000005c3d282414","time":0.55727893}"#,
+    r#"{"chunk":7,"hash":"000000793c282414","time":0.6501587}"#,
+];
+
+/// Salida esperada del último bloque, relleno con ceros.
+const GOLDEN_LAST: &str = r#"{"chunk":32,"hash":"0000005b3d292414","time":2.9721541}"#;
+
+/// WAV estéreo de 16 bits con un canal distinto en cada lado y una longitud que no es
+/// múltiplo del bloque, para cubrir la mezcla a mono y el bloque final.
+fn stereo_wav(path: &Path) {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * 3 + 1234)
+        .flat_map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let note = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 5) % 12) as f32 / 12.0);
+            let left = 0.4 * (2.0 * PI * note * t).sin() + 0.1;
+            let right = 0.3 * (2.0 * PI * 3.0 * note * t).sin();
+            [left, right].map(|sample| (sample * 20000.0) as i16)
+        })
+        .collect();
+    common::write_wav(path, 2, SAMPLE_RATE, &samples).unwrap();
+}
+
+/// Ejecuta un motor sobre `input` y devuelve su salida en NDJSON, con el índice de cada bloque
+/// como la escribe la línea de comandos.
+fn run_engine(engine: Engine, input: &Path) -> String {
+    let mut writer =
+        FingerprintWriter::new(Vec::new(), FingerprintFormat::NdJson).with_chunk_index();
+    band_fingerprints(
+        FileSource::open(input.to_str().unwrap()).unwrap(),
+        &FingerprintConfig::default(),
+        engine,
+        &mut writer,
+    )
+    .unwrap();
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+#[test]
+fn band_engines_write_identical_output() {
+    let input = std::env::temp_dir().join(format!("sonora_conformance_{}.wav", std::process::id()));
+    stereo_wav(&input);
+
+    let outputs: Vec<(Engine, String)> = Engine::ALL
+        .into_iter()
+        .map(|engine| (engine, run_engine(engine, &input)))
+        .collect();
+    let _ = std::fs::remove_file(&input);
+
+    let reference = &outputs[0].1;
+    assert!(!reference.is_empty());
+    for (engine, output) in &outputs {
+        assert_eq!(output, reference, "el motor {engine} difiere");
+    }
+    let lines: Vec<&str> = reference.lines().collect();
+    assert_eq!(&lines[..GOLDEN_HEAD.len()], GOLDEN_HEAD);
+    assert_eq!(lines.last(), Some(&GOLDEN_LAST));
+    // Un hash por bloque, numerados tras la cabecera.
+    for (chunk, line) in lines[1..].iter().enumerate() {
+        assert!(line.starts_with(&format!("{{\"chunk\":{chunk},")), "{line}");
+    }
+}
+
+#[test]
+fn stereo_is_mixed_frame_by_frame() {
+    // Con los dos canales iguales, la media trama a trama es la señal mono; concatenar los
+    // canales de cada paquete desordenaría el eje de tiempo.
+    let mono: Vec<f32> = (0..SAMPLE_RATE as usize * 2)
+        .map(|n| {
+            let note = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 5) % 12) as f32 / 12.0);
+            0.4 * (2.0 * PI * note * n as f32 / SAMPLE_RATE as f32).sin()
+        })
+        .collect();
+    let stereo: Vec<f32> = mono.iter().flat_map(|&sample| [sample, sample]).collect();
+
+    for engine in Engine::ALL {
+        let hashes = |samples: &[f32], channels| {
+            let mut hashes: Vec<HashEntry> = Vec::new();
+            band_fingerprints(
+                MemorySource::new(samples.to_vec(), channels, SAMPLE_RATE),
+                &FingerprintConfig::default(),
+                engine,
+                &mut hashes,
+            )
+            .unwrap();
+            hashes
+        };
+        let (from_mono, from_stereo) = (hashes(&mono, 1), hashes(&stereo, 2));
+        assert!(!from_mono.is_empty());
+        assert_eq!(from_mono.len(), from_stereo.len(), "motor {engine}");
+        for (a, b) in from_mono.iter().zip(&from_stereo) {
+            assert_eq!((a.hash, a.time), (b.hash, b.time), "motor {engine}");
+        }
+    }
+}
+
+/// WAV de 16 bits con `channels` canales que llevan todos la misma escala de notas con
+/// armónicos.
+fn multichannel_wav(path: &Path, channels: u16) {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * 2)
+        .flat_map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let note = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 5) * 5 % 12) as f32 / 12.0);
+            let sample: f32 = (1..=4)
+                .map(|h| (2.0 * PI * note * h as f32 * t).sin() / (4 * h) as f32)
+                .sum();
+            std::iter::repeat_n((sample * 20000.0) as i16, channels as usize)
+        })
+        .collect();
+    common::write_wav(path, channels, SAMPLE_RATE, &samples).unwrap();
+}
+
+#[test]
+fn any_channel_count_gives_the_mono_fingerprint() {
+    let path = |channels: u16| {
+        std::env::temp_dir().join(format!(
+            "sonora_channels_{}_{}.wav",
+            channels,
+            std::process::id()
+        ))
+    };
+    let mono = path(1);
+    multichannel_wav(&mono, 1);
+    let landmarks = |input: &Path| {
+        fingerprint_file(input.to_str().unwrap(), &FingerprintConfig::default()).unwrap()
+    };
+    let reference = landmarks(&mono);
+    assert!(!reference.is_empty());
+
+    for channels in [2, 6] {
+        let input = path(channels);
+        multichannel_wav(&input, channels);
+        for engine in Engine::ALL {
+            assert_eq!(
+                run_engine(engine, &input),
+                run_engine(engine, &mono),
+                "{channels} canales, motor {engine}"
+            );
+        }
+        let hashes = landmarks(&input);
+        let _ = std::fs::remove_file(&input);
+        assert_eq!(hashes.len(), reference.len(), "{channels} canales");
+        for (a, b) in hashes.iter().zip(&reference) {
+            assert_eq!((a.hash, a.time), (b.hash, b.time), "{channels} canales");
+        }
+    }
+    let _ = std::fs::remove_file(&mono);
+}
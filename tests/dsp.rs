@@ -0,0 +1,80 @@
+//! Diezmado con filtro antialiasing, también dentro de `resample`.
+
+use std::f32::consts::PI;
+
+use sonora::dsp::{decimate, Decimator};
+use sonora::{resample, SonoraError};
+
+const SAMPLE_RATE: f32 = 44100.0;
+
+fn tone(freq: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|n| (2.0 * PI * freq * n as f32 / SAMPLE_RATE).sin())
+        .collect()
+}
+
+/// Amplitud de pico lejos de los bordes.
+fn peak(samples: &[f32]) -> f32 {
+    let margin = samples.len() / 10;
+    samples[margin..samples.len() - margin]
+        .iter()
+        .fold(0.0, |max, s| max.max(s.abs()))
+}
+
+#[test]
+fn removes_what_would_alias() {
+    // A 44.1 kHz / 4 la nueva frecuencia de Nyquist es 5512 Hz.
+    let pass = decimate(&tone(1000.0, 44100), 4, 0.9, 127).unwrap();
+    let stop = decimate(&tone(8000.0, 44100), 4, 0.9, 127).unwrap();
+    assert_eq!(pass.len(), 11025);
+    assert!((peak(&pass) - 1.0).abs() < 0.01, "{}", peak(&pass));
+    assert!(peak(&stop) < 0.01, "{}", peak(&stop));
+
+    // Sin filtro, el tono de 8 kHz se pliega en 3025 Hz con toda su amplitud.
+    let dropped: Vec<f32> = tone(8000.0, 44100).into_iter().step_by(4).collect();
+    assert!(peak(&dropped) > 0.9);
+}
+
+#[test]
+fn blocks_match_the_whole_signal() {
+    let signal: Vec<f32> = tone(440.0, 10_000)
+        .iter()
+        .zip(tone(7000.0, 10_000))
+        .map(|(a, b)| a + b)
+        .collect();
+    let whole = decimate(&signal, 3, 0.8, 63).unwrap();
+
+    let mut decimator = Decimator::new(3, 0.8, 63).unwrap();
+    let mut blocks = Vec::new();
+    for block in signal.chunks(777) {
+        decimator.process(block, &mut blocks);
+    }
+    decimator.finish(&mut blocks);
+
+    assert_eq!(blocks, whole);
+    assert!(matches!(
+        Decimator::new(0, 0.8, 63),
+        Err(SonoraError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        Decimator::new(2, 1.5, 63),
+        Err(SonoraError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn resample_decimates_integer_ratios() {
+    let signal: Vec<f32> = tone(440.0, 44100)
+        .iter()
+        .zip(tone(9000.0, 44100))
+        .map(|(a, b)| a + b)
+        .collect();
+
+    // 16 cruces por cero a cada lado del núcleo de `resample`, con factor 4.
+    let decimated = decimate(&signal, 4, 1.0, 2 * 16 * 4 - 1).unwrap();
+    assert_eq!(resample(&signal, 44100, 11025), decimated);
+    assert!(peak(&decimated) < 1.01, "{}", peak(&decimated));
+
+    // Una relación no entera sigue interpolando.
+    assert_eq!(resample(&signal, 44100, 16000).len(), 16000);
+}
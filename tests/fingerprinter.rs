@@ -0,0 +1,949 @@
+//! Construcción de `Fingerprinter` y huellas desde archivos, lectores y orígenes de audio.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::f32::consts::PI;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use sonora::{
+    band_fingerprints, calculate_spectrogram, decode_reader, decode_to_samples,
+    decode_track_to_samples, fingerprint_channels, fingerprint_file, fingerprint_i16,
+    fingerprint_source, interleaved_to_mono, match_query, read_fingerprints, write_fingerprints,
+    AudioSource, BandHasher, CancelToken, ChannelPolicy, DbInserter, Engine, Excerpt, FileSource,
+    FingerprintConfig, FingerprintDb, FingerprintFormat, FingerprintWriter, Fingerprinter,
+    HashEntry, HashSink, Hasher, MemorySource, NormalizeOrder, PcmFormat, Progress, RawSource,
+    SonoraError, FINGERPRINT_VERSION,
+};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// WAV mono de 16 bits con una escala de senos, una nota cada cuarto de segundo.
+fn wav_bytes(seconds: usize) -> Vec<u8> {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let note = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 4) % 12) as f32 / 12.0);
+            let sample = 0.5 * (2.0 * PI * note * n as f32 / SAMPLE_RATE as f32).sin();
+            (sample * 20000.0) as i16
+        })
+        .collect();
+    common::encode_wav(1, SAMPLE_RATE, &samples)
+}
+
+#[test]
+fn reader_and_file_give_the_same_hashes() {
+    let bytes = wav_bytes(5);
+    let path = std::env::temp_dir().join("sonora_fingerprinter_test.wav");
+    std::fs::write(&path, &bytes).unwrap();
+    let fingerprinter = Fingerprinter::builder().fan_value(5).build().unwrap();
+
+    let from_file = fingerprinter.fingerprint(&path).unwrap();
+    let from_reader = fingerprinter
+        .fingerprint_reader(Cursor::new(bytes.clone()))
+        .unwrap();
+    let hinted = FileSource::from_reader_with_extension(Cursor::new(bytes), "wav").unwrap();
+    let from_hinted = fingerprinter.fingerprint_source(hinted).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(!from_file.is_empty());
+    for hashes in [&from_reader, &from_hinted] {
+        assert_eq!(from_file.len(), hashes.len());
+        for (a, b) in from_file.iter().zip(hashes) {
+            assert_eq!((a.hash, a.time), (b.hash, b.time));
+        }
+    }
+}
+
+#[test]
+fn raw_pcm_matches_the_wav_container() {
+    let bytes = wav_bytes(5);
+    let config = FingerprintConfig::default();
+    let from_wav = fingerprint_source(
+        FileSource::from_reader(Cursor::new(bytes.clone())).unwrap(),
+        &config,
+    )
+    .unwrap();
+    let raw = RawSource::new(
+        Cursor::new(bytes[44..].to_vec()),
+        PcmFormat::S16Le,
+        1,
+        SAMPLE_RATE,
+    )
+    .unwrap();
+    let from_raw = fingerprint_source(raw, &config).unwrap();
+
+    assert!(!from_wav.is_empty());
+    assert_eq!(from_wav.len(), from_raw.len());
+    for (a, b) in from_wav.iter().zip(&from_raw) {
+        assert_eq!((a.hash, a.time), (b.hash, b.time));
+    }
+    assert!(matches!(
+        RawSource::new(Cursor::new(Vec::new()), PcmFormat::S16Le, 0, SAMPLE_RATE),
+        Err(SonoraError::NoChannels)
+    ));
+    assert_eq!("f32le".parse::<PcmFormat>().unwrap(), PcmFormat::F32Le);
+}
+
+#[test]
+fn builder_rejects_inconsistent_parameters() {
+    let overlap = Fingerprinter::builder()
+        .window_size(512)
+        .overlap(512)
+        .build();
+    assert!(matches!(overlap, Err(SonoraError::InvalidConfig(_))));
+
+    let bands = Fingerprinter::builder().bands(vec![40, 30, 300]).build();
+    assert!(matches!(bands, Err(SonoraError::InvalidConfig(_))));
+
+    let fuz = Fingerprinter::builder().fuz_factor(0).build();
+    assert!(matches!(fuz, Err(SonoraError::InvalidConfig(_))));
+
+    let config = Fingerprinter::builder()
+        .chunk_size(2048)
+        .bands(vec![50, 80, 120, 200])
+        .build()
+        .unwrap()
+        .config()
+        .clone();
+    assert_eq!((config.chunk_hop, config.max_freq), (2048, 200));
+}
+
+#[test]
+fn memory_source_matches_the_decoded_file() {
+    let path = std::env::temp_dir().join("sonora_memory_source_test.wav");
+    std::fs::write(&path, wav_bytes(5)).unwrap();
+    let path = path.to_str().unwrap().to_string();
+    let config = FingerprintConfig {
+        fan_value: 5,
+        ..FingerprintConfig::default()
+    };
+
+    let from_file = fingerprint_file(&path, &config).unwrap();
+    let decoded = decode_to_samples(&path).unwrap();
+    let from_memory = fingerprint_source(MemorySource::from(decoded), &config).unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(!from_file.is_empty());
+    assert_eq!(from_file.len(), from_memory.len());
+    for (a, b) in from_file.iter().zip(&from_memory) {
+        assert_eq!((a.hash, a.time), (b.hash, b.time));
+    }
+}
+
+#[test]
+fn i16_samples_match_the_f32_path() {
+    // Estéreo con la escala en un canal y una quinta por encima en el otro.
+    let left = harmonic_scale(220.0, SAMPLE_RATE, 5);
+    let right = harmonic_scale(330.0, SAMPLE_RATE, 5);
+    let ints: Vec<i16> = left
+        .iter()
+        .zip(&right)
+        .flat_map(|(&l, &r)| [l, r])
+        .map(|s| (s * 20000.0) as i16)
+        .collect();
+    let floats: Vec<f32> = ints.iter().map(|&s| s as f32 / 32768.0).collect();
+    let config = FingerprintConfig::default();
+
+    let from_ints = fingerprint_i16(&ints, 2, SAMPLE_RATE, &config).unwrap();
+    let from_floats =
+        fingerprint_source(MemorySource::new(floats, 2, SAMPLE_RATE), &config).unwrap();
+    assert!(!from_ints.is_empty());
+    assert_eq!(from_ints.len(), from_floats.len());
+    for (a, b) in from_ints.iter().zip(&from_floats) {
+        assert_eq!((a.hash, a.time), (b.hash, b.time));
+    }
+    assert!(matches!(
+        fingerprint_i16(&ints, 0, SAMPLE_RATE, &config),
+        Err(SonoraError::NoChannels)
+    ));
+}
+
+#[test]
+fn excerpt_seeks_or_skips_to_the_same_frames() {
+    let bytes = wav_bytes(5);
+    let path = std::env::temp_dir().join("sonora_excerpt_test.wav");
+    std::fs::write(&path, &bytes).unwrap();
+    let decoded = decode_reader(Cursor::new(bytes.clone())).unwrap();
+    let (start, frames) = (SAMPLE_RATE as usize * 3 / 2, SAMPLE_RATE as usize * 2);
+    let expected = &decoded.samples[start..start + frames];
+
+    fn read_all(mut source: impl AudioSource) -> Vec<f32> {
+        let (mut all, mut block) = (Vec::new(), vec![0.0; 1000]);
+        loop {
+            let read = source.read_frames(&mut block).unwrap();
+            if read == 0 {
+                return all;
+            }
+            all.extend_from_slice(&block[..read]);
+        }
+    }
+    let excerpt = |source| {
+        read_all(Excerpt::new(
+            source,
+            Duration::from_millis(1500),
+            Some(Duration::from_secs(2)),
+        ))
+    };
+    // El archivo salta con el contenedor; el lector sin retroceso decodifica y descarta.
+    let mut file = FileSource::open(path.to_str().unwrap()).unwrap();
+    assert!(file.seek(0));
+    let sources: [Box<dyn AudioSource>; 3] = [
+        Box::new(file),
+        Box::new(FileSource::from_reader(Cursor::new(bytes)).unwrap()),
+        Box::new(MemorySource::from(decoded.clone())),
+    ];
+    for source in sources {
+        assert_eq!(excerpt(source), expected);
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let tail = read_all(Excerpt::new(
+        MemorySource::from(decoded.clone()),
+        Duration::from_secs(4),
+        None,
+    ));
+    assert_eq!(tail, &decoded.samples[SAMPLE_RATE as usize * 4..]);
+}
+
+/// Escala de notas con cuatro armónicos desde `base` Hz, una nota cada cuarto de segundo, a
+/// la tasa `rate`.
+fn harmonic_scale(base: f32, rate: u32, seconds: usize) -> Vec<f32> {
+    (0..rate as usize * seconds)
+        .map(|n| {
+            let t = n as f32 / rate as f32;
+            let note = base * 2f32.powf(((t * 4.0) as usize * 5 % 12) as f32 / 12.0);
+            (1..=4)
+                .map(|h| (2.0 * PI * note * h as f32 * t).sin() / (4 * h) as f32)
+                .sum::<f32>()
+        })
+        .collect()
+}
+
+/// WAV mono con `samples` codificadas como PCM entero de `bits` bits (`float = false`) o IEEE
+/// float.
+fn wav_with_format(samples: &[f32], bits: u16, float: bool) -> Vec<u8> {
+    let width = bits as usize / 8;
+    let data_len = (samples.len() * width) as u32;
+    let mut bytes = Vec::with_capacity(44 + samples.len() * width);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&(if float { 3u16 } else { 1 }).to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * width as u32).to_le_bytes());
+    bytes.extend_from_slice(&(width as u16).to_le_bytes());
+    bytes.extend_from_slice(&bits.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for &sample in samples {
+        match (bits, float) {
+            (8, false) => bytes.push((sample * 127.0 + 128.0) as u8),
+            (24, false) => {
+                bytes.extend_from_slice(&((sample * 8_388_607.0) as i32).to_le_bytes()[..3])
+            }
+            (32, false) => {
+                bytes.extend_from_slice(&((sample as f64 * 2_147_483_647.0) as i32).to_le_bytes())
+            }
+            (32, true) => bytes.extend_from_slice(&sample.to_le_bytes()),
+            (64, true) => bytes.extend_from_slice(&(sample as f64).to_le_bytes()),
+            _ => unreachable!(),
+        }
+    }
+    bytes
+}
+
+/// Elemento EBML con el tamaño codificado siempre en 8 bytes.
+fn ebml(id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut element = id.to_vec();
+    element.push(0x01);
+    element.extend_from_slice(&(data.len() as u64).to_be_bytes()[1..]);
+    element.extend_from_slice(data);
+    element
+}
+
+/// CRC de `bits` bits (8 o 16) con el polinomio `poly`, como los de las tramas FLAC.
+fn crc(bytes: &[u8], poly: u32, bits: u32) -> u32 {
+    let mask = (1 << bits) - 1;
+    bytes.iter().fold(0, |mut crc, &byte| {
+        crc ^= (byte as u32) << (bits - 8);
+        for _ in 0..8 {
+            crc = if crc >> (bits - 1) & 1 == 1 {
+                (crc << 1) ^ poly
+            } else {
+                crc << 1
+            } & mask;
+        }
+        crc
+    })
+}
+
+/// Trama FLAC mono de 16 bits con las muestras sin comprimir (subtrama verbatim).
+fn flac_frame(number: u8, samples: &[f32]) -> Vec<u8> {
+    // Sincronía de bloque fijo, tamaño de bloque en 16 bits al final, tasa de STREAMINFO.
+    let mut frame = vec![0xFF, 0xF8, 0x70, 0x08, number];
+    frame.extend_from_slice(&(samples.len() as u16 - 1).to_be_bytes());
+    frame.push(crc(&frame, 0x07, 8) as u8);
+    frame.push(0x02);
+    for &sample in samples {
+        frame.extend_from_slice(&((sample * 32767.0) as i16).to_be_bytes());
+    }
+    let footer = crc(&frame, 0x8005, 16) as u16;
+    frame.extend_from_slice(&footer.to_be_bytes());
+    frame
+}
+
+/// MKV con una pista FLAC mono por señal, intercaladas en bloques de 0.1 s.
+fn mkv_with_tracks(tracks: &[&[f32]]) -> Vec<u8> {
+    let block = SAMPLE_RATE as usize / 10;
+    let mut stream_info = b"fLaC\x80\x00\x00\x22".to_vec();
+    stream_info.extend_from_slice(&(block as u16).to_be_bytes());
+    stream_info.extend_from_slice(&(block as u16).to_be_bytes());
+    stream_info.extend_from_slice(&[0; 6]);
+    let frames = tracks[0].len() as u64;
+    stream_info.extend_from_slice(&((SAMPLE_RATE as u64) << 44 | 15 << 36 | frames).to_be_bytes());
+    stream_info.extend_from_slice(&[0; 16]);
+
+    let mut entries = Vec::new();
+    for number in 1..=tracks.len() as u64 {
+        let audio = [
+            ebml(&[0xB5], &(SAMPLE_RATE as f64).to_be_bytes()),
+            ebml(&[0x9F], &[1]),
+            ebml(&[0x62, 0x64], &[16]),
+        ]
+        .concat();
+        let entry = [
+            ebml(&[0xD7], &number.to_be_bytes()),
+            ebml(&[0x73, 0xC5], &number.to_be_bytes()),
+            ebml(&[0x83], &[2]),                                      // audio
+            ebml(&[0x23, 0xE3, 0x83], &100_000_000u32.to_be_bytes()), // 0.1 s por bloque
+            ebml(&[0x86], b"A_FLAC"),
+            ebml(&[0x63, 0xA2], &stream_info),
+            ebml(&[0xE1], &audio),
+        ]
+        .concat();
+        entries.extend(ebml(&[0xAE], &entry));
+    }
+
+    let mut cluster = ebml(&[0xE7], &[0]);
+    for (index, start) in (0..tracks[0].len()).step_by(block).enumerate() {
+        for (number, samples) in tracks.iter().enumerate() {
+            // Número de pista, tiempo relativo al cluster en ms y banderas (clave, sin lacing).
+            let mut data = vec![0x81 + number as u8];
+            data.extend_from_slice(&(index as i16 * 100).to_be_bytes());
+            data.push(0x80);
+            data.extend(flac_frame(index as u8, &samples[start..start + block]));
+            cluster.extend(ebml(&[0xA3], &data));
+        }
+    }
+
+    let segment = [
+        ebml(
+            &[0x15, 0x49, 0xA9, 0x66],
+            &ebml(&[0x2A, 0xD7, 0xB1], &1_000_000u32.to_be_bytes()),
+        ),
+        ebml(&[0x16, 0x54, 0xAE, 0x6B], &entries),
+        ebml(&[0x1F, 0x43, 0xB6, 0x75], &cluster),
+    ]
+    .concat();
+    [
+        ebml(&[0x1A, 0x45, 0xDF, 0xA3], &ebml(&[0x42, 0x82], b"matroska")),
+        ebml(&[0x18, 0x53, 0x80, 0x67], &segment),
+    ]
+    .concat()
+}
+
+#[test]
+fn track_selects_the_stream_of_a_multi_track_file() {
+    let (first, second) = (
+        harmonic_scale(220.0, SAMPLE_RATE, 4),
+        harmonic_scale(330.0, SAMPLE_RATE, 4),
+    );
+    let path = std::env::temp_dir().join("sonora_tracks_test.mkv");
+    std::fs::write(&path, mkv_with_tracks(&[&first, &second])).unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    for (track, expected) in [&first, &second].into_iter().enumerate() {
+        let decoded = decode_track_to_samples(&path, track).unwrap();
+        assert_eq!(decoded.samples.len(), expected.len());
+        for (a, b) in decoded.samples.iter().zip(expected) {
+            assert!((a - b).abs() < 1e-3);
+        }
+    }
+    let default = decode_to_samples(&path).unwrap();
+    let first_track = decode_track_to_samples(&path, 0).unwrap();
+    assert_eq!(default.samples, first_track.samples);
+
+    let config = FingerprintConfig::default();
+    let hashes = |source: FileSource| -> Vec<(u64, f32)> {
+        let hashes = fingerprint_source(source, &config).unwrap();
+        hashes
+            .iter()
+            .map(|entry| (entry.hash, entry.time))
+            .collect()
+    };
+    let by_track = hashes(FileSource::open_track(&path, 1).unwrap());
+    let decoded = decode_track_to_samples(&path, 1).unwrap();
+    let from_memory = fingerprint_source(MemorySource::from(decoded), &config).unwrap();
+    assert!(!by_track.is_empty());
+    assert_ne!(by_track, hashes(FileSource::open(&path).unwrap()));
+    assert!(by_track
+        .iter()
+        .zip(&from_memory)
+        .all(|(&a, b)| a == (b.hash, b.time)));
+    assert!(matches!(
+        FileSource::open_track(&path, 2),
+        Err(SonoraError::TrackNotFound {
+            index: 2,
+            available: 2
+        })
+    ));
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn corrupt_packets_are_skipped_as_silence() {
+    let signal = harmonic_scale(220.0, SAMPLE_RATE, 7);
+    let block = SAMPLE_RATE as usize / 10;
+    let clean = mkv_with_tracks(&[&signal]);
+    // Rompe el CRC de la cabecera de una trama FLAC: el decodificador la rechaza.
+    let corrupt = |bytes: &mut Vec<u8>, frame: u8| {
+        let header = [0xFF, 0xF8, 0x70, 0x08, frame];
+        let pos = bytes.windows(5).position(|w| w == header).unwrap();
+        bytes[pos + 7] ^= 0xFF;
+    };
+    let path = std::env::temp_dir().join("sonora_corrupt_test.mkv");
+
+    let mut bytes = clean.clone();
+    corrupt(&mut bytes, 10);
+    std::fs::write(&path, &bytes).unwrap();
+    let decoded = decode_to_samples(path.to_str().unwrap()).unwrap();
+    assert_eq!(decoded.skipped_packets, 1);
+    // El paquete perdido se sustituye por silencio y el resto conserva sus tiempos.
+    assert_eq!(decoded.samples.len(), signal.len());
+    let lost = 10 * block..11 * block;
+    assert!(decoded.samples[lost.clone()].iter().all(|&s| s == 0.0));
+    for (n, (a, b)) in decoded.samples.iter().zip(&signal).enumerate() {
+        assert!(lost.contains(&n) || (a - b).abs() < 1e-3);
+    }
+
+    // Si no se puede decodificar nada, el error no se confunde con el final del archivo.
+    let mut bytes = clean;
+    for frame in 0..70 {
+        corrupt(&mut bytes, frame);
+    }
+    std::fs::write(&path, &bytes).unwrap();
+    let result = decode_to_samples(path.to_str().unwrap());
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(result, Err(SonoraError::Decode(_))));
+}
+
+#[test]
+fn every_sample_format_decodes_to_the_same_signal() {
+    let reference = decode_reader(Cursor::new(wav_bytes(1))).unwrap().samples;
+    for (bits, float) in [(8, false), (24, false), (32, false), (32, true), (64, true)] {
+        let decoded = decode_reader(Cursor::new(wav_with_format(&reference, bits, float))).unwrap();
+        assert_eq!(decoded.samples.len(), reference.len(), "{} bits", bits);
+        let error = decoded
+            .samples
+            .iter()
+            .zip(&reference)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        // U8 solo tiene 8 bits de resolución; el resto reproduce las muestras de 16 bits.
+        let tolerance = if bits == 8 { 0.02 } else { 1e-4 };
+        assert!(error < tolerance, "{} bits: error {}", bits, error);
+    }
+}
+
+#[test]
+fn float_wav_files_decode_and_fingerprint() {
+    // Un WAV cuyo formato natural es F32, que un decodificador solo de S16 descartaría.
+    let samples = harmonic_scale(220.0, SAMPLE_RATE, 3);
+    let path = std::env::temp_dir().join(format!("sonora_f32_{}.wav", std::process::id()));
+    std::fs::write(&path, wav_with_format(&samples, 32, true)).unwrap();
+    let path = path.to_str().unwrap().to_string();
+
+    let decoded = decode_to_samples(&path).unwrap();
+    let hashes = fingerprint_file(&path, &FingerprintConfig::default()).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(decoded.samples.len(), samples.len());
+    assert!(decoded.samples.iter().any(|&sample| sample.abs() > 0.1));
+    assert!(!hashes.is_empty());
+}
+
+#[test]
+fn landmarks_do_not_depend_on_the_source_rate() {
+    // La misma escala sintetizada a cada tasa.
+    let scale = |rate: u32| harmonic_scale(220.0, rate, 5);
+    // A 16 kHz la ventana crece en proporción para conservar la resolución.
+    for (canonical_rate, window_size) in [(11025, 1024), (16000, 1486)] {
+        let config = FingerprintConfig {
+            canonical_rate,
+            window_size,
+            overlap: window_size / 2,
+            ..FingerprintConfig::default()
+        };
+        let hashes = |rate: u32| {
+            fingerprint_source(MemorySource::new(scale(rate), 1, rate), &config).unwrap()
+        };
+
+        let mut db = FingerprintDb::new();
+        db.insert(0, &hashes(canonical_rate));
+        for rate in [22050, 44100, 48000] {
+            let results = match_query(&hashes(rate), &db);
+            let context = format!("{} Hz a {} Hz", rate, canonical_rate);
+            assert_eq!(results.first().map(|r| r.song_id), Some(0), "{}", context);
+            assert!(results[0].offset_seconds.abs() < 0.1, "{}", context);
+        }
+    }
+}
+
+#[test]
+fn normalize_order_changes_the_mix_of_an_unbalanced_stereo_signal() {
+    // Canal izquierdo casi a fondo y derecho muy bajo, con tonos de un número entero de
+    // ciclos para que sean ortogonales.
+    let tone = |amplitude: f32, frequency: f32| -> Vec<f32> {
+        (0..SAMPLE_RATE)
+            .map(|n| amplitude * (2.0 * PI * frequency * n as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    };
+    let (left, right) = (tone(0.9, 440.0), tone(0.02, 1000.0));
+    let stereo: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .flat_map(|(&l, &r)| [l, r])
+        .collect();
+    let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+    // Cuánto se amplifica el canal derecho en la mezcla respecto al izquierdo.
+    let right_weight = |order| {
+        let mono = interleaved_to_mono(&stereo, 2, order).unwrap();
+        (dot(&mono, &right) / dot(&right, &right)) / (dot(&mono, &left) / dot(&left, &left))
+    };
+
+    let after = right_weight(NormalizeOrder::AfterDownmix);
+    let before = right_weight(NormalizeOrder::BeforeDownmix);
+    // Normalizando tras la mezcla los canales conservan su proporción; antes, el canal bajo
+    // se amplifica 0.9 / 0.02 = 45 veces más y pesa lo mismo que el otro.
+    assert!((after - 1.0).abs() < 0.1, "{after}");
+    assert!((before - 45.0).abs() < 5.0, "{before}");
+    assert_ne!(
+        interleaved_to_mono(&stereo, 2, NormalizeOrder::AfterDownmix).unwrap(),
+        interleaved_to_mono(&stereo, 2, NormalizeOrder::BeforeDownmix).unwrap()
+    );
+}
+
+#[test]
+fn a_channel_one_sample_short_drops_the_ragged_frame() {
+    // 101 muestras a la izquierda y 100 a la derecha: la última trama no tiene canal derecho.
+    let stereo: Vec<f32> = (0..201)
+        .map(|i| if i % 2 == 0 { 0.5 } else { 0.25 })
+        .collect();
+    for order in [NormalizeOrder::AfterDownmix, NormalizeOrder::BeforeDownmix] {
+        let mono = interleaved_to_mono(&stereo, 2, order).unwrap();
+        assert_eq!(mono.len(), 100, "{order:?}");
+        assert!(mono.iter().all(|&s| s == mono[0]), "{order:?}");
+    }
+    assert!(matches!(
+        interleaved_to_mono(&stereo, 0, NormalizeOrder::AfterDownmix),
+        Err(SonoraError::NoChannels)
+    ));
+
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * 4 + 1)
+        .map(|n| (8000.0 * (2.0 * PI * 440.0 * (n / 2) as f32 / SAMPLE_RATE as f32).sin()) as i16)
+        .collect();
+    fingerprint_i16(&samples, 2, SAMPLE_RATE, &FingerprintConfig::default()).unwrap();
+}
+
+#[test]
+fn channel_policy_selects_the_signal() {
+    let left = harmonic_scale(220.0, SAMPLE_RATE, 3);
+    let right = harmonic_scale(330.0, SAMPLE_RATE, 3);
+    let stereo: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .flat_map(|(&l, &r)| [l, r])
+        .collect();
+    let mono = |samples: Vec<f32>| {
+        fingerprint_source(
+            MemorySource::new(samples, 1, SAMPLE_RATE),
+            &FingerprintConfig::default(),
+        )
+        .unwrap()
+    };
+    let with_policy = |policy| {
+        let config = FingerprintConfig {
+            channel_policy: policy,
+            ..FingerprintConfig::default()
+        };
+        fingerprint_source(MemorySource::new(stereo.clone(), 2, SAMPLE_RATE), &config)
+    };
+    let same = |a: &[HashEntry], b: &[HashEntry]| {
+        assert!(!a.is_empty());
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!((a.hash, a.time), (b.hash, b.time));
+        }
+    };
+
+    let mid: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .map(|(l, r)| (l + r) / 2.0)
+        .collect();
+    let side: Vec<f32> = left
+        .iter()
+        .zip(&right)
+        .map(|(l, r)| (l - r) / 2.0)
+        .collect();
+    same(
+        &with_policy(ChannelPolicy::Left).unwrap(),
+        &mono(left.clone()),
+    );
+    same(
+        &with_policy(ChannelPolicy::Right).unwrap(),
+        &mono(right.clone()),
+    );
+    same(&with_policy(ChannelPolicy::Mid).unwrap(), &mono(mid));
+    same(&with_policy(ChannelPolicy::Side).unwrap(), &mono(side));
+
+    let per_channel = fingerprint_channels(
+        MemorySource::new(stereo.clone(), 2, SAMPLE_RATE),
+        &FingerprintConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(per_channel.len(), 2);
+    same(&per_channel[0], &mono(left));
+    same(&per_channel[1], &mono(right));
+    assert!(matches!(
+        with_policy(ChannelPolicy::PerChannel),
+        Err(SonoraError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn band_hasher_emits_one_hash_per_window() {
+    let samples: Vec<f32> = (0..SAMPLE_RATE as usize * 2)
+        .map(|n| (2.0 * PI * 440.0 * n as f32 / SAMPLE_RATE as f32).sin())
+        .collect();
+    let config = FingerprintConfig::default();
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE as usize, &config);
+    let hashes = BandHasher.hash(&spectrogram, &config).unwrap();
+    assert_eq!(hashes.len(), spectrogram.times.len());
+    assert!(hashes
+        .iter()
+        .zip(&spectrogram.times)
+        .all(|(entry, &time)| entry.time == time));
+
+    let fingerprinter = Fingerprinter::builder().hasher(BandHasher).build().unwrap();
+    let from_source = fingerprinter
+        .fingerprint_source(MemorySource::new(samples, 1, SAMPLE_RATE))
+        .unwrap();
+    assert!(!from_source.is_empty());
+}
+
+#[test]
+fn mono_wav_files_go_through_every_engine() {
+    // Los antiguos binarios por bandas leían siempre el segundo canal y fallaban con un WAV mono.
+    let path = std::env::temp_dir().join(format!("sonora_mono_{}.wav", std::process::id()));
+    std::fs::write(&path, wav_bytes(3)).unwrap();
+    let path = path.to_str().unwrap().to_string();
+    let config = FingerprintConfig::default();
+
+    let source = FileSource::open(&path).unwrap();
+    assert_eq!(source.channels(), 1);
+    let mut reference: Vec<HashEntry> = Vec::new();
+    band_fingerprints(source, &config, Engine::Sequential, &mut reference).unwrap();
+    assert!(!reference.is_empty());
+    for engine in Engine::ALL {
+        let mut hashes: Vec<HashEntry> = Vec::new();
+        band_fingerprints(
+            FileSource::open(&path).unwrap(),
+            &config,
+            engine,
+            &mut hashes,
+        )
+        .unwrap();
+        assert_eq!(hashes.len(), reference.len(), "{engine}");
+        assert!(
+            hashes.iter().zip(&reference).all(|(a, b)| a.hash == b.hash),
+            "{engine}"
+        );
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cancelled_token_aborts_the_pipeline() {
+    let token = CancelToken::new();
+    let fingerprinter = Fingerprinter::builder()
+        .cancel_token(token.clone())
+        .build()
+        .unwrap();
+    let source = || MemorySource::new(vec![0.1; SAMPLE_RATE as usize], 1, SAMPLE_RATE);
+
+    assert!(fingerprinter.fingerprint_source(source()).is_ok());
+    token.cancel();
+    assert!(matches!(
+        fingerprinter.fingerprint_source(source()),
+        Err(SonoraError::Cancelled)
+    ));
+}
+
+#[test]
+fn progress_sink_sees_every_stage() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let fingerprinter = Fingerprinter::builder()
+        .fan_value(5)
+        .progress(move |progress| sink.lock().unwrap().push(progress))
+        .build()
+        .unwrap();
+
+    let bytes = wav_bytes(5);
+    let total_bytes = bytes.len() as u64;
+    let hashes = fingerprinter
+        .fingerprint_reader(Cursor::new(bytes))
+        .unwrap();
+    let events = events.lock().unwrap();
+
+    let decoded = events.iter().rev().find_map(|event| match *event {
+        Progress::Decoded { frames, bytes } => Some((frames, bytes)),
+        _ => None,
+    });
+    let (frames, bytes) = decoded.unwrap();
+    assert_eq!(frames, SAMPLE_RATE as u64 * 5);
+    assert!(bytes.is_some_and(|bytes| bytes > 0 && bytes <= total_bytes));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Progress::Windows { done, total } if done == total)));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, Progress::Peaks { count } if *count > 0)));
+    assert_eq!(
+        events.last(),
+        Some(&Progress::Hashes {
+            count: hashes.len()
+        })
+    );
+}
+
+#[test]
+fn sinks_receive_every_batch() {
+    let audio = decode_reader(Cursor::new(wav_bytes(5))).unwrap();
+    let config = FingerprintConfig::default();
+    let run = |sink: &mut dyn HashSink| {
+        band_fingerprints(
+            MemorySource::from(audio.clone()),
+            &config,
+            Engine::Sequential,
+            sink,
+        )
+        .unwrap()
+    };
+
+    let mut collected: Vec<HashEntry> = Vec::new();
+    run(&mut collected);
+    // El binario se escribe entero al final aunque lleguen varios lotes.
+    let mut writer = FingerprintWriter::new(Vec::new(), FingerprintFormat::Binary);
+    run(&mut writer);
+    assert_eq!(writer.count(), collected.len());
+    let written =
+        read_fingerprints(Cursor::new(writer.into_inner()), FingerprintFormat::Binary).unwrap();
+    assert!(!collected.is_empty());
+    assert_eq!(
+        written.iter().map(|e| (e.hash, e.time)).collect::<Vec<_>>(),
+        collected
+            .iter()
+            .map(|e| (e.hash, e.time))
+            .collect::<Vec<_>>()
+    );
+
+    let path = std::env::temp_dir().join("sonora_sink_test.wav");
+    std::fs::write(&path, wav_bytes(5)).unwrap();
+    let fingerprinter = Fingerprinter::builder().build().unwrap();
+    let mut db = FingerprintDb::new();
+    fingerprinter
+        .fingerprint_into(&path, &mut DbInserter::new(&mut db, 7))
+        .unwrap();
+    let expected = fingerprinter.fingerprint(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(db.song_hash_counts(), [(7, expected.len())]);
+    assert!(db.lookup(expected[0].hash).contains(&(7, expected[0].time)));
+}
+
+#[test]
+fn ndjson_files_record_the_fingerprint_version() {
+    let entries = vec![
+        HashEntry {
+            hash: 0xabc,
+            time: 0.25,
+        },
+        HashEntry {
+            hash: u64::MAX,
+            time: 1.5,
+        },
+    ];
+    let mut whole = Vec::new();
+    write_fingerprints(&mut whole, &entries, FingerprintFormat::NdJson).unwrap();
+    // Por lotes, la cabecera se escribe una sola vez.
+    let mut writer = FingerprintWriter::new(Vec::new(), FingerprintFormat::NdJson);
+    writer.accept(&entries[..1]).unwrap();
+    writer.accept(&entries[1..]).unwrap();
+    writer.finish().unwrap();
+    let batched = writer.into_inner();
+    assert_eq!(batched, whole);
+
+    let text = String::from_utf8(whole).unwrap();
+    let header = format!("{{\"version\":{}}}", FINGERPRINT_VERSION);
+    assert_eq!(text.lines().next(), Some(header.as_str()));
+    let read = read_fingerprints(Cursor::new(&text), FingerprintFormat::NdJson).unwrap();
+    assert_eq!(
+        read.iter().map(|e| (e.hash, e.time)).collect::<Vec<_>>(),
+        [(0xabc, 0.25), (u64::MAX, 1.5)]
+    );
+
+    let other = text.replacen(&header, "{\"version\":1}", 1);
+    assert!(matches!(
+        read_fingerprints(Cursor::new(other), FingerprintFormat::NdJson),
+        Err(SonoraError::VersionMismatch {
+            found: 1,
+            expected: FINGERPRINT_VERSION
+        })
+    ));
+    let headless: String = text
+        .lines()
+        .skip(1)
+        .map(|line| format!("{line}\n"))
+        .collect();
+    assert!(matches!(
+        read_fingerprints(Cursor::new(headless), FingerprintFormat::NdJson),
+        Err(SonoraError::InvalidFingerprints)
+    ));
+
+    let mut empty = FingerprintWriter::new(Vec::new(), FingerprintFormat::NdJson);
+    empty.finish().unwrap();
+    let empty = empty.into_inner();
+    assert!(
+        read_fingerprints(Cursor::new(empty), FingerprintFormat::NdJson)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn non_finite_times_are_rejected_in_ndjson() {
+    for time in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        let entries = [
+            HashEntry { hash: 1, time: 0.5 },
+            HashEntry { hash: 2, time },
+        ];
+        let mut out = Vec::new();
+        assert!(matches!(
+            write_fingerprints(&mut out, &entries, FingerprintFormat::NdJson),
+            Err(SonoraError::InvalidFingerprints)
+        ));
+        assert!(out.is_empty());
+
+        // El lote se rechaza entero; lo ya escrito sigue siendo un archivo válido.
+        let mut writer = FingerprintWriter::new(Vec::new(), FingerprintFormat::NdJson);
+        writer.accept(&entries[..1]).unwrap();
+        assert!(writer.accept(&entries).is_err());
+        writer.finish().unwrap();
+        let read = read_fingerprints(Cursor::new(writer.into_inner()), FingerprintFormat::NdJson);
+        assert_eq!(read.unwrap().len(), 1);
+
+        // El binario guarda el tiempo tal cual.
+        let mut binary = Vec::new();
+        write_fingerprints(&mut binary, &entries, FingerprintFormat::Binary).unwrap();
+        let read = read_fingerprints(Cursor::new(binary), FingerprintFormat::Binary).unwrap();
+        assert_eq!(read[1].time.to_bits(), time.to_bits());
+    }
+
+    let nan = format!("{{\"version\":{FINGERPRINT_VERSION}}}\n{{\"hash\":\"01\",\"time\":NaN}}\n");
+    assert!(matches!(
+        read_fingerprints(Cursor::new(nan), FingerprintFormat::NdJson),
+        Err(SonoraError::InvalidFingerprints)
+    ));
+}
+
+/// Asignador del sistema que recuerda la mayor reserva pedida desde un hilo marcado con
+/// `TRACKED`.
+struct LargestAllocation;
+
+static LARGEST: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static TRACKED: Cell<bool> = const { Cell::new(false) };
+}
+
+impl LargestAllocation {
+    fn record(size: usize) {
+        if TRACKED.try_with(Cell::get).unwrap_or(false) {
+            LARGEST.fetch_max(size, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for LargestAllocation {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: LargestAllocation = LargestAllocation;
+
+#[test]
+fn a_huge_reported_size_keeps_allocations_bounded() {
+    // Cabecera que anuncia casi 4 GiB de datos para un archivo de 5 s.
+    let honest = wav_bytes(5);
+    let mut lying = honest.clone();
+    lying[4..8].copy_from_slice(&(u32::MAX - 8).to_le_bytes());
+    lying[40..44].copy_from_slice(&(u32::MAX - 44).to_le_bytes());
+    let config = FingerprintConfig::default();
+    let landmarks = |bytes: Vec<u8>| {
+        fingerprint_source(
+            FileSource::from_reader(Cursor::new(bytes)).unwrap(),
+            &config,
+        )
+    };
+    let bands = |bytes: Vec<u8>| {
+        let mut hashes: Vec<HashEntry> = Vec::new();
+        band_fingerprints(
+            FileSource::from_reader(Cursor::new(bytes)).unwrap(),
+            &config,
+            Engine::Sequential,
+            &mut hashes,
+        )
+        .map(|()| hashes)
+    };
+
+    TRACKED.with(|tracked| tracked.set(true));
+    let from_lying = (landmarks(lying.clone()), bands(lying));
+    TRACKED.with(|tracked| tracked.set(false));
+    let largest = LARGEST.load(Ordering::Relaxed);
+    assert!(largest < 64 << 20, "reserva de {largest} bytes");
+
+    let (landmarks_lying, bands_lying) = (from_lying.0.unwrap(), from_lying.1.unwrap());
+    let (landmarks_honest, bands_honest) =
+        (landmarks(honest.clone()).unwrap(), bands(honest).unwrap());
+    assert!(!landmarks_lying.is_empty());
+    assert_eq!(landmarks_lying.len(), landmarks_honest.len());
+    assert_eq!(bands_lying.len(), bands_honest.len());
+}
@@ -0,0 +1,172 @@
+//! Vectores de referencia del pipeline de landmarks sobre WAV sintéticos generados al vuelo.
+//!
+//! Cada caso fija el número de hashes, los primeros hashes y un resumen de la secuencia
+//! completa. Si un cambio del algoritmo los altera a propósito, hay que subir
+//! `FINGERPRINT_VERSION` y actualizar estos valores.
+
+mod common;
+
+use std::f32::consts::PI;
+use std::path::PathBuf;
+
+use sonora::{fingerprint_file, FingerprintConfig, HashEntry};
+
+/// Resultado esperado de un caso.
+struct Golden {
+    count: usize,
+    head: &'static [(u64, f32)],
+    digest: u64,
+}
+
+/// Escribe un WAV PCM de 16 bits con `channels` canales; `sample(n, c)` da la muestra `n` del
+/// canal `c` en `[-1, 1]`.
+fn write_wav(
+    name: &str,
+    sample_rate: u32,
+    channels: u16,
+    frames: usize,
+    sample: impl Fn(usize, u16) -> f32,
+) -> PathBuf {
+    let samples: Vec<i16> = (0..frames)
+        .flat_map(|n| (0..channels).map(move |c| (n, c)))
+        .map(|(n, c)| (sample(n, c).clamp(-1.0, 1.0) * 32767.0) as i16)
+        .collect();
+    let path =
+        std::env::temp_dir().join(format!("sonora_golden_{}_{name}.wav", std::process::id()));
+    common::write_wav(&path, channels, sample_rate, &samples).unwrap();
+    path
+}
+
+/// FNV-1a sobre los hashes y los bits de los tiempos, en orden.
+fn digest(hashes: &[HashEntry]) -> u64 {
+    hashes.iter().fold(0xcbf2_9ce4_8422_2325, |acc, entry| {
+        [entry.hash, entry.time.to_bits() as u64]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .fold(acc, |acc, byte| {
+                (acc ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    })
+}
+
+fn check(name: &str, path: PathBuf, expected: &Golden) {
+    let hashes = fingerprint_file(path.to_str().unwrap(), &FingerprintConfig::default());
+    let _ = std::fs::remove_file(&path);
+    let hashes = hashes.unwrap();
+
+    let head: Vec<(u64, f32)> = hashes
+        .iter()
+        .take(6)
+        .map(|entry| (entry.hash, entry.time))
+        .collect();
+    assert_eq!(hashes.len(), expected.count, "{name}: número de hashes");
+    assert_eq!(head, expected.head, "{name}: primeros hashes");
+    assert_eq!(
+        digest(&hashes),
+        expected.digest,
+        "{name}: secuencia completa"
+    );
+}
+
+/// Nota pulsada: fundamental y dos armónicos con una envolvente que decae desde el ataque,
+/// así que cada nota deja picos claros en el espectrograma.
+fn pluck(freq: f32, t: f32) -> f32 {
+    let envelope = (-4.0 * t).exp();
+    envelope
+        * [(1.0, 0.5), (2.0, 0.25), (3.0, 0.15)]
+            .iter()
+            .map(|&(harmonic, gain)| gain * (2.0 * PI * harmonic * freq * t).sin())
+            .sum::<f32>()
+}
+
+/// Escala cromática de notas pulsadas, una cada cuarto de segundo, mono a 22050 Hz.
+#[test]
+fn chromatic_scale_mono() {
+    let rate = 22050;
+    let note_len = rate as usize / 4;
+    let path = write_wav("scale", rate, 1, rate as usize * 8, |n, _| {
+        let note = 220.0 * 2f32.powf((n / note_len % 12) as f32 / 12.0);
+        pluck(note, (n % note_len) as f32 / rate as f32)
+    });
+    check(
+        "escala",
+        path,
+        &Golden {
+            count: 23,
+            head: &[
+                (0x4007c000, 1.4860771),
+                (0x400dc00f, 1.4860771),
+                (0x400a4019, 1.4860771),
+                (0x40030028, 1.4860771),
+                (0x4009c050, 1.4860771),
+                (0x7c0dc00f, 1.4860771),
+            ],
+            digest: 0x344cd3e3c0315ad9,
+        },
+    );
+}
+
+/// Acordes pulsados con una voz distinta en cada canal, estéreo a 44100 Hz, para cubrir la
+/// mezcla a mono y el remuestreo.
+#[test]
+fn two_voice_chords_stereo() {
+    let rate = 44100;
+    let chord_len = rate as usize / 3;
+    let path = write_wav("chords", rate, 2, rate as usize * 4, |n, c| {
+        let root = 196.0 * 2f32.powf((n / chord_len * 5 % 12) as f32 / 12.0);
+        let freq = if c == 0 { root } else { root * 1.5 };
+        pluck(freq, (n % chord_len) as f32 / rate as f32)
+    });
+    check(
+        "acordes",
+        path,
+        &Golden {
+            count: 66,
+            head: &[
+                (0x3409c000, 0.32507935),
+                (0x34068007, 0.32507935),
+                (0x340d0007, 0.32507935),
+                (0x3413c007, 0.32507935),
+                (0x3404001b, 0.32507935),
+                (0x340dc028, 0.32507935),
+            ],
+            digest: 0x0ebbafd9cd96571a,
+        },
+    );
+}
+
+/// Barrido lineal de 300 Hz a 3 kHz en 6 s con pulsos de amplitud cada 0,2 s, mono a
+/// 48000 Hz.
+#[test]
+fn linear_sweep_mono() {
+    let rate = 48000;
+    let seconds = 6.0;
+    let path = write_wav(
+        "sweep",
+        rate,
+        1,
+        (rate as f32 * seconds) as usize,
+        |n, _| {
+            let t = n as f32 / rate as f32;
+            let phase = 2.0 * PI * (300.0 * t + (3000.0 - 300.0) * t * t / (2.0 * seconds));
+            let pulse = (-6.0 * (t % 0.2)).exp();
+            0.5 * pulse * phase.sin()
+        },
+    );
+    check(
+        "barrido",
+        path,
+        &Golden {
+            count: 21,
+            head: &[
+                (0x880d0010, 0.7894785),
+                (0x8810401c, 0.7894785),
+                (0x8814c02c, 0.7894785),
+                (0x88180038, 0.7894785),
+                (0x881f0050, 0.7894785),
+                (0x8822405c, 0.7894785),
+            ],
+            digest: 0x78068086156edfd8,
+        },
+    );
+}
@@ -0,0 +1,395 @@
+//! Generación de hashes sobre audio repetitivo.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::PI;
+
+use sonora::{
+    calculate_spectrogram, find_peaks, fingerprint_from_spectrogram, generate_hashes,
+    generate_hashes_with, landmark_hash, match_query, match_query_with, FingerprintConfig,
+    FingerprintDb, HashScheme, MatchConfig, Peak, SonoraError, TargetZone, TupleHash,
+    CANONICAL_SAMPLE_RATE, FINGERPRINT_VERSION,
+};
+
+const SAMPLE_RATE: usize = CANONICAL_SAMPLE_RATE as usize;
+
+/// Bucle de cuatro notas repetido durante `seconds` segundos. Cada nota dura seis saltos de
+/// la ventana por defecto, así que el bucle cae siempre igual respecto a las ventanas y los
+/// `delta_t` se repiten exactamente; la fase vuelve a empezar en cada nota.
+fn looped_melody(seconds: f32) -> Vec<f32> {
+    let notes = [440.0, 660.0, 550.0, 880.0];
+    let config = FingerprintConfig::default();
+    let note_len = 6 * (config.window_size - config.overlap);
+    (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|n| {
+            let frequency = notes[(n / note_len) % notes.len()];
+            let t = (n % note_len) as f32 / SAMPLE_RATE as f32;
+            0.5 * (2.0 * PI * frequency * t).sin()
+        })
+        .collect()
+}
+
+#[test]
+fn dedup_collapses_repeated_hashes() {
+    let samples = looped_melody(30.0);
+    let mut config = FingerprintConfig::default();
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+
+    let all = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+    config.dedup = true;
+    let deduped = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+
+    // Con un máximo de una aparición cada hash queda una sola vez, y no se pierde ninguno.
+    let distinct: HashSet<u64> = all.iter().map(|entry| entry.hash).collect();
+    assert_eq!(deduped.len(), distinct.len());
+    assert!(
+        deduped.len() * 4 < all.len(),
+        "{} de {} entradas",
+        deduped.len(),
+        all.len()
+    );
+}
+
+#[test]
+fn max_hash_repeats_caps_each_hash() {
+    let samples = looped_melody(30.0);
+    let config = FingerprintConfig {
+        dedup: true,
+        max_hash_repeats: 3,
+        ..FingerprintConfig::default()
+    };
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+
+    let hashes = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+
+    let mut counts = HashMap::new();
+    for entry in &hashes {
+        *counts.entry(entry.hash).or_insert(0) += 1;
+    }
+    assert!(counts.values().all(|&count| count <= 3));
+    assert!(counts.values().any(|&count| count == 3));
+}
+
+#[test]
+fn one_bin_jitter_keeps_the_landmark_hash() {
+    let config = FingerprintConfig::default();
+    let resolution = SAMPLE_RATE as f32 / config.window_size as f32;
+    // Los bins 92 y 93 (~990 y ~1001 Hz) caen en el mismo intervalo de 20 Hz.
+    let (bin, next_bin) = (92.0 * resolution, 93.0 * resolution);
+
+    let reference = landmark_hash(bin, 2000.0, 1.0, &config);
+    assert_eq!(reference, landmark_hash(bin, 2000.0, 1.0, &config));
+    assert_eq!(reference, landmark_hash(next_bin, 2000.0, 1.02, &config));
+    assert_ne!(reference, landmark_hash(bin, 2000.0, 1.1, &config));
+
+    let exact = FingerprintConfig {
+        freq_bucket_hz: 0.0,
+        ..FingerprintConfig::default()
+    };
+    assert_ne!(
+        landmark_hash(bin, 2000.0, 1.0, &exact),
+        landmark_hash(next_bin, 2000.0, 1.0, &exact)
+    );
+}
+
+#[cfg(feature = "sha1")]
+#[test]
+fn sha1_scheme_reproduces_the_legacy_hashes() {
+    let config = FingerprintConfig {
+        hash_scheme: HashScheme::Sha1,
+        ..FingerprintConfig::default()
+    };
+    // SHA-1 de "22|33|10", el hash hexadecimal de entonces truncado a 16 caracteres.
+    assert_eq!(
+        format!("{:016x}", landmark_hash(440.0, 660.0, 0.5, &config)),
+        "296a7163ef1f8939"
+    );
+    assert_ne!(
+        landmark_hash(440.0, 660.0, 0.5, &config),
+        landmark_hash(440.0, 660.0, 0.5, &FingerprintConfig::default())
+    );
+}
+
+#[test]
+fn target_zone_only_pairs_peaks_inside_the_zone() {
+    let peak = |time, frequency| Peak {
+        time,
+        frequency,
+        magnitude: 1.0,
+    };
+    let peaks = [
+        peak(0.0, 1000.0),
+        peak(0.05, 1050.0), // demasiado cerca en el tiempo
+        peak(0.5, 1100.0),  // dentro de la zona
+        peak(0.6, 3000.0),  // fuera de la banda de frecuencia
+        peak(3.0, 1000.0),  // más allá de `max_dt`
+    ];
+    let zone = TargetZone {
+        min_dt: 0.1,
+        max_dt: 2.0,
+        freq_band: 500.0,
+    };
+    let config = FingerprintConfig {
+        target_zone: Some(zone),
+        ..FingerprintConfig::default()
+    };
+
+    let anchored: Vec<u64> = generate_hashes(&peaks, &config)
+        .unwrap()
+        .iter()
+        .filter(|entry| entry.time == 0.0)
+        .map(|entry| entry.hash)
+        .collect();
+    assert_eq!(anchored, [landmark_hash(1000.0, 1100.0, 0.5, &config)]);
+
+    // Sin zona, el ancla se empareja con todos los picos siguientes dentro de `max_delta_t`.
+    let config = FingerprintConfig::default();
+    let fan_out = generate_hashes(&peaks, &config)
+        .unwrap()
+        .iter()
+        .filter(|entry| entry.time == 0.0)
+        .count();
+    assert_eq!(fan_out, 4);
+}
+
+#[test]
+fn peaks_with_equal_times_hash_deterministically() {
+    let peak = |time, frequency, magnitude| Peak {
+        time,
+        frequency,
+        magnitude,
+    };
+    // Varios picos por instante y magnitudes empatadas, para que el orden dependa de la
+    // frecuencia.
+    let peaks = vec![
+        peak(0.5, 2000.0, 1.0),
+        peak(0.5, 500.0, 1.0),
+        peak(0.5, 1200.0, 2.0),
+        peak(1.0, 800.0, 1.0),
+        peak(1.0, 300.0, 1.0),
+        peak(1.5, 900.0, 2.0),
+    ];
+    let config = FingerprintConfig {
+        anchor_stride: 2,
+        ..FingerprintConfig::default()
+    };
+    let hashes = |peaks: &[Peak]| -> Vec<(u64, f32)> {
+        generate_hashes(peaks, &config)
+            .unwrap()
+            .iter()
+            .map(|entry| (entry.hash, entry.time))
+            .collect()
+    };
+
+    let expected = hashes(&peaks);
+    assert!(!expected.is_empty());
+    let mut reversed = peaks.clone();
+    reversed.reverse();
+    assert_eq!(hashes(&reversed), expected);
+    for _ in 0..10 {
+        assert_eq!(hashes(&peaks), expected);
+    }
+}
+
+#[test]
+fn hashes_come_out_in_anchor_time_order() {
+    let config = FingerprintConfig::default();
+    let samples = looped_melody(20.0);
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+    let mut peaks = find_peaks(&spectrogram, &config);
+    assert!(peaks.len() > 10);
+    // Barajar los picos, como los entregaría una detección paralela.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for i in (1..peaks.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        peaks.swap(i, state as usize % (i + 1));
+    }
+
+    let zoned = FingerprintConfig {
+        target_zone: Some(TargetZone::default()),
+        ..FingerprintConfig::default()
+    };
+    for config in [config, zoned] {
+        let hashes = generate_hashes(&peaks, &config).unwrap();
+        assert!(!hashes.is_empty());
+        assert!(hashes.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+}
+
+#[test]
+fn nan_peaks_are_rejected() {
+    let config = FingerprintConfig::default();
+    let peaks = [
+        Peak {
+            time: 0.0,
+            frequency: 440.0,
+            magnitude: 1.0,
+        },
+        Peak {
+            time: f32::NAN,
+            frequency: 880.0,
+            magnitude: 1.0,
+        },
+    ];
+
+    assert!(matches!(
+        generate_hashes(&peaks, &config),
+        Err(SonoraError::InvalidPeak)
+    ));
+}
+
+#[test]
+fn databases_match_queries_hashed_with_their_own_function() {
+    // Un pico cada 50 ms a una frecuencia pseudoaleatoria, para que no se repitan landmarks.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let song: Vec<Peak> = (0..200)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            Peak {
+                time: i as f32 * 0.05,
+                frequency: 200.0 + (state % 2800) as f32,
+                magnitude: 1.0,
+            }
+        })
+        .collect();
+    // Los picos entre 3 y 6 s, como si la consulta empezara ahí.
+    let query: Vec<Peak> = song
+        .iter()
+        .filter(|peak| (3.0..6.0).contains(&peak.time))
+        .map(|peak| Peak {
+            time: peak.time - 3.0,
+            ..peak.clone()
+        })
+        .collect();
+    let path = std::env::temp_dir().join("sonora_hash_scheme.db");
+
+    for (scheme, other) in [
+        (HashScheme::Packed, HashScheme::Fnv1a),
+        (HashScheme::Fnv1a, HashScheme::Packed),
+    ] {
+        let config = FingerprintConfig {
+            hash_scheme: scheme,
+            ..FingerprintConfig::default()
+        };
+        let mut db = FingerprintDb::with_hash_scheme(scheme);
+        db.insert(1, &generate_hashes(&song, &config).unwrap());
+        db.save(&path).unwrap();
+        let db = FingerprintDb::load(&path).unwrap();
+        assert_eq!(db.hash_scheme(), scheme);
+
+        db.check_hash_scheme(&config).unwrap();
+        let hashes = generate_hashes(&query, &config).unwrap();
+        let match_config = MatchConfig {
+            hash_scheme: scheme,
+            ..MatchConfig::default()
+        };
+        let results = match_query_with(&hashes, &db, &match_config);
+        assert_eq!(results[0].song_id, 1);
+        assert!((results[0].offset_seconds - 3.0).abs() < 0.1);
+
+        // El emparejador rechaza una consulta declarada con otro esquema aunque sus hashes
+        // coincidan.
+        let mismatched = MatchConfig {
+            hash_scheme: other,
+            ..MatchConfig::default()
+        };
+        assert!(match_query_with(&hashes, &db, &mismatched).is_empty());
+
+        // Las consultas del otro esquema no dan coincidencias, y la comprobación lo detecta.
+        let other = FingerprintConfig {
+            hash_scheme: other,
+            ..FingerprintConfig::default()
+        };
+        assert!(matches!(
+            db.check_hash_scheme(&other),
+            Err(SonoraError::InvalidConfig(_))
+        ));
+        let hashes = generate_hashes(&query, &other).unwrap();
+        assert!(match_query_with(&hashes, &db, &match_config).is_empty());
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    // Cualquier hasher determinista sirve para experimentar fuera de `HashScheme`.
+    let config = FingerprintConfig::default();
+    let sip = TupleHash::<DefaultHasher>::new(&config);
+    let mut db = FingerprintDb::new();
+    db.insert(1, &generate_hashes_with(&song, &config, &sip).unwrap());
+    let results = match_query(&generate_hashes_with(&query, &config, &sip).unwrap(), &db);
+    assert_eq!(results[0].song_id, 1);
+    assert!(match_query(&generate_hashes(&query, &config).unwrap(), &db).is_empty());
+}
+
+#[test]
+fn the_database_header_carries_the_hash_scheme() {
+    let path = std::env::temp_dir().join("sonora_hash_scheme_header.db");
+    FingerprintDb::with_hash_scheme(HashScheme::Fnv1a)
+        .save(&path)
+        .unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..4], b"SNDB");
+    assert_eq!(bytes[4..8], FINGERPRINT_VERSION.to_le_bytes());
+    assert_eq!(bytes[8], 2);
+
+    // En la versión actual el byte es obligatorio.
+    std::fs::write(&path, &bytes[..8]).unwrap();
+    assert!(matches!(
+        FingerprintDb::load(&path),
+        Err(SonoraError::InvalidDatabase)
+    ));
+
+    // La versión anterior lo guardaba al final y podía faltar: vacía y sin él es `Packed`.
+    let mut previous = b"SNDB".to_vec();
+    previous.extend_from_slice(&(FINGERPRINT_VERSION - 1).to_le_bytes());
+    previous.extend_from_slice(&[0; 16]);
+    std::fs::write(&path, &previous).unwrap();
+    assert_eq!(
+        FingerprintDb::load(&path).unwrap().hash_scheme(),
+        HashScheme::Packed
+    );
+    previous.push(2);
+    std::fs::write(&path, &previous).unwrap();
+    assert_eq!(
+        FingerprintDb::load(&path).unwrap().hash_scheme(),
+        HashScheme::Fnv1a
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn anchor_stride_two_halves_the_hashes_and_still_self_matches() {
+    // Un pico cada 50 ms con frecuencia e intensidad pseudoaleatorias.
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let track: Vec<Peak> = (0..400)
+        .map(|i| Peak {
+            time: i as f32 * 0.05,
+            frequency: 200.0 + (next() % 2800) as f32,
+            magnitude: (next() % 1000) as f32,
+        })
+        .collect();
+
+    let all = generate_hashes(&track, &FingerprintConfig::default()).unwrap();
+    let config = FingerprintConfig {
+        anchor_stride: 2,
+        ..FingerprintConfig::default()
+    };
+    let strided = generate_hashes(&track, &config).unwrap();
+    let ratio = strided.len() as f32 / all.len() as f32;
+    assert!((0.4..0.6).contains(&ratio), "{ratio}");
+
+    let mut db = FingerprintDb::new();
+    db.insert(1, &strided);
+    let results = match_query(&strided, &db);
+    assert_eq!(results[0].song_id, 1);
+    assert!(results[0].offset_seconds.abs() < 0.05);
+    assert_eq!(results[0].score, strided.len());
+}
@@ -0,0 +1,125 @@
+//! Flujos HTTP: redirecciones, cuerpos con longitud o troceados, y reconexión tras un corte.
+
+mod common;
+
+use std::f32::consts::PI;
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use sonora::{decode_reader, AudioSource, HttpSource, Reconnect, SonoraError};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// WAV mono de 16 bits con una escala de senos, una nota cada cuarto de segundo.
+fn wav_bytes(seconds: usize) -> Vec<u8> {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let note = 440.0 * 2f32.powf((n * 4 / SAMPLE_RATE as usize % 12) as f32 / 12.0);
+            let sample = (2.0 * PI * note * n as f32 / SAMPLE_RATE as f32).sin();
+            (sample * 16000.0) as i16
+        })
+        .collect();
+    common::encode_wav(1, SAMPLE_RATE, &samples)
+}
+
+/// Servidor local que contesta cada conexión con la siguiente respuesta y la cierra; agotadas
+/// las respuestas deja de escuchar.
+///
+/// # Retorna
+///
+/// * `String` - URL base del servidor, sin barra final.
+fn serve(responses: Vec<Vec<u8>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            // El cliente puede cerrar antes de leerlo todo.
+            let _ = reader.get_mut().write_all(&response);
+        }
+    });
+    url
+}
+
+fn read_all(source: &mut impl AudioSource) -> (Vec<f32>, Result<(), SonoraError>) {
+    let (mut all, mut block) = (Vec::new(), vec![0.0; 1000]);
+    loop {
+        match source.read_frames(&mut block) {
+            Ok(0) => return (all, Ok(())),
+            Ok(read) => all.extend_from_slice(&block[..read]),
+            Err(e) => return (all, Err(e)),
+        }
+    }
+}
+
+#[test]
+fn complete_responses_end_the_stream() {
+    let wav = wav_bytes(2);
+    let expected = decode_reader(Cursor::new(wav.clone())).unwrap().samples;
+
+    let mut sized = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nContent-Length: {}\r\n\r\n",
+        wav.len()
+    )
+    .into_bytes();
+    sized.extend_from_slice(&wav);
+    let mut chunked = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+    for chunk in wav.chunks(5000) {
+        chunked.extend(format!("{:x}\r\n", chunk.len()).into_bytes());
+        chunked.extend_from_slice(chunk);
+        chunked.extend_from_slice(b"\r\n");
+    }
+    chunked.extend_from_slice(b"0\r\n\r\n");
+    let url = serve(vec![
+        b"HTTP/1.1 302 Found\r\nLocation: /radio\r\n\r\n".to_vec(),
+        sized,
+        chunked,
+    ]);
+
+    for path in ["/", "/radio.wav"] {
+        let mut source = HttpSource::open(&format!("{}{}", url, path)).unwrap();
+        assert_eq!((source.channels(), source.sample_rate()), (1, SAMPLE_RATE));
+        let (samples, end) = read_all(&mut source);
+        end.unwrap();
+        assert_eq!(samples, expected);
+        assert_eq!(source.reconnects(), 0);
+    }
+}
+
+#[test]
+fn dropped_streams_reconnect_until_the_attempts_run_out() {
+    let wav = wav_bytes(1);
+    let expected = decode_reader(Cursor::new(wav.clone())).unwrap().samples;
+
+    // Un flujo en directo no sabe cuánto va a durar: declara el tamaño máximo.
+    let mut unbounded = wav;
+    unbounded[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+    unbounded[40..44].copy_from_slice(&(u32::MAX - 36).to_le_bytes());
+    let mut live = b"ICY 200 OK\r\ncontent-type: audio/wav\r\n\r\n".to_vec();
+    live.extend_from_slice(&unbounded);
+    let url = serve(vec![live.clone(), live]);
+
+    let reconnect = Reconnect {
+        max_attempts: Some(2),
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(20),
+    };
+    let mut source = HttpSource::with_reconnect(&format!("{}/live", url), reconnect).unwrap();
+    let (samples, end) = read_all(&mut source);
+    // Cada conexión vuelve a empezar el flujo; el servidor ya no escucha tras la segunda.
+    assert_eq!(samples, [expected.clone(), expected].concat());
+    assert_eq!(source.reconnects(), 1);
+    assert!(matches!(end, Err(SonoraError::Io(_))));
+
+    assert!(matches!(
+        HttpSource::open("https://example.com/radio.mp3"),
+        Err(SonoraError::Http(_))
+    ));
+}
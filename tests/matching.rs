@@ -0,0 +1,231 @@
+//! Emparejamiento de consultas contra una base de datos de huellas.
+
+use std::f32::consts::PI;
+
+use sonora::{
+    band_fingerprints, fingerprint_source, match_query_with, Engine, FingerprintConfig,
+    FingerprintDb, HashEntry, MatchConfig, MatchResult, MemorySource, SonoraError,
+    FINGERPRINT_VERSION,
+};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// Melodía de notas con cuatro armónicos elegidas al azar con `seed`, una cada cuarto de
+/// segundo, para que ni la señal ni su inversa se repitan.
+fn melody(seed: u64, seconds: usize) -> Vec<f32> {
+    let mut state = seed;
+    let notes: Vec<f32> = (0..seconds * 4)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            220.0 * 2f32.powf((state % 24) as f32 / 12.0)
+        })
+        .collect();
+    (0..SAMPLE_RATE as usize * seconds)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            let note = notes[n * 4 / SAMPLE_RATE as usize];
+            (1..=4)
+                .map(|h| (2.0 * PI * note * h as f32 * t).sin() / (4 * h) as f32)
+                .sum::<f32>()
+        })
+        .collect()
+}
+
+/// Hashes por bandas de `samples`.
+fn band_hashes(samples: Vec<f32>) -> Vec<HashEntry> {
+    let mut hashes = Vec::new();
+    let source = MemorySource::new(samples, 1, SAMPLE_RATE);
+    band_fingerprints(
+        source,
+        &FingerprintConfig::default(),
+        Engine::Sequential,
+        &mut hashes,
+    )
+    .unwrap();
+    hashes
+}
+
+fn candidate(song_id: u32, score: usize, confidence: f32) -> MatchResult {
+    MatchResult {
+        song_id,
+        score,
+        confidence,
+        alignment_quality: 1.0,
+        offset_seconds: 1.5,
+        segments: Vec::new(),
+        histogram: None,
+        reversed: false,
+    }
+}
+
+#[test]
+fn results_order_by_score_and_check_a_threshold() {
+    let mut results = [
+        candidate(1, 12, 0.3),
+        candidate(2, 40, 0.8),
+        candidate(3, 7, 0.1),
+    ];
+    results.sort_by(|a, b| b.partial_cmp(a).unwrap());
+    let ids: Vec<u32> = results.iter().map(|result| result.song_id).collect();
+    assert_eq!(ids, [2, 1, 3]);
+    let best = results
+        .iter()
+        .max_by(|a, b| a.partial_cmp(b).unwrap())
+        .unwrap();
+    assert_eq!(best.song_id, 2);
+    assert!(candidate(4, 40, 0.1) < candidate(5, 41, 0.0));
+
+    assert!(best.is_match(0.8));
+    assert!(!results[1].is_match(0.5));
+    assert_eq!(
+        best.to_string(),
+        "canción 2\tpuntuación 40\tconfianza 0.800\tdesplazamiento 1.50 s"
+    );
+}
+
+#[test]
+fn results_sort_and_max_by_score_then_lowest_song_id() {
+    let mut results = [
+        candidate(7, 12, 0.3),
+        candidate(3, 40, 0.8),
+        candidate(9, 40, 0.1),
+        candidate(1, 7, 0.9),
+    ];
+    assert_eq!(results.iter().max().map(|result| result.song_id), Some(3));
+
+    results.sort();
+    let order: Vec<(u32, usize)> = results.iter().map(|r| (r.song_id, r.score)).collect();
+    assert_eq!(order, [(1, 7), (7, 12), (9, 40), (3, 40)]);
+
+    // La igualdad es coherente con el orden: solo cuentan la canción y la puntuación.
+    assert_eq!(candidate(3, 40, 0.8), candidate(3, 40, 0.2));
+    assert_ne!(candidate(3, 40, 0.8), candidate(9, 40, 0.8));
+}
+
+#[test]
+fn reversed_copies_match_only_under_match_reversed() {
+    let mut source = melody(0x9e37_79b9_7f4a_7c15, 8);
+    // Con un número entero de saltos, los bloques de la copia invertida son los del original.
+    let hop = FingerprintConfig::default().chunk_hop;
+    source.truncate(source.len() / hop * hop);
+    let mut db = FingerprintDb::new();
+    db.insert(1, &band_hashes(source.clone()));
+    let reversed = band_hashes(source.into_iter().rev().collect());
+
+    let config = MatchConfig {
+        min_confidence: 0.5,
+        ..MatchConfig::default()
+    };
+    assert!(match_query_with(&reversed, &db, &config).is_empty());
+
+    let config = MatchConfig {
+        match_reversed: true,
+        ..config
+    };
+    let results = match_query_with(&reversed, &db, &config);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].song_id, 1);
+    assert!(results[0].reversed);
+    assert!(results[0].to_string().ends_with("\tinvertida"));
+}
+
+#[test]
+fn a_query_with_a_dropped_second_still_matches() {
+    let landmarks = |samples: Vec<f32>| {
+        let source = MemorySource::new(samples, 1, SAMPLE_RATE);
+        fingerprint_source(source, &FingerprintConfig::default()).unwrap()
+    };
+    let song = melody(0x2545_f491_4f6c_dd1d, 12);
+    let mut db = FingerprintDb::new();
+    db.insert(1, &landmarks(song.clone()));
+    // De 2 a 5 s y de 6 a 9 s: falta el segundo del medio.
+    let second = SAMPLE_RATE as usize;
+    let query: Vec<f32> = song[2 * second..5 * second]
+        .iter()
+        .chain(&song[6 * second..9 * second])
+        .copied()
+        .collect();
+    let query = landmarks(query);
+
+    let single = match_query_with(&query, &db, &MatchConfig::default());
+    let config = MatchConfig {
+        max_offset_jump: Some(1.5),
+        ..MatchConfig::default()
+    };
+    let aligned = match_query_with(&query, &db, &config);
+    assert_eq!(aligned[0].song_id, 1);
+    assert!(aligned[0].score > single[0].score);
+    // Los segmentos recorren la consulta con el desplazamiento de cada mitad.
+    let segments = &aligned[0].segments;
+    assert!(
+        (segments[0].offset_seconds - 2.0).abs() < 0.1,
+        "{segments:?}"
+    );
+    let last = segments.last().unwrap();
+    assert!((last.offset_seconds - 3.0).abs() < 0.1, "{segments:?}");
+    assert!(last.query_start > 2.5);
+}
+
+#[test]
+fn clean_matches_have_a_higher_alignment_quality() {
+    let entry = |hash, time| HashEntry { hash, time };
+    let query: Vec<HashEntry> = (0..40).map(|i| entry(i, i as f32 * 0.1)).collect();
+    let mut db = FingerprintDb::new();
+    // La canción 1 contiene la consulta entera 3 s más adelante.
+    let clean: Vec<HashEntry> = (0..20).map(|i| entry(i, 3.0 + i as f32 * 0.1)).collect();
+    db.insert(1, &clean);
+    // La canción 2 comparte tantos hashes, pero solo 6 caen en el mismo desplazamiento.
+    let coincidental: Vec<HashEntry> = (20..40)
+        .map(|i| match i {
+            20..=25 => entry(i, 1.0 + i as f32 * 0.1),
+            _ => entry(i, i as f32 * 0.7),
+        })
+        .collect();
+    db.insert(2, &coincidental);
+
+    let config = MatchConfig {
+        min_score: 1,
+        ..MatchConfig::default()
+    };
+    let results = match_query_with(&query, &db, &config);
+    assert_eq!(results.len(), 2);
+    assert_eq!((results[0].song_id, results[0].score), (1, 20));
+    assert_eq!(results[0].alignment_quality, 1.0);
+    assert_eq!((results[1].song_id, results[1].score), (2, 6));
+    assert!(results[1].alignment_quality < 0.5);
+}
+
+#[test]
+fn fingerprints_of_another_version_are_refused_loudly() {
+    let song = melody(0x2545_f491_4f6c_dd1d, 6);
+    let mut db = FingerprintDb::new();
+    db.insert(1, &band_hashes(song.clone()));
+    let path = std::env::temp_dir().join(format!("sonora_version_{}.db", std::process::id()));
+    db.save(&path).unwrap();
+
+    // Una base de datos de otra versión no se carga, ni siquiera con hashes válidos.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[4..8].copy_from_slice(&(FINGERPRINT_VERSION + 1).to_le_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+    let loaded = FingerprintDb::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        loaded,
+        Err(SonoraError::VersionMismatch { found, expected: FINGERPRINT_VERSION })
+            if found == FINGERPRINT_VERSION + 1
+    ));
+
+    // Y una consulta de otra versión no se empareja aunque sus hashes coincidan.
+    let query = band_hashes(song);
+    assert_eq!(
+        match_query_with(&query, &db, &MatchConfig::default())[0].song_id,
+        1
+    );
+    let config = MatchConfig {
+        version: FINGERPRINT_VERSION - 1,
+        ..MatchConfig::default()
+    };
+    assert!(match_query_with(&query, &db, &config).is_empty());
+}
@@ -0,0 +1,239 @@
+//! Detección de picos sobre señales sintéticas con un espectro conocido, y detectores propios.
+
+use std::f32::consts::PI;
+
+use sonora::{
+    calculate_spectrogram, find_peaks, fingerprint_from_spectrogram,
+    fingerprint_from_spectrogram_with, harmonic_product_spectrum, FingerprintConfig, Fingerprinter,
+    MemorySource, Peak, PeakDetector, Spectrogram, CANONICAL_SAMPLE_RATE,
+};
+
+const SAMPLE_RATE: usize = CANONICAL_SAMPLE_RATE as usize;
+
+/// Suma de senos de amplitud `amplitude` durante `seconds` segundos.
+fn tones(frequencies: &[f32], amplitude: f32, seconds: f32) -> Vec<f32> {
+    let len = (seconds * SAMPLE_RATE as f32) as usize;
+    (0..len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            frequencies
+                .iter()
+                .map(|f| amplitude * (2.0 * PI * f * t).sin())
+                .sum()
+        })
+        .collect()
+}
+
+/// Frecuencia del bin de la FFT más cercano a `frequency`.
+fn nearest_bin(frequency: f32, config: &FingerprintConfig) -> f32 {
+    let resolution = SAMPLE_RATE as f32 / config.window_size as f32;
+    (frequency / resolution).round() * resolution
+}
+
+#[test]
+fn two_tones_peak_at_their_nearest_bins_only() {
+    let config = FingerprintConfig::default();
+    let samples = tones(&[1000.0, 4000.0], 0.5, 2.0);
+
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+    let peaks = find_peaks(&spectrogram, &config);
+
+    let expected = [nearest_bin(1000.0, &config), nearest_bin(4000.0, &config)];
+    assert!(!peaks.is_empty());
+    for peak in &peaks {
+        assert!(
+            expected.contains(&peak.frequency),
+            "pico inesperado en {} Hz (t = {} s)",
+            peak.frequency,
+            peak.time
+        );
+    }
+    for frequency in expected {
+        assert!(
+            peaks.iter().any(|peak| peak.frequency == frequency),
+            "falta el pico en {frequency} Hz"
+        );
+    }
+}
+
+#[test]
+fn silence_has_no_peaks() {
+    let config = FingerprintConfig::default();
+    let samples = vec![0.0; 2 * SAMPLE_RATE];
+
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+
+    assert!(!spectrogram.times.is_empty());
+    assert!(find_peaks(&spectrogram, &config).is_empty());
+}
+
+#[test]
+fn impulse_has_a_flat_spectrum() {
+    let config = FingerprintConfig::default();
+    let hop_size = config.window_size - config.overlap;
+    // El impulso cae en el centro de la ventana `frame`, donde la ventana de Hann vale 1.
+    let frame = 7;
+    let position = frame * hop_size + config.window_size / 2;
+    let mut samples = vec![0.0; 2 * SAMPLE_RATE];
+    samples[position] = 10.0;
+
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+
+    let spectrum = &spectrogram.magnitudes[frame];
+    let min = spectrum.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = spectrum.iter().copied().fold(0.0, f32::max);
+    assert!((max - 10.0).abs() < 1e-3, "máximo {max}");
+    assert!(min / max > 0.999, "espectro no plano: {min}..{max}");
+
+    // Las ventanas que no contienen el impulso (o lo tienen en un extremo) quedan a 0.
+    for (i, magnitudes) in spectrogram.magnitudes.iter().enumerate() {
+        if i != frame {
+            assert!(magnitudes.iter().all(|&m| m < 1e-3), "ventana {i}");
+        }
+    }
+
+    let peaks = find_peaks(&spectrogram, &config);
+    assert!(!peaks.is_empty());
+    assert!(peaks
+        .iter()
+        .all(|peak| peak.time == spectrogram.times[frame]));
+}
+
+#[test]
+fn a_hand_built_spectrogram_is_fingerprinted() {
+    let config = FingerprintConfig::default();
+    let (frames, bins, hop) = (
+        80,
+        config.window_size / 2,
+        config.window_size - config.overlap,
+    );
+    let resolution = SAMPLE_RATE as f32 / config.window_size as f32;
+    // Tres picos aislados sobre un fondo en silencio, sin pasar por ninguna FFT.
+    let spikes = [(5, 30), (30, 80), (55, 150)];
+    let mut magnitudes = vec![vec![0.0; bins]; frames];
+    for &(frame, bin) in &spikes {
+        magnitudes[frame][bin] = 10.0;
+    }
+    let spectrogram = Spectrogram {
+        frequencies: (0..bins).map(|bin| bin as f32 * resolution).collect(),
+        times: (0..frames)
+            .map(|frame| (frame * hop) as f32 / SAMPLE_RATE as f32)
+            .collect(),
+        magnitudes,
+        sample_rate: SAMPLE_RATE,
+        window_size: config.window_size,
+        hop_size: hop,
+    };
+
+    let peaks = find_peaks(&spectrogram, &config);
+    let found: Vec<(f32, f32)> = peaks
+        .iter()
+        .map(|peak| (peak.time, peak.frequency))
+        .collect();
+    let expected: Vec<(f32, f32)> = spikes
+        .iter()
+        .map(|&(frame, bin)| (spectrogram.times[frame], spectrogram.frequencies[bin]))
+        .collect();
+    assert_eq!(found, expected);
+
+    // Cada pico se empareja con los siguientes: 2 + 1 hashes, anclados en los dos primeros.
+    let hashes = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+    let times: Vec<f32> = hashes.iter().map(|entry| entry.time).collect();
+    assert_eq!(times, [expected[0].0, expected[0].0, expected[1].0]);
+}
+
+/// Detector mínimo: el bin más fuerte de cada ventana.
+struct LoudestBin;
+
+impl PeakDetector for LoudestBin {
+    fn detect(&self, spectrogram: &Spectrogram, _config: &FingerprintConfig) -> Vec<Peak> {
+        spectrogram
+            .magnitudes
+            .iter()
+            .zip(&spectrogram.times)
+            .filter_map(|(row, &time)| {
+                let (bin, &magnitude) = row.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+                Some(Peak {
+                    time,
+                    frequency: spectrogram.frequencies[bin],
+                    magnitude,
+                })
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn custom_detector_replaces_the_neighborhood_search() {
+    let config = FingerprintConfig::default();
+    let samples = tones(&[1000.0], 0.5, 2.0);
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+
+    let default = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+    let custom = fingerprint_from_spectrogram_with(&spectrogram, &config, &LoudestBin).unwrap();
+    assert!(!custom.is_empty());
+    assert_ne!(default.len(), custom.len());
+
+    let fingerprinter = Fingerprinter::builder()
+        .peak_detector(LoudestBin)
+        .build()
+        .unwrap();
+    let from_source = fingerprinter
+        .fingerprint_source(MemorySource::new(samples, 1, CANONICAL_SAMPLE_RATE))
+        .unwrap();
+    assert_eq!(from_source.len(), custom.len());
+}
+
+#[test]
+fn hps_reinforces_the_fundamental_over_inharmonic_noise() {
+    let config = FingerprintConfig::default();
+    let resolution = SAMPLE_RATE as f32 / config.window_size as f32;
+    // Fundamental en el bin 20 con sus armónicos 2, 3 y 4, y un tono inarmónico igual de
+    // fuerte en el bin 67, cuyos múltiplos no coinciden con nada.
+    let bins = [20.0, 40.0, 60.0, 80.0, 67.0];
+    let frequencies: Vec<f32> = bins.iter().map(|bin| bin * resolution).collect();
+    let samples = tones(&frequencies, 0.1, 2.0);
+    let mut spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+    let frame = spectrogram.magnitudes.len() / 2;
+    let ratio = |spectrogram: &Spectrogram| {
+        spectrogram.magnitudes[frame][20] / spectrogram.magnitudes[frame][67]
+    };
+
+    let before = ratio(&spectrogram);
+    assert!((before - 1.0).abs() < 0.1, "{before}");
+    harmonic_product_spectrum(&mut spectrogram, 3);
+    let after = ratio(&spectrogram);
+    assert!(after > 10.0 * before, "{before} -> {after}");
+}
+
+#[test]
+fn the_novelty_gate_keeps_sweeps_and_drops_steady_tones() {
+    let seconds = 4.0;
+    let steady = tones(&[1000.0], 0.5, seconds);
+    // Barrido lineal de 200 Hz a 4 kHz: el espectro cambia en cada ventana.
+    let (start, end) = (200.0, 4000.0);
+    let rate = (end - start) / seconds;
+    let sweep: Vec<f32> = (0..steady.len())
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            0.5 * (2.0 * PI * (start * t + rate * t * t / 2.0)).sin()
+        })
+        .collect();
+
+    let hashes = |samples: &[f32], novelty_threshold: f32| {
+        let config = FingerprintConfig {
+            novelty_threshold,
+            ..FingerprintConfig::default()
+        };
+        let spectrogram = calculate_spectrogram(samples, SAMPLE_RATE, &config);
+        fingerprint_from_spectrogram(&spectrogram, &config)
+            .unwrap()
+            .len()
+    };
+
+    // Sin puerta, el tono sostenido también genera algún hash; con ella, ninguno o casi.
+    let ungated = hashes(&steady, 0.0);
+    let (steady, sweep) = (hashes(&steady, 0.1), hashes(&sweep, 0.1));
+    assert!(steady < ungated, "tono {steady} de {ungated}");
+    assert!(sweep > 10 * steady.max(1), "tono {steady}, barrido {sweep}");
+}
@@ -0,0 +1,44 @@
+//! Ida y vuelta por JSON de los tipos intermedios, con la feature `serde`.
+#![cfg(feature = "serde")]
+
+use std::f32::consts::PI;
+
+use sonora::{
+    calculate_spectrogram, find_peaks, generate_hashes, FingerprintConfig, HashEntry, Peak,
+    Spectrogram, CANONICAL_SAMPLE_RATE,
+};
+
+#[test]
+fn intermediate_types_round_trip_through_json() {
+    let config = FingerprintConfig::default();
+    let rate = CANONICAL_SAMPLE_RATE as usize;
+    let samples: Vec<f32> = (0..rate * 2)
+        .map(|n| {
+            let t = n as f32 / rate as f32;
+            (-2.0 * (t % 0.5)).exp() * (2.0 * PI * 880.0 * t).sin()
+        })
+        .collect();
+
+    let spectrogram = calculate_spectrogram(&samples, rate, &config);
+    let json = serde_json::to_string(&spectrogram).unwrap();
+    let restored: Spectrogram = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.magnitudes, spectrogram.magnitudes);
+    assert_eq!(restored.times, spectrogram.times);
+    assert_eq!(restored.hop_size, spectrogram.hop_size);
+
+    let peaks = find_peaks(&restored, &config);
+    assert!(!peaks.is_empty());
+    let restored_peaks: Vec<Peak> =
+        serde_json::from_str(&serde_json::to_string(&peaks).unwrap()).unwrap();
+    let hashes = generate_hashes(&restored_peaks, &config).unwrap();
+    assert_eq!(
+        hashes.len(),
+        generate_hashes(&peaks, &config).unwrap().len()
+    );
+
+    let restored_hashes: Vec<HashEntry> =
+        serde_json::from_str(&serde_json::to_string(&hashes).unwrap()).unwrap();
+    for (a, b) in hashes.iter().zip(&restored_hashes) {
+        assert_eq!((a.hash, a.time), (b.hash, b.time));
+    }
+}
@@ -0,0 +1,182 @@
+//! Espectrograma con distintos tipos de muestra y precisiones de la FFT.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use num_complex::Complex;
+use sonora::{
+    cached_window, calculate_spectrogram, calculate_spectrogram_with, hann_window, window,
+    FingerprintConfig, RustFftTransform, Transform, WindowFn, CANONICAL_SAMPLE_RATE,
+};
+
+const SAMPLE_RATE: usize = CANONICAL_SAMPLE_RATE as usize;
+
+/// Tono de 1 kHz a media amplitud durante un segundo, cuantizado a 16 bits.
+fn tone_i16() -> Vec<i16> {
+    (0..SAMPLE_RATE)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            (16384.0 * (2.0 * PI * 1000.0 * t).sin()).round() as i16
+        })
+        .collect()
+}
+
+#[test]
+fn integer_samples_match_their_scaled_floats() {
+    let config = FingerprintConfig::default();
+    let ints = tone_i16();
+    let floats: Vec<f32> = ints.iter().map(|&s| s as f32 / 32768.0).collect();
+
+    let from_ints = calculate_spectrogram(&ints, SAMPLE_RATE, &config);
+    let from_floats = calculate_spectrogram(&floats, SAMPLE_RATE, &config);
+    assert_eq!(from_ints.magnitudes, from_floats.magnitudes);
+    assert_eq!(from_ints.times, from_floats.times);
+}
+
+#[test]
+fn f64_precision_agrees_with_f32() {
+    let config = FingerprintConfig {
+        remove_dc: true,
+        ..FingerprintConfig::default()
+    };
+    let samples = tone_i16();
+
+    let single = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+    let double = calculate_spectrogram_with(
+        &samples,
+        SAMPLE_RATE,
+        &config,
+        &RustFftTransform::<f64>::with_precision(config.window_size),
+    );
+
+    assert_eq!(single.magnitudes.len(), double.magnitudes.len());
+    assert_eq!(single.frequencies, double.frequencies);
+    let peak = single.magnitudes[0].iter().cloned().fold(0.0f32, f32::max);
+    for (a, b) in single.magnitudes.iter().zip(&double.magnitudes) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= peak * 1e-5, "{x} frente a {y}");
+        }
+    }
+}
+
+#[test]
+fn cached_windows_are_shared_per_kind_and_size() {
+    let hann = cached_window(WindowFn::Hann, 1024);
+    assert!(Arc::ptr_eq(&hann, &cached_window(WindowFn::Hann, 1024)));
+    assert_eq!(*hann, window(WindowFn::Hann, 1024));
+
+    // El tipo forma parte de la clave: otra ventana del mismo tamaño no reutiliza la de Hann.
+    let hamming = cached_window(WindowFn::Hamming, 1024);
+    assert!(!Arc::ptr_eq(&hann, &hamming));
+    assert_ne!(hann, hamming);
+    assert_eq!(*hamming, window(WindowFn::Hamming, 1024));
+    assert_eq!(cached_window(WindowFn::Hann, 512).len(), 512);
+}
+
+#[test]
+fn without_overlap_the_windows_are_contiguous() {
+    let config = FingerprintConfig {
+        window_size: 1000,
+        overlap: 0,
+        ..FingerprintConfig::default()
+    };
+    let samples = tone_i16();
+    assert_ne!(samples.len() % config.window_size, 0);
+
+    let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+    assert_eq!(
+        spectrogram.magnitudes.len(),
+        samples.len() / config.window_size
+    );
+    assert_eq!(spectrogram.times.len(), spectrogram.magnitudes.len());
+}
+
+/// DFT directa de coste cuadrático, como referencia de [`Transform`].
+struct NaiveDft;
+
+impl Transform for NaiveDft {
+    fn forward(&self, buffer: &mut [Complex<f32>]) {
+        let n = buffer.len();
+        let input = buffer.to_vec();
+        for (k, output) in buffer.iter_mut().enumerate() {
+            *output = input
+                .iter()
+                .enumerate()
+                .map(|(t, &x)| {
+                    let angle = -2.0 * std::f64::consts::PI * (k * t % n) as f64 / n as f64;
+                    x * Complex::new(angle.cos() as f32, angle.sin() as f32)
+                })
+                .sum();
+        }
+    }
+}
+
+#[test]
+fn a_naive_dft_gives_the_same_magnitudes() {
+    let config = FingerprintConfig {
+        window_size: 64,
+        overlap: 32,
+        ..FingerprintConfig::default()
+    };
+    let samples = &tone_i16()[..SAMPLE_RATE / 10];
+
+    let fft = calculate_spectrogram(samples, SAMPLE_RATE, &config);
+    let dft = calculate_spectrogram_with(samples, SAMPLE_RATE, &config, &NaiveDft);
+    assert_eq!(fft.times, dft.times);
+    assert_eq!(fft.magnitudes.len(), dft.magnitudes.len());
+    let peak = fft.magnitudes[1].iter().cloned().fold(0.0f32, f32::max);
+    assert!(peak > 1.0);
+    for (a, b) in fft.magnitudes.iter().zip(&dft.magnitudes) {
+        for (x, y) in a.iter().zip(b) {
+            assert!((x - y).abs() <= peak * 1e-4, "{x} frente a {y}");
+        }
+    }
+}
+
+#[test]
+fn hann_keeps_the_original_coefficients() {
+    // Fórmula de la ventana de Hann anterior a `WindowFn`, calculada en `f32`.
+    let original = |size: usize| -> Vec<f32> {
+        (0..size)
+            .map(|i| 0.5 * (1.0 - (2.0 * PI * i as f32 / size as f32).cos()))
+            .collect()
+    };
+    assert_eq!(FingerprintConfig::default().window_fn, WindowFn::Hann);
+    for size in [64, 1024, 4096] {
+        assert_eq!(window(WindowFn::Hann, size), original(size));
+        assert_eq!(hann_window(size), original(size));
+    }
+}
+
+#[test]
+fn reported_resolutions_follow_the_construction_parameters() {
+    let samples: Vec<f32> = tone_i16().iter().map(|&s| s as f32 / 32768.0).collect();
+    for (window_size, overlap) in [(1024, 512), (2048, 1536), (512, 0)] {
+        let config = FingerprintConfig {
+            window_size,
+            overlap,
+            ..FingerprintConfig::default()
+        };
+        let spectrogram = calculate_spectrogram(&samples, SAMPLE_RATE, &config);
+        let hop = window_size - overlap;
+        assert_eq!(
+            (
+                spectrogram.sample_rate,
+                spectrogram.window_size,
+                spectrogram.hop_size
+            ),
+            (SAMPLE_RATE, window_size, hop)
+        );
+        assert_eq!(spectrogram.frame_rate(), SAMPLE_RATE as f32 / hop as f32);
+        assert_eq!(
+            spectrogram.freq_resolution(),
+            SAMPLE_RATE as f32 / window_size as f32
+        );
+
+        // Y coinciden con la separación de los ejes que acompañan a las magnitudes.
+        let step = spectrogram.times[1] - spectrogram.times[0];
+        assert!((step * spectrogram.frame_rate() - 1.0).abs() < 1e-4);
+        let bin = spectrogram.frequencies[1] - spectrogram.frequencies[0];
+        assert!((bin - spectrogram.freq_resolution()).abs() < 1e-4);
+    }
+}
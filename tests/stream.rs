@@ -0,0 +1,159 @@
+//! Huellas perezosas de un archivo frente al pipeline completo.
+
+mod common;
+
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+use sonora::{
+    calculate_spectrogram, fingerprint_file, fingerprint_file_stream, fingerprint_from_spectrogram,
+    stream_fingerprints, FingerprintConfig, HashEntry, IncrementalFingerprinter, SonoraError,
+    CANONICAL_SAMPLE_RATE,
+};
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// Escribe un WAV estéreo de 16 bits con dos escalas de senos a casi fondo de escala, para que
+/// la normalización de `fingerprint_file` apenas cambie la señal.
+fn write_wav(path: &std::path::Path, seconds: usize) {
+    let samples: Vec<i16> = (0..SAMPLE_RATE as usize * seconds)
+        .flat_map(|n| {
+            let t = n as f32 / SAMPLE_RATE as f32;
+            // Dos voces que cambian de nota a ritmos distintos, para que haya picos en todo el
+            // audio.
+            let low = 220.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 4) % 12) as f32 / 12.0);
+            let high = 1760.0 * 2f32.powf((n / (SAMPLE_RATE as usize / 3) % 7) as f32 / 12.0);
+            let sample = 0.6 * (2.0 * PI * low * t).sin() + 0.4 * (2.0 * PI * high * t).sin();
+            [(sample * 32767.0) as i16; 2]
+        })
+        .collect();
+    common::write_wav(path, 2, SAMPLE_RATE, &samples).unwrap();
+}
+
+#[test]
+fn file_stream_matches_the_batch_pipeline() {
+    let path = std::env::temp_dir().join("sonora_file_stream_test.wav");
+    write_wav(&path, 10);
+    let path_str = path.to_str().unwrap();
+    let config = FingerprintConfig::default();
+
+    let batch = fingerprint_file(path_str, &config).unwrap();
+    let streamed: Vec<_> = fingerprint_file_stream(path_str, &config)
+        .unwrap()
+        .collect();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(streamed.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    let key = |time: f32, hash: u64| ((time * 1000.0).round() as i64, hash);
+    let streamed: HashSet<_> = streamed.iter().map(|e| key(e.time, e.hash)).collect();
+    let shared = batch
+        .iter()
+        .filter(|e| streamed.contains(&key(e.time, e.hash)))
+        .count();
+    assert!(
+        shared * 10 >= batch.len() * 9,
+        "{} de {} hashes compartidos",
+        shared,
+        batch.len()
+    );
+}
+
+#[test]
+fn max_memory_uses_the_streaming_pipeline() {
+    let path = std::env::temp_dir().join("sonora_max_memory_test.wav");
+    write_wav(&path, 10);
+    let path_str = path.to_str().unwrap();
+    let config = FingerprintConfig {
+        max_memory: Some(64 * 1024),
+        ..FingerprintConfig::default()
+    };
+
+    let bounded = fingerprint_file(path_str, &config).unwrap();
+    let streamed: Vec<_> = fingerprint_file_stream(path_str, &FingerprintConfig::default())
+        .unwrap()
+        .collect();
+    let _ = std::fs::remove_file(&path);
+
+    assert!(!bounded.is_empty());
+    assert_eq!(
+        bounded.iter().map(|e| (e.hash, e.time)).collect::<Vec<_>>(),
+        streamed
+            .iter()
+            .map(|e| (e.hash, e.time))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn overlap_as_large_as_the_window_is_an_error() {
+    let path = std::env::temp_dir().join("sonora_bad_overlap_test.wav");
+    write_wav(&path, 1);
+    let path_str = path.to_str().unwrap();
+    let config = FingerprintConfig {
+        overlap: 1024,
+        ..FingerprintConfig::default()
+    };
+
+    let batch = fingerprint_file(path_str, &config);
+    let lazy = fingerprint_file_stream(path_str, &config).map(|stream| stream.count());
+    let bounded = fingerprint_file(
+        path_str,
+        &FingerprintConfig {
+            max_memory: Some(64 * 1024),
+            ..config.clone()
+        },
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(matches!(batch, Err(SonoraError::InvalidConfig(_))));
+    assert!(matches!(lazy, Err(SonoraError::InvalidConfig(_))));
+    assert!(matches!(bounded, Err(SonoraError::InvalidConfig(_))));
+
+    // Los flujos no devuelven errores: terminan sin hashes.
+    let (tx, rx) = crossbeam::channel::unbounded();
+    tx.send(vec![0.5; 22050]).unwrap();
+    drop(tx);
+    assert_eq!(stream_fingerprints(rx, &config).count(), 0);
+}
+
+#[test]
+fn appended_pieces_give_the_hashes_of_the_whole_recording() {
+    let rate = CANONICAL_SAMPLE_RATE;
+    let samples: Vec<f32> = (0..rate as usize * 12)
+        .map(|n| {
+            let t = n as f32 / rate as f32;
+            let low = 220.0 * 2f32.powf((n / (rate as usize / 4) % 12) as f32 / 12.0);
+            let high = 1760.0 * 2f32.powf((n / (rate as usize / 3) % 7) as f32 / 12.0);
+            0.6 * (2.0 * PI * low * t).sin() + 0.4 * (2.0 * PI * high * t).sin()
+        })
+        .collect();
+    let config = FingerprintConfig::default();
+    let spectrogram = calculate_spectrogram(&samples, rate as usize, &config);
+    let whole = fingerprint_from_spectrogram(&spectrogram, &config).unwrap();
+
+    // Trozos de tamaños irregulares, ninguno múltiplo del salto de la ventana.
+    let mut incremental = IncrementalFingerprinter::new(&config, rate).unwrap();
+    let mut pieces: Vec<HashEntry> = Vec::new();
+    let mut rest = samples.as_slice();
+    for size in [700, 13_001, 5, 9_999].into_iter().cycle() {
+        let (piece, tail) = rest.split_at(size.min(rest.len()));
+        let hashes = incremental.push(piece).unwrap();
+        // Cada llamada solo devuelve hashes posteriores a los ya devueltos.
+        if let (Some(last), Some(first)) = (pieces.last(), hashes.first()) {
+            assert!(last.time <= first.time);
+        }
+        pieces.extend(hashes);
+        rest = tail;
+        if rest.is_empty() {
+            break;
+        }
+    }
+    pieces.extend(incremental.finish().unwrap());
+
+    let key = |time: f32, hash: u64| ((time * 1000.0).round() as i64, hash);
+    let mut whole: Vec<_> = whole.iter().map(|e| key(e.time, e.hash)).collect();
+    let mut pieces: Vec<_> = pieces.iter().map(|e| key(e.time, e.hash)).collect();
+    whole.sort_unstable();
+    pieces.sort_unstable();
+    assert!(!whole.is_empty());
+    assert_eq!(pieces, whole);
+}